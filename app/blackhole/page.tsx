@@ -13,6 +13,7 @@ type WasmModule = {
 type Renderer = {
   render(): void
   resize(width: number, height: number): void
+  set_pixel_ratio(ratio: number): void
   on_mouse_move(x: number, y: number): void
   on_mouse_button(button: number, pressed: boolean, x: number, y: number): void
   on_wheel(delta_y: number): void
@@ -114,8 +115,10 @@ export default function BlackHolePage() {
     const handleResize = () => {
       if (canvas && renderer) {
         const { clientWidth, clientHeight } = canvas
-        canvas.width = clientWidth
-        canvas.height = clientHeight
+        const ratio = window.devicePixelRatio || 1
+        canvas.width = Math.round(clientWidth * ratio)
+        canvas.height = Math.round(clientHeight * ratio)
+        renderer.set_pixel_ratio(ratio)
         renderer.resize(clientWidth, clientHeight)
       }
     }
@@ -0,0 +1,114 @@
+/// How a buffer is bound, which decides the device limit it must respect.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BufferKind {
+    Uniform,
+    Storage,
+}
+
+/// Makes sure `buffer` can hold `required` bytes, replacing it with a larger
+/// one if not. Returns `true` when the buffer was reallocated, in which case
+/// every bind group referencing it must be rebuilt.
+///
+/// Fails when the size exceeds the device's binding limit for `kind`, or when
+/// storage buffers are unavailable altogether (as on the WebGL2 backend),
+/// instead of letting `write_buffer` run past the end of the allocation.
+pub fn ensure_capacity(
+    device: &wgpu::Device,
+    buffer: &mut wgpu::Buffer,
+    label: &str,
+    kind: BufferKind,
+    required: u64,
+) -> Result<bool, String> {
+    if required <= buffer.size() {
+        return Ok(false);
+    }
+
+    let limits = device.limits();
+    let (max_size, usage) = match kind {
+        BufferKind::Uniform => (
+            limits.max_uniform_buffer_binding_size as u64,
+            wgpu::BufferUsages::UNIFORM,
+        ),
+        BufferKind::Storage => {
+            if limits.max_storage_buffers_per_shader_stage == 0 {
+                return Err(format!(
+                    "{} needs a storage buffer, which this device does not support",
+                    label
+                ));
+            }
+            (
+                limits.max_storage_buffer_binding_size as u64,
+                wgpu::BufferUsages::STORAGE,
+            )
+        }
+    };
+    let Some(size) = grown_size(buffer.size(), required, max_size, label)? else {
+        return Ok(false);
+    };
+
+    log::info!("Growing {} from {} to {} bytes", label, buffer.size(), size);
+    *buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some(label),
+        size,
+        usage: usage | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    Ok(true)
+}
+
+/// Size to reallocate a `current`-byte buffer to so it holds `required`
+/// bytes, or `None` if it already does. Fails past `max_size`, the device's
+/// binding limit.
+pub fn grown_size(
+    current: u64,
+    required: u64,
+    max_size: u64,
+    label: &str,
+) -> Result<Option<u64>, String> {
+    if required <= current {
+        return Ok(None);
+    }
+    if required > max_size {
+        return Err(format!(
+            "{} needs {} bytes but the device allows at most {}",
+            label, required, max_size
+        ));
+    }
+    // Uniform structs are 16-byte aligned; grow geometrically to avoid
+    // reallocating on every small addition.
+    Ok(Some(required.next_power_of_two().max(16).min(max_size)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn planet_buffer_grows_by_powers_of_two_within_the_limit() {
+        // The planet uniform starts at 16 bytes and grows with its array of
+        // orbit overlay points, 16 bytes each after a 96-byte header
+        let limit = 1 << 16;
+        let required = |points: u64| 96 + 16 * points;
+        assert_eq!(grown_size(16, 16, limit, "Planet Buffer"), Ok(None));
+        assert_eq!(
+            grown_size(16, required(128), limit, "Planet Buffer"),
+            Ok(Some(4096))
+        );
+        assert_eq!(
+            grown_size(4096, required(128), limit, "Planet Buffer"),
+            Ok(None)
+        );
+        assert_eq!(
+            grown_size(4096, required(256), limit, "Planet Buffer"),
+            Ok(Some(8192))
+        );
+        assert_eq!(grown_size(0, 4, limit, "Planet Buffer"), Ok(Some(16)));
+        // Rounding up past the limit is capped at the limit
+        assert_eq!(
+            grown_size(32768, required(3000), limit, "Planet Buffer"),
+            Ok(Some(limit))
+        );
+        let error = grown_size(limit, required(4096), limit, "Planet Buffer").unwrap_err();
+        assert!(error.contains("Planet Buffer"), "{}", error);
+    }
+}
@@ -1,5 +1,164 @@
-use glam::{Mat4, Vec3};
+use crate::physics::DEFAULT_ESCAPE_RADIUS;
+use glam::{Mat3, Mat4, Vec3};
 use std::f32::consts::PI;
+use wasm_bindgen::prelude::*;
+
+/// Closest the camera gets to a pole, so the orbit basis stays well defined.
+pub const POLE_EPSILON: f32 = 1e-3;
+/// Squared length of `forward x up` below which the two are treated as
+/// parallel and the basis falls back to another reference axis.
+const PARALLEL_EPSILON: f32 = 1e-8;
+
+/// Clip planes in Schwarzschild radii used by `fit_clip_planes`. For
+/// Sagittarius A* these land close to the original 1e8 m / 1e13 m.
+const NEAR_PLANE_RS: f64 = 0.01;
+const FAR_PLANE_RS: f64 = 800.0;
+
+/// Limits for the vertical field of view, in degrees.
+pub const MIN_FOV: f32 = 1.0;
+pub const MAX_FOV: f32 = 170.0;
+
+/// A curated starting shot for `BlackHoleRenderer::set_viewpoint`. Angles
+/// are in degrees, with `elevation` measured from the pole as the camera's
+/// is. Disk-relative presets take the pole to be the disk's normal, so they
+/// follow its tilt and precession; the others use world +Y.
+#[derive(Debug, Clone, Copy)]
+pub struct Viewpoint {
+    pub name: &'static str,
+    pub azimuth: f32,
+    pub elevation: f32,
+    pub radius_rs: f32,
+    pub fov: f32,
+    pub disk_relative: bool,
+}
+
+pub const VIEWPOINTS: [Viewpoint; 4] = [
+    // A couple of degrees above the plane, so the lensed far side of the
+    // disk arches over the shadow
+    Viewpoint {
+        name: "edge-on-disk",
+        azimuth: 0.0,
+        elevation: 86.0,
+        radius_rs: 20.0,
+        fov: 45.0,
+        disk_relative: true,
+    },
+    Viewpoint {
+        name: "face-on-disk",
+        azimuth: 0.0,
+        elevation: 0.0,
+        radius_rs: 20.0,
+        fov: 50.0,
+        disk_relative: true,
+    },
+    Viewpoint {
+        name: "polar",
+        azimuth: 0.0,
+        elevation: 0.0,
+        radius_rs: 30.0,
+        fov: 40.0,
+        disk_relative: false,
+    },
+    // Just outside the photon sphere at 1.5 r_s, where the shadow fills
+    // most of the sky
+    Viewpoint {
+        name: "near-photon-sphere",
+        azimuth: 0.0,
+        elevation: 80.0,
+        radius_rs: 2.0,
+        fov: 100.0,
+        disk_relative: true,
+    },
+];
+
+impl Viewpoint {
+    pub fn find(name: &str) -> Option<&'static Viewpoint> {
+        VIEWPOINTS.iter().find(|viewpoint| viewpoint.name == name)
+    }
+
+    /// The camera's `(azimuth, elevation)` in radians for this shot, given
+    /// the rotation from the disk's frame to the world.
+    pub fn orbit_angles(&self, disk_orientation: Mat3, handedness: Handedness) -> (f32, f32) {
+        let (azimuth, elevation) = (self.azimuth.to_radians(), self.elevation.to_radians());
+        if !self.disk_relative {
+            return (azimuth, elevation);
+        }
+        let local = Vec3::new(
+            elevation.sin() * azimuth.cos(),
+            elevation.cos(),
+            elevation.sin() * azimuth.sin(),
+        );
+        // Orbit angles are defined right-handed
+        let dir = handedness.apply(disk_orientation * local);
+        (dir.z.atan2(dir.x), dir.y.clamp(-1.0, 1.0).acos())
+    }
+}
+
+/// `(right, up)` completing an orthonormal basis with unit `forward`, with
+/// `up` as close to `up_reference` as possible. When `forward` is parallel to
+/// `up_reference`, `fallback` is used in its place.
+pub fn orthonormal_basis(forward: Vec3, up_reference: Vec3, fallback: Vec3) -> (Vec3, Vec3) {
+    let mut right = forward.cross(up_reference);
+    if right.length_squared() < PARALLEL_EPSILON {
+        right = forward.cross(fallback);
+    }
+    let right = right.normalize();
+    (right, right.cross(forward).normalize())
+}
+
+/// A running dolly zoom: the camera radius moves from `start_radius` to
+/// `end_radius` while the field of view changes so the half-width of the
+/// view at the target, `radius * tan(fov / 2)`, goes linearly from
+/// `start_width` to `end_width`. With matching end values the hole keeps its
+/// apparent size while the background warps around it.
+#[derive(Debug, Clone, Copy)]
+pub struct DollyZoom {
+    pub start_time: f64,
+    pub duration: f64,
+    pub start_radius: f32,
+    pub end_radius: f32,
+    pub start_width: f32,
+    pub end_width: f32,
+}
+
+/// How primary rays leave the camera.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Projection {
+    Perspective,
+    /// Parallel rays from an image plane `2 * ortho_scale` meters tall.
+    Orthographic,
+}
+
+impl Projection {
+    /// The projection's code in the shader's `camera.projection`.
+    pub fn shader_code(self) -> f32 {
+        match self {
+            Projection::Perspective => 0.0,
+            Projection::Orthographic => 1.0,
+        }
+    }
+}
+
+/// Handedness of the world frame that camera angles are given in.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Handedness {
+    /// x right, y up, z toward the viewer (glam's `_rh` conventions).
+    RightHanded,
+    /// As `RightHanded` with z negated.
+    LeftHanded,
+}
+
+impl Handedness {
+    /// Maps a right-handed world vector into this frame.
+    pub fn apply(self, v: Vec3) -> Vec3 {
+        match self {
+            Handedness::RightHanded => v,
+            Handedness::LeftHanded => Vec3::new(v.x, v.y, -v.z),
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct Camera {
@@ -9,12 +168,31 @@ pub struct Camera {
     pub max_radius: f32,
     pub azimuth: f32,
     pub elevation: f32,
+    pub min_elevation: f32,
+    pub max_elevation: f32,
+    /// Orbit over the poles instead of stopping at the elevation limits.
+    pub allow_pole_crossing: bool,
+    /// Set after crossing a pole an odd number of times; the camera is then
+    /// upside down relative to world Y.
+    pub pole_flipped: bool,
     pub orbit_speed: f32,
     pub zoom_speed: f32,
     pub dragging: bool,
     pub moving: bool,
     pub last_x: f64,
     pub last_y: f64,
+    pub projection: Projection,
+    pub ortho_scale: f32,
+    /// Vertical field of view in degrees for the perspective projection.
+    pub fov: f32,
+    pub dolly: Option<DollyZoom>,
+    /// Clip planes in meters for `projection_matrix`. The ray-traced image
+    /// ignores them; only rasterized overlays are clipped.
+    pub near: f32,
+    pub far: f32,
+    /// Mirrors the orbit, basis and overlay matrices through the z = 0
+    /// plane when left-handed.
+    pub handedness: Handedness,
 }
 
 impl Camera {
@@ -26,17 +204,33 @@ impl Camera {
             max_radius: 1e12,
             azimuth: 0.0,
             elevation: 1.66,
+            min_elevation: 0.01,
+            max_elevation: PI - 0.01,
+            allow_pole_crossing: false,
+            pole_flipped: false,
             orbit_speed: 0.01,
             zoom_speed: 25e9,
             dragging: false,
             moving: false,
             last_x: 0.0,
             last_y: 0.0,
+            projection: Projection::Perspective,
+            ortho_scale: 5e10,
+            fov: 60.0,
+            dolly: None,
+            near: 1e8,
+            far: DEFAULT_ESCAPE_RADIUS as f32,
+            handedness: Handedness::RightHanded,
         }
     }
 
     pub fn position(&self) -> Vec3 {
-        let clamped_elevation = self.elevation.clamp(0.01, PI - 0.01);
+        self.handedness.apply(self.orbit_position())
+    }
+
+    /// `position` in the right-handed frame the orbit angles are defined in.
+    fn orbit_position(&self) -> Vec3 {
+        let clamped_elevation = self.elevation.clamp(POLE_EPSILON, PI - POLE_EPSILON);
         Vec3::new(
             self.radius * clamped_elevation.sin() * self.azimuth.cos(),
             self.radius * clamped_elevation.cos(),
@@ -44,6 +238,145 @@ impl Camera {
         )
     }
 
+    /// Position and orthonormal `(right, up, forward)` basis, as uploaded to
+    /// the shader. When left-handed all four are the right-handed ones with
+    /// z negated, so `right` becomes `up x forward` and every primary ray
+    /// (origin and direction) is mirrored through z = 0.
+    pub fn basis(&self) -> (Vec3, Vec3, Vec3, Vec3) {
+        let pos = self.orbit_position();
+        let forward = (self.target - pos).normalize();
+        let up_reference = self.up_reference();
+        // Looking straight along the pole (an offset target can line it up
+        // even with the elevation clamped), fall back to a horizontal
+        // reference giving the `right` the view tends to as it tilts onto
+        // the pole from this azimuth, so the basis never goes NaN there.
+        let toward_axis = -Vec3::new(self.azimuth.cos(), 0.0, self.azimuth.sin());
+        let fallback = toward_axis * -forward.dot(up_reference).signum();
+        let (right, up) = orthonormal_basis(forward, up_reference, fallback);
+        let mirror = |v| self.handedness.apply(v);
+        (mirror(pos), mirror(right), mirror(up), mirror(forward))
+    }
+
+    /// World-space reference for the camera's up direction, reversed while
+    /// the camera is on the far side of a pole.
+    pub fn up_reference(&self) -> Vec3 {
+        if self.pole_flipped { -Vec3::Y } else { Vec3::Y }
+    }
+
+    /// Clamps elevation to the limits, or with pole crossing enabled wraps it
+    /// back into `[0, PI]` and swings the azimuth round to the other side.
+    pub fn constrain_elevation(&mut self) {
+        if self.allow_pole_crossing {
+            if self.elevation < 0.0 || self.elevation > PI {
+                self.elevation = if self.elevation < 0.0 {
+                    -self.elevation
+                } else {
+                    2.0 * PI - self.elevation
+                };
+                self.azimuth += PI;
+                self.pole_flipped = !self.pole_flipped;
+            }
+        } else {
+            self.elevation = self.elevation.clamp(self.min_elevation, self.max_elevation);
+        }
+    }
+
+    /// Allows or forbids orbiting over the poles. Forbidding it while past a
+    /// pole turns the view the right way up again, with +Y up, and clamps
+    /// the elevation back within the limits.
+    pub fn set_pole_crossing(&mut self, allow: bool) {
+        self.allow_pole_crossing = allow;
+        if !allow {
+            self.pole_flipped = false;
+        }
+        self.constrain_elevation();
+    }
+
+    /// Starts a dolly zoom from the current radius and field of view to
+    /// `end_radius` meters and `end_fov` degrees over `duration` seconds.
+    pub fn start_dolly_zoom(&mut self, now: f64, duration: f64, end_fov: f32, end_radius: f32) {
+        let end_radius = end_radius.clamp(self.min_radius, self.max_radius);
+        let half_fov = |fov: f32| (fov.to_radians() / 2.0).tan();
+        self.dolly = Some(DollyZoom {
+            start_time: now,
+            duration,
+            start_radius: self.radius,
+            end_radius,
+            start_width: self.radius * half_fov(self.fov),
+            end_width: end_radius * half_fov(end_fov.clamp(MIN_FOV, MAX_FOV)),
+        });
+    }
+
+    /// Moves a running dolly zoom to time `now`, ending it once complete.
+    pub fn advance_dolly_zoom(&mut self, now: f64) {
+        let Some(dolly) = self.dolly else {
+            return;
+        };
+        let t = if dolly.duration > 0.0 {
+            ((now - dolly.start_time) / dolly.duration).clamp(0.0, 1.0) as f32
+        } else {
+            1.0
+        };
+        // Smoothstep so the move eases in and out
+        let t = t * t * (3.0 - 2.0 * t);
+        self.radius = dolly.start_radius + (dolly.end_radius - dolly.start_radius) * t;
+        let width = dolly.start_width + (dolly.end_width - dolly.start_width) * t;
+        self.fov = (2.0 * (width / self.radius).atan().to_degrees()).clamp(MIN_FOV, MAX_FOV);
+        if t >= 1.0 {
+            self.dolly = None;
+        }
+    }
+
+    /// Places the camera at world-space `eye` looking at `target`, solving
+    /// for the orbit angles and radius about the world origin. The radius is
+    /// clamped to the camera's limits and the elevation constrained as usual,
+    /// which moves the eye along its ray from the origin. Dragging or scrolling
+    /// afterwards goes back to looking at the world origin. Returns false,
+    /// changing nothing, if `eye` is at the origin or on `target`.
+    pub fn look_from(&mut self, eye: Vec3, target: Vec3) -> bool {
+        // Orbit angles are defined right-handed
+        let (eye, target) = (self.handedness.apply(eye), self.handedness.apply(target));
+        let radius = eye.length();
+        if radius <= 0.0 || eye.distance_squared(target) <= 0.0 {
+            return false;
+        }
+        self.dolly = None;
+        self.pole_flipped = false;
+        self.azimuth = eye.z.atan2(eye.x);
+        self.elevation = (eye.y / radius).clamp(-1.0, 1.0).acos();
+        self.constrain_elevation();
+        self.radius = radius.clamp(self.min_radius, self.max_radius);
+        self.target = target;
+        true
+    }
+
+    /// Sets the orbit radius to `multiple` Schwarzschild radii of a hole
+    /// with `r_s` meters, at most `max_radius`. `min_radius` is a fixed
+    /// distance in meters, which for a light hole lies many r_s out, so it
+    /// isn't applied: keeping clear of the horizon is left to the caller.
+    pub fn set_radius_rs(&mut self, multiple: f32, r_s: f64) {
+        self.radius = (multiple * r_s as f32).min(self.max_radius);
+    }
+
+    /// Pushes the camera out along its ray from the world origin until it
+    /// is at least `min_distance` from world-space `center`, for keeping it
+    /// out of a hole that need not sit at the origin. A camera already far
+    /// enough away is left where it is.
+    pub fn keep_away_from(&mut self, center: Vec3, min_distance: f32) {
+        let center = self.handedness.apply(center);
+        let direction = self.orbit_position() / self.radius;
+        let along = direction.dot(center);
+        let reach_sq = min_distance * min_distance - (center.length_squared() - along * along);
+        if reach_sq <= 0.0 {
+            return;
+        }
+        // The ray is within `min_distance` of `center` between these radii
+        let reach = reach_sq.sqrt();
+        if self.radius > along - reach && self.radius < along + reach {
+            self.radius = along + reach;
+        }
+    }
+
     pub fn update(&mut self) {
         self.target = Vec3::ZERO;
         self.moving = self.dragging;
@@ -54,9 +387,11 @@ impl Camera {
         let dy = (y - self.last_y) as f32;
 
         if self.dragging {
+            // Past a pole, dragging down moves elevation the other way
+            let flip = if self.pole_flipped { -1.0 } else { 1.0 };
             self.azimuth += dx * self.orbit_speed;
-            self.elevation -= dy * self.orbit_speed;
-            self.elevation = self.elevation.clamp(0.01, PI - 0.01);
+            self.elevation -= dy * self.orbit_speed * flip;
+            self.constrain_elevation();
         }
 
         self.last_x = x;
@@ -67,6 +402,8 @@ impl Camera {
     pub fn process_mouse_button(&mut self, button: u8, pressed: bool, x: f64, y: f64) {
         if button == 0 {
             if pressed {
+                // Grabbing the view takes over from a running dolly zoom
+                self.dolly = None;
                 self.dragging = true;
                 self.last_x = x;
                 self.last_y = y;
@@ -77,17 +414,75 @@ impl Camera {
     }
 
     pub fn process_scroll(&mut self, yoffset: f64) {
+        self.dolly = None;
         self.radius -= yoffset as f32 * self.zoom_speed;
         self.radius = self.radius.clamp(self.min_radius, self.max_radius);
         self.update();
     }
 
     pub fn view_matrix(&self) -> Mat4 {
-        Mat4::look_at_rh(self.position(), self.target, Vec3::Y)
+        // The basis's own up, which unlike `up_reference` stays usable when
+        // looking along the pole
+        let (pos, _, up, _) = self.basis();
+        match self.handedness {
+            Handedness::RightHanded => Mat4::look_at_rh(pos, self.target, up),
+            Handedness::LeftHanded => Mat4::look_at_lh(pos, self.handedness.apply(self.target), up),
+        }
+    }
+
+    /// Sets the overlay clip planes in meters. Returns false, leaving them
+    /// unchanged, unless `0 < near < far`.
+    pub fn set_clip_planes(&mut self, near: f32, far: f32) -> bool {
+        if !(near > 0.0 && far > near && far.is_finite()) {
+            return false;
+        }
+        self.near = near;
+        self.far = far;
+        true
+    }
+
+    /// Scales the clip planes to a black hole with Schwarzschild radius `r_s`
+    /// meters, so overlays keep their depth precision when the mass changes
+    /// by orders of magnitude.
+    pub fn fit_clip_planes(&mut self, r_s: f64) {
+        self.set_clip_planes((r_s * NEAR_PLANE_RS) as f32, (r_s * FAR_PLANE_RS) as f32);
+    }
+
+    /// `tan(fov / 2)` of the vertical axis for primary rays on a viewport
+    /// `aspect` wide per unit of height. `fov` is vertical, so on a wide
+    /// viewport the horizontal field is wider still; once that would pass
+    /// `MAX_FOV`, both axes narrow together so rays at the left and right
+    /// edges keep pointing forward instead of fanning out sideways.
+    pub fn tan_half_fov(&self, aspect: f32) -> f32 {
+        let max_tan = (MAX_FOV.to_radians() / 2.0).tan();
+        (self.fov.to_radians() / 2.0)
+            .tan()
+            .min(max_tan / aspect.max(1.0))
     }
 
     pub fn projection_matrix(&self, aspect: f32, fov: f32) -> Mat4 {
-        Mat4::perspective_rh(fov.to_radians(), aspect, 1e8, 1e13)
+        match self.handedness {
+            Handedness::RightHanded => {
+                Mat4::perspective_rh(fov.to_radians(), aspect, self.near, self.far)
+            }
+            Handedness::LeftHanded => {
+                Mat4::perspective_lh(fov.to_radians(), aspect, self.near, self.far)
+            }
+        }
+    }
+
+    /// `projection_matrix` for `Projection::Orthographic`, spanning
+    /// `ortho_scale` meters from the center to the top edge.
+    pub fn orthographic_matrix(&self, aspect: f32) -> Mat4 {
+        let (half_w, half_h) = (self.ortho_scale * aspect, self.ortho_scale);
+        match self.handedness {
+            Handedness::RightHanded => {
+                Mat4::orthographic_rh(-half_w, half_w, -half_h, half_h, self.near, self.far)
+            }
+            Handedness::LeftHanded => {
+                Mat4::orthographic_lh(-half_w, half_w, -half_h, half_h, self.near, self.far)
+            }
+        }
     }
 }
 
@@ -96,3 +491,334 @@ impl Default for Camera {
         Self::new()
     }
 }
+
+/// The shader's `Camera` uniform, field for field. The live view and
+/// `reference_render` both build their primary rays from it, so the CPU
+/// side reads exactly what the GPU is given.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraUniform {
+    /// Camera position relative to the hole, in meters.
+    pub pos: Vec3,
+    pub right: Vec3,
+    pub up: Vec3,
+    pub forward: Vec3,
+    pub tan_half_fov: f32,
+    pub aspect: f32,
+    pub moving: bool,
+    /// 0 perspective, 1 orthographic, 2 equirectangular panorama.
+    pub projection: f32,
+    pub ortho_scale: f32,
+    pub black_hole_rs: f32,
+    pub c_light: f32,
+}
+
+impl CameraUniform {
+    /// Number of `f32`s in the packed uniform.
+    pub const LEN: usize = 24;
+
+    /// Packs the uniform in the shader's layout, vec3s padded to 16 bytes.
+    pub fn to_floats(&self) -> Vec<f32> {
+        let mut data = Vec::with_capacity(Self::LEN);
+        for v in [self.pos, self.right, self.up, self.forward] {
+            data.extend_from_slice(&[v.x, v.y, v.z, 0.0]);
+        }
+        data.extend_from_slice(&[
+            self.tan_half_fov,
+            self.aspect,
+            if self.moving { 1.0 } else { 0.0 },
+            0.0,
+            self.projection,
+            self.ortho_scale,
+            self.black_hole_rs,
+            self.c_light,
+        ]);
+        data
+    }
+
+    /// Origin and direction of the primary ray through `(ndc_x, ndc_y)` (y
+    /// up), as the shader's `primary_ray` builds it.
+    pub fn primary_ray(&self, ndc_x: f32, ndc_y: f32) -> (Vec3, Vec3) {
+        if self.projection > 1.5 {
+            // Screen-up is -up, as in the perspective rays below
+            let lon = ndc_x * PI;
+            let lat = ndc_y * 0.5 * PI;
+            let dir = lat.cos() * (lon.sin() * self.right + lon.cos() * self.forward)
+                - lat.sin() * self.up;
+            (self.pos, dir.normalize())
+        } else if self.projection > 0.5 {
+            let scale = self.ortho_scale;
+            let origin =
+                self.pos + ndc_x * self.aspect * scale * self.right - ndc_y * scale * self.up;
+            (origin, self.forward)
+        } else {
+            let screen_u = ndc_x * self.aspect * self.tan_half_fov;
+            let screen_v = ndc_y * self.tan_half_fov;
+            (
+                self.pos,
+                (screen_u * self.right - screen_v * self.up + self.forward).normalize(),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disabling_pole_crossing_rights_a_flipped_camera() {
+        let mut camera = Camera::new();
+        camera.set_pole_crossing(true);
+        camera.elevation = -0.2;
+        camera.constrain_elevation();
+        assert!(camera.pole_flipped);
+
+        camera.set_pole_crossing(false);
+        assert!(!camera.pole_flipped);
+        assert_eq!(camera.up_reference(), Vec3::Y);
+        assert!((camera.min_elevation..=camera.max_elevation).contains(&camera.elevation));
+        camera.elevation = -0.2;
+        camera.constrain_elevation();
+        assert_eq!(camera.elevation, camera.min_elevation);
+        assert!(!camera.pole_flipped);
+    }
+
+    #[test]
+    fn keep_away_from_pushes_out_from_an_offset_center() {
+        let mut camera = Camera::new();
+        camera.radius = 2e10;
+        let center = camera.position() * 1.2;
+        camera.keep_away_from(center, 1e10);
+        assert!((camera.position().distance(center) - 1e10).abs() < 1e4);
+        assert!(camera.radius > 2e10);
+
+        // Far enough already, or the ray passes wide of it: unchanged
+        let radius = camera.radius;
+        camera.keep_away_from(Vec3::ZERO, 1e10);
+        camera.keep_away_from(Vec3::new(0.0, 5e10, 0.0), 1e10);
+        assert_eq!(camera.radius, radius);
+    }
+
+    #[test]
+    fn radius_in_schwarzschild_radii_reaches_the_isco() {
+        let black_hole = crate::physics::BlackHole::sagittarius_a();
+        let mut camera = Camera::new();
+        camera.set_radius_rs(3.0, black_hole.r_s);
+        let isco = black_hole.isco_radius() as f32;
+        assert!((camera.position().length() / isco - 1.0).abs() < 1e-6);
+        camera.set_radius_rs(1e6, black_hole.r_s);
+        assert_eq!(camera.radius, camera.max_radius);
+
+        // Ten solar masses: 2 r_s is some 60 km, far inside `min_radius`
+        let r_s = 10.0 * black_hole.r_s / 4.3e6;
+        camera.set_radius_rs(2.0, r_s);
+        assert!((camera.radius / (2.0 * r_s as f32) - 1.0).abs() < 1e-6);
+        assert!(camera.radius < camera.min_radius);
+    }
+
+    #[test]
+    fn wide_viewport_edge_rays_stay_finite_and_forward() {
+        let mut camera = Camera::new();
+        camera.fov = MAX_FOV;
+        let (_, right, up, forward) = camera.basis();
+        for aspect in [1.0, 4.0, 16.0] {
+            let tan_half_fov = camera.tan_half_fov(aspect);
+            // Corner ray, built as the shader builds primary rays
+            let dir = (forward + aspect * tan_half_fov * right + tan_half_fov * up).normalize();
+            assert!(dir.is_finite(), "aspect {}", aspect);
+            let horizontal = (aspect * tan_half_fov).atan().to_degrees();
+            assert!(horizontal <= MAX_FOV / 2.0 + 1e-3, "aspect {}", aspect);
+            assert!(dir.dot(forward) > 0.0);
+        }
+        // Only the horizontal field that would pass MAX_FOV is capped
+        camera.fov = 60.0;
+        let uncapped = 30f32.to_radians().tan();
+        assert!((camera.tan_half_fov(1.0) - uncapped).abs() < 1e-6);
+        assert!((camera.tan_half_fov(0.25) - uncapped).abs() < 1e-6);
+        let capped = camera.tan_half_fov(4.0) * 4.0;
+        assert!((capped - (MAX_FOV / 2.0).to_radians().tan()).abs() < 1e-5);
+    }
+
+    #[test]
+    fn basis_looking_straight_down_is_orthonormal() {
+        let mut camera = Camera::new();
+        camera.azimuth = 0.4;
+        camera.elevation = camera.min_elevation;
+        // A target right below the camera lines forward up with the pole
+        let pos = camera.position();
+        camera.target = Vec3::new(pos.x, 0.0, pos.z);
+        let (_, right, up, forward) = camera.basis();
+        assert_eq!(forward, -Vec3::Y);
+        for v in [right, up] {
+            assert!(v.is_finite() && (v.length() - 1.0).abs() < 1e-5);
+        }
+        assert!(right.dot(up).abs() < 1e-5);
+        assert!(right.dot(forward).abs() < 1e-5);
+        assert!(up.dot(forward).abs() < 1e-5);
+        assert!(right.cross(up).distance(-forward) < 1e-5);
+        assert!(camera.view_matrix().is_finite());
+
+        // Matches the basis with the target nudged toward the axis, as when
+        // orbiting onto the pole, so the view doesn't spin as it gets there
+        camera.target -= Vec3::new(pos.x, 0.0, pos.z).normalize() * 1e-3 * camera.radius;
+        let (_, nearby_right, _, _) = camera.basis();
+        assert!(nearby_right.distance(right) < 1e-2);
+    }
+
+    #[test]
+    fn look_from_round_trips_through_position() {
+        for handedness in [Handedness::RightHanded, Handedness::LeftHanded] {
+            let mut camera = Camera::new();
+            camera.handedness = handedness;
+            let eye = Vec3::new(-6e10, 4e10, 9e10);
+            let target = Vec3::new(1e9, -2e9, 3e9);
+            assert!(camera.look_from(eye, target));
+            assert!(camera.position().distance(eye) < 1e-5 * eye.length());
+            assert_eq!(camera.target, handedness.apply(target));
+            let (pos, _, _, forward) = camera.basis();
+            assert!(pos.distance(eye) < 1e-5 * eye.length());
+            assert!(forward.distance((target - eye).normalize()) < 1e-5);
+        }
+
+        let mut camera = Camera::new();
+        let before = camera.position();
+        assert!(!camera.look_from(Vec3::ZERO, Vec3::X));
+        assert!(!camera.look_from(Vec3::X * 5e10, Vec3::X * 5e10));
+        assert_eq!(camera.position(), before);
+    }
+
+    #[test]
+    fn view_and_projection_matrices_are_finite_and_center_the_target() {
+        let mut straight_down = Camera::new();
+        straight_down.elevation = straight_down.min_elevation;
+        let pos = straight_down.position();
+        straight_down.target = Vec3::new(pos.x, 0.0, pos.z);
+        let mut left_handed = Camera::new();
+        left_handed.handedness = Handedness::LeftHanded;
+
+        for camera in [Camera::new(), straight_down, left_handed] {
+            let view = camera.view_matrix();
+            assert!(view.is_finite());
+            for aspect in [0.5, 1.0, 4.0] {
+                let fov = 2.0 * camera.tan_half_fov(aspect).atan().to_degrees();
+                for projection in [
+                    camera.projection_matrix(aspect, fov),
+                    camera.orthographic_matrix(aspect),
+                ] {
+                    assert!(projection.is_finite());
+                    let target = camera.handedness.apply(camera.target);
+                    let clip = projection * view * target.extend(1.0);
+                    let ndc = clip.truncate() / clip.w;
+                    assert!(ndc.x.abs() < 1e-4 && ndc.y.abs() < 1e-4, "{:?}", ndc);
+                    assert!((0.0..=1.0).contains(&ndc.z), "{:?}", ndc);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn packed_uniform_matches_the_shaders_camera_struct() {
+        let uniform = CameraUniform {
+            pos: Vec3::new(1.0, 2.0, 3.0),
+            right: Vec3::new(4.0, 5.0, 6.0),
+            up: Vec3::new(7.0, 8.0, 9.0),
+            forward: Vec3::new(10.0, 11.0, 12.0),
+            tan_half_fov: 13.0,
+            aspect: 14.0,
+            moving: true,
+            projection: 15.0,
+            ortho_scale: 16.0,
+            black_hole_rs: 17.0,
+            c_light: 18.0,
+        };
+        let expected: [(&str, &[f32]); 11] = [
+            ("pos", &uniform.pos.to_array()),
+            ("right", &uniform.right.to_array()),
+            ("up", &uniform.up.to_array()),
+            ("forward", &uniform.forward.to_array()),
+            ("tan_half_fov", &[uniform.tan_half_fov]),
+            ("aspect", &[uniform.aspect]),
+            ("moving", &[1.0]),
+            ("projection", &[uniform.projection]),
+            ("ortho_scale", &[uniform.ortho_scale]),
+            ("black_hole_rs", &[uniform.black_hole_rs]),
+            ("c_light", &[uniform.c_light]),
+        ];
+
+        let source = include_str!("shader.wgsl");
+        let module = naga::front::wgsl::parse_str(source).unwrap();
+        let (members, span) = module
+            .types
+            .iter()
+            .find_map(|(_, ty)| match &ty.inner {
+                naga::TypeInner::Struct { members, span }
+                    if ty.name.as_deref() == Some("Camera") =>
+                {
+                    Some((members.clone(), *span))
+                }
+                _ => None,
+            })
+            .expect("shader has no `struct Camera`");
+        let data = uniform.to_floats();
+        assert_eq!(data.len(), CameraUniform::LEN);
+        assert_eq!(span as usize, 4 * CameraUniform::LEN);
+
+        let fields: Vec<_> = members
+            .iter()
+            .filter_map(|m| {
+                m.name
+                    .as_deref()
+                    .filter(|name| !name.starts_with('_'))
+                    .map(|name| (name, m.offset))
+            })
+            .collect();
+        assert_eq!(fields.len(), expected.len(), "{:?}", fields);
+        for (name, values) in expected {
+            let (_, offset) = fields
+                .iter()
+                .find(|(field, _)| *field == name)
+                .unwrap_or_else(|| panic!("shader's Camera has no `{}`", name));
+            let at = *offset as usize / 4;
+            assert_eq!(&data[at..at + values.len()], values, "{}", name);
+        }
+    }
+
+    #[test]
+    fn uniform_rays_follow_the_camera_basis() {
+        let camera = Camera::new();
+        let (pos, right, up, forward) = camera.basis();
+        let mut uniform = CameraUniform {
+            pos,
+            right,
+            up,
+            forward,
+            tan_half_fov: camera.tan_half_fov(2.0),
+            aspect: 2.0,
+            moving: false,
+            projection: Projection::Perspective.shader_code(),
+            ortho_scale: camera.ortho_scale,
+            black_hole_rs: 1.0,
+            c_light: 1.0,
+        };
+        let close = |a: Vec3, b: Vec3| (a - b).length() <= 1e-5 * b.length().max(1.0);
+
+        let (origin, center) = uniform.primary_ray(0.0, 0.0);
+        assert_eq!(origin, pos);
+        assert!(close(center, forward));
+        // Screen-up is -up
+        let (_, top) = uniform.primary_ray(0.0, 1.0);
+        assert!(close(
+            top,
+            (forward - uniform.tan_half_fov * up).normalize()
+        ));
+
+        uniform.projection = Projection::Orthographic.shader_code();
+        let (origin, dir) = uniform.primary_ray(1.0, 0.0);
+        assert!(close(origin, pos + 2.0 * camera.ortho_scale * right));
+        assert_eq!(dir, forward);
+
+        uniform.projection = 2.0;
+        let (_, behind) = uniform.primary_ray(1.0, 0.0);
+        assert!(close(behind, -forward), "{:?}", behind);
+    }
+}
@@ -1,12 +1,18 @@
-use crate::physics::{C, G, Ray};
-use glam::Vec3;
+use crate::physics::{Disk, Ray};
+use glam::{DVec3, Vec3};
 
-const SAG_A_MASS: f64 = 8.54e36;
-const SAG_A_RS: f64 = 2.0 * G * SAG_A_MASS / (C * C);
 const D_LAMBDA: f64 = 1e7;
-const ESCAPE_R: f64 = 1e30;
+/// Default step budget per ray, matching the shader's `NSTEPS` default.
+pub const DEFAULT_MAX_STEPS: u32 = 2000;
+/// Longest affine step a light pulse photon takes, in meters, and the most
+/// steps it takes per `LightPulse::advance_to`.
+const PULSE_STEP: f64 = 1e8;
+const MAX_PULSE_SUBSTEPS: usize = 256;
+/// Orbital angle per RK4 step for `TimelikeOrbit`, in radians.
+const ORBIT_DPHI: f64 = 0.01;
 
-pub fn init_ray(pos: Vec3, dir: Vec3) -> Ray {
+/// Starts a ray at `pos`, relative to the hole, heading along `dir`.
+pub fn init_ray(pos: Vec3, dir: Vec3, r_s: f64) -> Ray {
     let r = pos.length() as f64;
     let theta = (pos.z as f64 / r).acos();
     let phi = (pos.y as f64).atan2(pos.x as f64);
@@ -23,7 +29,7 @@ pub fn init_ray(pos: Vec3, dir: Vec3) -> Ray {
     let dphi = (-phi.sin() * dx + phi.cos() * dy) / (r * theta.sin());
 
     let angular_momentum = r * r * theta.sin() * dphi;
-    let f = 1.0 - SAG_A_RS / r;
+    let f = 1.0 - r_s / r;
     let dt_dl = ((dr * dr / f)
         + r * r * (dtheta * dtheta + theta.sin() * theta.sin() * dphi * dphi))
         .sqrt();
@@ -78,8 +84,14 @@ pub fn rk4_step(ray: &mut Ray, dl: f64, r_s: f64) {
     ray.dphi += dl * k1b.z as f64;
 }
 
-pub fn trace_ray(pos: Vec3, dir: Vec3, r_s: f64, max_steps: usize) -> TraceResult {
-    let mut ray = init_ray(pos, dir);
+/// Traces a ray until it is captured, passes `escape_r`, or runs out of
+/// steps. A small `escape_r` cuts the lensing short; a large one spends steps
+/// on rays that are already effectively free, and rays that exhaust
+/// `max_steps` first report `MaxSteps`. The escape radius is never less than
+/// 1.5x the starting radius so that rays from a distant camera still travel.
+pub fn trace_ray(pos: Vec3, dir: Vec3, r_s: f64, escape_r: f64, max_steps: usize) -> TraceResult {
+    let mut ray = init_ray(pos, dir, r_s);
+    let escape_r = escape_r.max(ray.r * 1.5);
 
     for _ in 0..max_steps {
         if ray.r <= r_s {
@@ -88,7 +100,7 @@ pub fn trace_ray(pos: Vec3, dir: Vec3, r_s: f64, max_steps: usize) -> TraceResul
 
         rk4_step(&mut ray, D_LAMBDA, r_s);
 
-        if ray.r > ESCAPE_R {
+        if ray.r > escape_r {
             return TraceResult::Escaped;
         }
     }
@@ -96,11 +108,925 @@ pub fn trace_ray(pos: Vec3, dir: Vec3, r_s: f64, max_steps: usize) -> TraceResul
     TraceResult::MaxSteps
 }
 
+/// Time-reversal self-check of `rk4_step`: integrates the ray `steps`
+/// steps forward, reverses it with `Ray::reverse`, integrates the same
+/// number of steps back and returns how far in meters it ends from where it
+/// started. Exact integration would return 0.
+///
+/// The step is a single explicit (Euler) stage of `D_LAMBDA` with the
+/// derivatives rounded to f32, which is not time-reversible, so the error
+/// grows with path length and curvature. Far from the hole it stays below
+/// 1e-4 r_s for a few thousand steps; a ray passing about 3 r_s from the
+/// center returns to within 2e-3 r_s after 15000 steps each way, and
+/// several times that by 25000. Rays that reach the horizon cannot be
+/// retraced and return `None`.
+pub fn retrace_error(pos: Vec3, dir: Vec3, r_s: f64, steps: usize) -> Option<f64> {
+    let mut ray = init_ray(pos, dir, r_s);
+    let start = ray.position();
+    for _ in 0..steps {
+        rk4_step(&mut ray, D_LAMBDA, r_s);
+        if ray.r <= r_s {
+            return None;
+        }
+    }
+    ray = ray.reverse();
+    for _ in 0..steps {
+        rk4_step(&mut ray, D_LAMBDA, r_s);
+        if ray.r <= r_s {
+            return None;
+        }
+    }
+    Some(ray.position().distance(start))
+}
+
+/// Outcome of `trace_like_shader`: what the ray hit, the path length in
+/// meters it travelled to get there, and where it stopped.
+#[derive(Debug, Clone, Copy)]
+pub struct ShaderTrace {
+    pub result: TraceResult,
+    pub path_length: f64,
+    /// World-space point in meters where the ray stopped; for disk and
+    /// object hits, the point on the surface.
+    pub position: Vec3,
+    /// Unit direction of travel over the last step, or zero if the ray
+    /// stopped before taking one.
+    pub direction: Vec3,
+    /// Steps taken, including the one that ended the trace.
+    pub steps: u32,
+    /// Closest the ray came to the hole's center, in meters.
+    pub min_r: f64,
+    /// Angle the ray swept about the hole in its orbital plane, in turns:
+    /// the ray's winding number. A ray aimed straight away from the hole
+    /// sweeps almost nothing and one bent past it about half a turn; each
+    /// loop around the photon sphere adds one more, which gives the order
+    /// of the lensed image it belongs to.
+    pub winding: f64,
+}
+
+/// One step of `trace_like_shader`, as passed to `trace_like_shader_with`'s
+/// callback. Points are in meters relative to the hole.
+#[derive(Debug, Clone, Copy)]
+pub struct ShaderStep {
+    pub start: DVec3,
+    pub end: DVec3,
+    /// Fraction along the step where it crosses the disk's midplane in
+    /// front of any solid surface, whether the crossing is translucent or
+    /// ends the trace.
+    pub disk_crossing: Option<f64>,
+    /// Fraction along the step where the trace stops and what it stopped
+    /// on, or `None` if it goes on.
+    pub stop: Option<(f64, TraceResult)>,
+}
+
+/// Traces a ray with the compute shader's scheme rather than `rk4_step`:
+/// leapfrog in `u = 1/r` within the ray's orbital plane, in units where
+/// `r_s = 2`, over two revolutions split into `max_steps` steps. Stops at the
+/// horizon, the disk, the sphere at `center` with `radius`, or `escape_r`
+/// (all in meters). As in the shader, all but the last of the disk's
+/// `max_crossings` midplane crossings are translucent and the ray goes on
+/// through them. Following the shader step for step keeps CPU answers,
+/// such as where an object appears on screen, aligned with the image.
+pub fn trace_like_shader(
+    pos: Vec3,
+    dir: Vec3,
+    r_s: f64,
+    escape_r: f64,
+    max_steps: u32,
+    disk: &Disk,
+    center: Vec3,
+    radius: f32,
+) -> ShaderTrace {
+    trace_like_shader_with(
+        pos,
+        dir,
+        r_s,
+        escape_r,
+        max_steps,
+        disk,
+        center,
+        radius,
+        |_| {},
+    )
+}
+
+/// `trace_like_shader`, calling `on_step` after each step it takes, in
+/// order, including the one that ends the trace.
+pub fn trace_like_shader_with(
+    pos: Vec3,
+    dir: Vec3,
+    r_s: f64,
+    escape_r: f64,
+    max_steps: u32,
+    disk: &Disk,
+    center: Vec3,
+    radius: f32,
+    mut on_step: impl FnMut(ShaderStep),
+) -> ShaderTrace {
+    let unit_scale = r_s / 2.0;
+    let center = center.as_dvec3();
+    let radius = radius as f64;
+    let dir = dir.as_dvec3().normalize();
+
+    let mut pos = pos.as_dvec3() / unit_scale;
+    let mut u = 1.0 / pos.length();
+    let escape_distance = (escape_r / unit_scale).max(1.5 / u);
+
+    // Orbital plane, as in the shader's `init_orbit`
+    let normal = pos.normalize();
+    let tangent_unnorm = normal.cross(dir).cross(normal);
+    let tangent = if tangent_unnorm.length() > 1e-9 {
+        tangent_unnorm.normalize()
+    } else {
+        normal.any_orthonormal_vector()
+    };
+    let denominator = dir.dot(tangent);
+    let mut du = if denominator.abs() > 1e-9 {
+        -dir.dot(normal) / denominator * u
+    } else {
+        0.0
+    };
+
+    let steps = max_steps.max(1);
+    let step = 2.0 * 2.0 * std::f64::consts::PI / steps as f64;
+    let mut phi: f64 = 0.0;
+    let mut path_length = 0.0;
+    let mut last_step = DVec3::ZERO;
+    let to_disk = disk.orientation().transpose().as_dmat3();
+    // `progress` is the steps taken and the largest u reached so far
+    let finish =
+        |result, path_length, position: DVec3, step: DVec3, progress: (u32, f64), phi: f64| {
+            ShaderTrace {
+                result,
+                path_length,
+                position: position.as_vec3(),
+                direction: step.normalize_or_zero().as_vec3(),
+                steps: progress.0,
+                min_r: unit_scale / progress.1,
+                winding: phi / (2.0 * std::f64::consts::PI),
+            }
+        };
+    let mut max_u = u;
+    let mut crossings = 0;
+
+    for taken in 1..=steps {
+        let old_u = u;
+        u += du * step;
+        du += -u * (1.0 - 1.5 * u * u) * step;
+        if u < 0.0 {
+            return finish(
+                TraceResult::Escaped,
+                path_length,
+                pos * unit_scale,
+                last_step,
+                (taken, max_u),
+                phi,
+            );
+        }
+        phi += step;
+        max_u = max_u.max(u);
+        let progress = (taken, max_u);
+
+        let old_pos = pos;
+        pos = (phi.cos() * normal + phi.sin() * tangent) / u;
+        let a = old_pos * unit_scale;
+        let b = pos * unit_scale;
+        let segment = b - a;
+        last_step = segment;
+        let segment_length = segment.length();
+
+        // Nearest solid surface reached within the step, as a fraction
+        // along it, as in the shader: e.g. a planet sitting between the
+        // camera and the disk's near edge hides the disk even when both are
+        // hit in one step
+        let mut nearest: Option<(f64, TraceResult)> = None;
+        let mut consider = |t: f64, result| {
+            if nearest.is_none_or(|(best, _)| t < best) {
+                nearest = Some((t, result));
+            }
+        };
+        if u > 0.5 {
+            consider(
+                ((0.5 - old_u) / (u - old_u)).clamp(0.0, 1.0),
+                TraceResult::HitBlackHole,
+            );
+        }
+        if segment_length > 0.0 {
+            let d = segment / segment_length;
+            let oc = a - center;
+            let half_b = oc.dot(d);
+            let disc = half_b * half_b - (oc.dot(oc) - radius * radius);
+            if disc >= 0.0 {
+                let t = -half_b - disc.sqrt();
+                if (0.0..=segment_length).contains(&t) {
+                    consider(t / segment_length, TraceResult::HitObject);
+                }
+            }
+        }
+
+        // The disk is weighed against the solids separately: all but the
+        // last crossing allowed are translucent and the ray goes on
+        let (disk_a, disk_b) = (to_disk * a, to_disk * b);
+        let radial = (disk_b.x * disk_b.x + disk_b.z * disk_b.z).sqrt();
+        let disk_crossing = (disk_a.y * disk_b.y < 0.0
+            && radial >= disk.inner_radius as f64
+            && radial <= disk.outer_radius as f64)
+            .then(|| disk_a.y / (disk_a.y - disk_b.y))
+            .filter(|&t| nearest.is_none_or(|(solid, _)| t < solid));
+        if let Some(t) = disk_crossing {
+            if crossings + 1 >= disk.max_crossings {
+                nearest = Some((t, TraceResult::HitDisk));
+            } else {
+                crossings += 1;
+            }
+        }
+        on_step(ShaderStep {
+            start: a,
+            end: b,
+            disk_crossing,
+            stop: nearest,
+        });
+
+        match nearest {
+            Some((_, TraceResult::HitBlackHole)) => {
+                return finish(
+                    TraceResult::HitBlackHole,
+                    path_length,
+                    b,
+                    segment,
+                    progress,
+                    phi,
+                );
+            }
+            Some((t, TraceResult::HitDisk)) => {
+                return finish(
+                    TraceResult::HitDisk,
+                    path_length,
+                    a + segment * t,
+                    segment,
+                    progress,
+                    phi,
+                );
+            }
+            Some((t, result)) => {
+                let hit = a + segment * t;
+                return finish(
+                    result,
+                    path_length + segment_length * t,
+                    hit,
+                    segment,
+                    progress,
+                    phi,
+                );
+            }
+            None => {}
+        }
+        path_length += segment_length;
+
+        if 1.0 / u > escape_distance {
+            return finish(TraceResult::Escaped, path_length, b, segment, progress, phi);
+        }
+    }
+
+    finish(
+        TraceResult::MaxSteps,
+        path_length,
+        pos * unit_scale,
+        last_step,
+        (steps, max_u),
+        phi,
+    )
+}
+
+/// `trace_like_shader`, also returning every point the ray passed through:
+/// the start and the end of each step, the last one cut short where the
+/// trace stopped on a surface.
+pub fn trace_ray_path(
+    pos: Vec3,
+    dir: Vec3,
+    r_s: f64,
+    escape_r: f64,
+    max_steps: u32,
+    disk: &Disk,
+    center: Vec3,
+    radius: f32,
+) -> (ShaderTrace, Vec<Vec3>) {
+    let mut path = vec![pos];
+    let trace = trace_like_shader_with(
+        pos,
+        dir,
+        r_s,
+        escape_r,
+        max_steps,
+        disk,
+        center,
+        radius,
+        |step| {
+            let end = match step.stop {
+                Some((t, _)) => step.start.lerp(step.end, t),
+                None => step.end,
+            };
+            path.push(end.as_vec3());
+        },
+    );
+    (trace, path)
+}
+
+/// The shader's step count for a ray starting at `pos`, in meters from the
+/// hole: the full `max_steps` within 50 r_s, fewer further out where
+/// gravity is weaker, down to a tenth.
+pub fn adaptive_steps(pos: Vec3, r_s: f64, max_steps: u32) -> u32 {
+    let r0 = pos.length() as f64 / (r_s / 2.0);
+    (max_steps as f64 * (100.0 / r0).clamp(0.1, 1.0)) as u32
+}
+
+/// `trace_like_shader` from `pos` along each of `directions`, given as xyz
+/// triples; a trailing partial triple is ignored.
+pub fn trace_batch(
+    pos: Vec3,
+    directions: &[f32],
+    r_s: f64,
+    escape_r: f64,
+    max_steps: u32,
+    disk: &Disk,
+    center: Vec3,
+    radius: f32,
+) -> Vec<ShaderTrace> {
+    directions
+        .chunks_exact(3)
+        .map(|d| {
+            trace_like_shader(
+                pos,
+                Vec3::new(d[0], d[1], d[2]),
+                r_s,
+                escape_r,
+                max_steps,
+                disk,
+                center,
+                radius,
+            )
+        })
+        .collect()
+}
+
+/// Lensing magnification of the sky seen along three nearby unit
+/// `directions`: the solid angle they span over that spanned by `escapes`,
+/// the directions they leave the hole's neighborhood in (e.g.
+/// `ShaderTrace::direction`). Infinite if the escapes coincide.
+pub fn magnification(directions: [DVec3; 3], escapes: [DVec3; 3]) -> f64 {
+    let area = |[a, b, c]: [DVec3; 3]| (b - a).cross(c - a).length();
+    let (image, source) = (area(directions), area(escapes));
+    if source <= 0.0 {
+        return f64::INFINITY;
+    }
+    image / source
+}
+
+/// Traces a ray with `trace_like_shader` until it first crosses the disk and
+/// returns the crossing point in meters and the unit direction the light was
+/// travelling there, as needed for Doppler factors on the CPU. `escape_r`
+/// and `max_steps` are as for `trace_like_shader`; pass the live view's to
+/// match what it shows. `None` if the ray is captured, escapes, or runs out
+/// of steps first.
+pub fn trace_to_disk(
+    pos: Vec3,
+    dir: Vec3,
+    r_s: f64,
+    escape_r: f64,
+    max_steps: u32,
+    disk: &Disk,
+) -> Option<(Vec3, Vec3)> {
+    // Stop at the first crossing, however many the disk composites
+    let opaque = Disk {
+        max_crossings: 1,
+        ..*disk
+    };
+    let trace = trace_like_shader(pos, dir, r_s, escape_r, max_steps, &opaque, Vec3::ZERO, 0.0);
+    (trace.result == TraceResult::HitDisk).then_some((trace.position, trace.direction))
+}
+
+/// An expanding shell of photons emitted from a point, advanced along with
+/// the simulation clock so the pulse can be watched wrapping around the hole.
+#[derive(Debug, Clone)]
+pub struct LightPulse {
+    pub photons: Vec<Ray>,
+    /// Multiplies the distance the photons cover per unit of simulated time.
+    pub speed_scale: f64,
+    /// Simulation time the photons have been advanced to.
+    pub time: f64,
+}
+
+impl LightPulse {
+    /// Emits `count` photons spread evenly over the sphere of directions at
+    /// simulation time `time`.
+    pub fn emit(origin: Vec3, count: usize, speed_scale: f64, r_s: f64, time: f64) -> Self {
+        let golden_angle = std::f32::consts::PI * (3.0 - 5.0f32.sqrt());
+        let photons = (0..count)
+            .map(|i| {
+                let y = 1.0 - 2.0 * (i as f32 + 0.5) / count as f32;
+                let ring = (1.0 - y * y).sqrt();
+                let angle = golden_angle * i as f32;
+                let dir = Vec3::new(ring * angle.cos(), y, ring * angle.sin());
+                init_ray(origin, dir, r_s)
+            })
+            .collect();
+
+        LightPulse {
+            photons,
+            speed_scale,
+            time,
+        }
+    }
+
+    /// Advances every photon to simulation time `time` and drops the ones
+    /// that were captured or passed `escape_r`. Each unit of time covers `c`
+    /// (times `speed_scale`) of affine length, which far from the hole is
+    /// the distance travelled, in steps of at most `PULSE_STEP`; a jump too
+    /// long for `MAX_PULSE_SUBSTEPS` of those takes longer, coarser steps.
+    /// Never steps back.
+    pub fn advance_to(&mut self, time: f64, c: f64, r_s: f64, escape_r: f64) {
+        let distance = (time - self.time).max(0.0) * c * self.speed_scale;
+        self.time = self.time.max(time);
+        if !(distance > 0.0 && distance.is_finite()) {
+            return;
+        }
+        let substeps = (distance / PULSE_STEP)
+            .ceil()
+            .min(MAX_PULSE_SUBSTEPS as f64) as usize;
+        let step = distance / substeps as f64;
+        for ray in &mut self.photons {
+            for _ in 0..substeps {
+                rk4_step(ray, step, r_s);
+            }
+        }
+        self.photons
+            .retain(|ray| ray.r > r_s && ray.r < escape_r && ray.r.is_finite());
+    }
+}
+
+/// A bound massive-particle geodesic in its orbital plane. Lengths are in
+/// units of `M = GM/c^2` and coordinate time in units of `M/c`, so the orbit
+/// equation is `u'' + u = 1/L^2 + 3u^2` for `u = M/r` as a function of the
+/// orbital angle. The `3u^2` term is what makes the periapsis advance.
+#[derive(Debug, Clone, Copy)]
+pub struct TimelikeOrbit {
+    /// `M / r`
+    pub u: f64,
+    /// `du/dphi`
+    pub du: f64,
+    pub phi: f64,
+    /// Coordinate time since periapsis, in units of `M/c`.
+    pub t: f64,
+    /// Orbital angle of the latest periapsis passage. Advances by a little
+    /// more than `2 pi` each orbit as the periapsis precesses.
+    pub periapsis_phi: f64,
+    angular_momentum: f64,
+    energy: f64,
+}
+
+impl TimelikeOrbit {
+    /// Starts at periapsis of the bound orbit with semi-latus rectum `p`
+    /// (in units of M) and eccentricity `e`. Returns `None` unless the orbit
+    /// is stable, `p > 6 + 2e`; closer in, the particle plunges.
+    pub fn from_periapsis(p: f64, e: f64) -> Option<Self> {
+        if !(0.0..1.0).contains(&e) || p <= 6.0 + 2.0 * e {
+            return None;
+        }
+        let denom = p - 3.0 - e * e;
+        let angular_momentum = p / denom.sqrt();
+        let energy = ((p - 2.0 - 2.0 * e) * (p - 2.0 + 2.0 * e) / (p * denom)).sqrt();
+
+        Some(TimelikeOrbit {
+            u: (1.0 + e) / p,
+            du: 0.0,
+            phi: 0.0,
+            t: 0.0,
+            periapsis_phi: 0.0,
+            angular_momentum,
+            energy,
+        })
+    }
+
+    /// Derivatives of `(u, du, t)` with respect to phi.
+    fn rhs(&self, u: f64, du: f64) -> (f64, f64, f64) {
+        let l = self.angular_momentum;
+        let ddu = 1.0 / (l * l) - u + 3.0 * u * u;
+        let dt = self.energy / (l * u * u * (1.0 - 2.0 * u));
+        (du, ddu, dt)
+    }
+
+    pub fn rk4_step(&mut self, dphi: f64) {
+        let (a1, b1, c1) = self.rhs(self.u, self.du);
+        let (a2, b2, c2) = self.rhs(self.u + 0.5 * dphi * a1, self.du + 0.5 * dphi * b1);
+        let (a3, b3, c3) = self.rhs(self.u + 0.5 * dphi * a2, self.du + 0.5 * dphi * b2);
+        let (a4, b4, c4) = self.rhs(self.u + dphi * a3, self.du + dphi * b3);
+
+        let old_du = self.du;
+        self.u += dphi / 6.0 * (a1 + 2.0 * a2 + 2.0 * a3 + a4);
+        self.du += dphi / 6.0 * (b1 + 2.0 * b2 + 2.0 * b3 + b4);
+        self.t += dphi / 6.0 * (c1 + 2.0 * c2 + 2.0 * c3 + c4);
+
+        // u peaks at periapsis, so du turns from rising to falling there
+        if old_du > 0.0 && self.du <= 0.0 {
+            self.periapsis_phi = self.phi + dphi * old_du / (old_du - self.du);
+        }
+        self.phi += dphi;
+    }
+
+    /// Steps forward until coordinate time reaches `t`. Never steps back.
+    pub fn advance_to(&mut self, t: f64) {
+        while self.t < t {
+            self.rk4_step(ORBIT_DPHI);
+        }
+    }
+
+    /// Coordinate-time rate of the orbital angle, `dphi/dt`, at the current
+    /// point.
+    pub fn angular_rate(&self) -> f64 {
+        1.0 / self.rhs(self.u, self.du).2
+    }
+}
+
+/// How a traced ray ended. The discriminants are part of the JS API, as the
+/// bytes returned by `BlackHoleRenderer::trace_batch`.
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(u8)]
 pub enum TraceResult {
-    HitBlackHole,
-    HitDisk,
-    HitObject,
-    Escaped,
-    MaxSteps,
+    HitBlackHole = 0,
+    HitDisk = 1,
+    HitObject = 2,
+    Escaped = 3,
+    MaxSteps = 4,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::{Camera, Handedness};
+    use crate::physics::{BlackHole, DEFAULT_ESCAPE_RADIUS, PhysicsConstants};
+
+    #[test]
+    fn retrace_error_stays_within_its_documented_bounds() {
+        let r_s = BlackHole::sagittarius_a().r_s;
+        let at = |x: f32, y: f32, z: f32| Vec3::new(x, y, z) * r_s as f32;
+
+        let far = retrace_error(at(-30.0, 20.0, 0.3), Vec3::X, r_s, 3000).unwrap();
+        assert!(far < 1e-4 * r_s, "far ray off by {} r_s", far / r_s);
+        let close = retrace_error(at(-10.0, 3.0, 0.0), Vec3::X, r_s, 15000).unwrap();
+        assert!(close < 2e-3 * r_s, "close ray off by {} r_s", close / r_s);
+        assert!(close > far);
+        assert!(retrace_error(at(-10.0, 0.5, 0.0), Vec3::X, r_s, 15000).is_none());
+    }
+
+    #[test]
+    fn geometrized_units_deflect_rays_as_si_does() {
+        let si = BlackHole::sagittarius_a();
+        let geometrized = BlackHole::with_constants(
+            si.position,
+            si.constants
+                .convert_mass(si.mass, &PhysicsConstants::GEOMETRIZED),
+            PhysicsConstants::GEOMETRIZED,
+        );
+        assert!((geometrized.r_s / si.r_s - 1.0).abs() < 1e-12);
+
+        let (pos, right, _, forward) = Camera::new().basis();
+        let dir = forward + 0.2 * right;
+        let trace = |black_hole: &BlackHole| {
+            trace_like_shader(
+                pos,
+                dir,
+                black_hole.r_s,
+                DEFAULT_ESCAPE_RADIUS,
+                DEFAULT_MAX_STEPS,
+                &Disk::new(0.0, 0.0, 0.0),
+                Vec3::ZERO,
+                0.0,
+            )
+        };
+        let (a, b) = (trace(&si), trace(&geometrized));
+        assert_eq!(a.result, TraceResult::Escaped);
+        assert_eq!(a.result, b.result);
+        assert_eq!(a.steps, b.steps);
+        assert!(a.direction.angle_between(b.direction) < 1e-5);
+        // And the ray was bent
+        assert!(a.direction.angle_between(dir) > 1e-3);
+    }
+
+    #[test]
+    fn grazing_rays_wind_further_than_distant_ones() {
+        let r_s = BlackHole::sagittarius_a().r_s;
+        let (pos, _, up, forward) = Camera::new().basis();
+        let ray = |angle: f32| {
+            let dir = forward * angle.cos() + up * angle.sin();
+            trace_like_shader(
+                pos,
+                dir,
+                r_s,
+                DEFAULT_ESCAPE_RADIUS,
+                DEFAULT_MAX_STEPS,
+                &Disk::new(0.0, 0.0, 0.0),
+                Vec3::ZERO,
+                0.0,
+            )
+        };
+
+        // Bisect for the edge of the shadow
+        let (mut captured, mut escaped) = (0.01, 0.5);
+        for _ in 0..24 {
+            let mid = 0.5 * (captured + escaped);
+            if ray(mid).result == TraceResult::HitBlackHole {
+                captured = mid;
+            } else {
+                escaped = mid;
+            }
+        }
+        let grazing = ray(escaped * 1.001);
+        let distant = ray(0.5);
+        assert_eq!(grazing.result, TraceResult::Escaped);
+        assert_eq!(distant.result, TraceResult::Escaped);
+        assert!(
+            grazing.winding > distant.winding + 0.1,
+            "grazing {} vs distant {}",
+            grazing.winding,
+            distant.winding
+        );
+    }
+
+    #[test]
+    fn magnification_is_near_one_far_from_the_hole() {
+        let r_s = BlackHole::sagittarius_a().r_s;
+        let mut camera = Camera::new();
+        camera.radius = camera.max_radius;
+        let (pos, right, up, forward) = camera.basis();
+        let tan_half_fov = camera.tan_half_fov(1.0);
+        let h = 1e-3;
+
+        for (x, y) in [(0.9, 0.9), (0.5, 0.0)] {
+            let mut directions = [DVec3::ZERO; 3];
+            let mut escapes = [DVec3::ZERO; 3];
+            for (i, (x, y)) in [(x, y), (x + h, y), (x, y + h)].into_iter().enumerate() {
+                let dir = (x * tan_half_fov * right - y * tan_half_fov * up + forward).normalize();
+                let traced = trace_like_shader(
+                    pos,
+                    dir,
+                    r_s,
+                    DEFAULT_ESCAPE_RADIUS,
+                    adaptive_steps(pos, r_s, DEFAULT_MAX_STEPS),
+                    &Disk::new(0.0, 0.0, 0.0),
+                    Vec3::ZERO,
+                    0.0,
+                );
+                assert_eq!(traced.result, TraceResult::Escaped);
+                directions[i] = dir.as_dvec3();
+                escapes[i] = traced.direction.as_dvec3();
+            }
+            let magnification = magnification(directions, escapes);
+            assert!((magnification - 1.0).abs() < 0.02, "{}", magnification);
+        }
+    }
+
+    /// The edge-on case `set_disk_multi_crossing` is for: from 4 degrees
+    /// above the disk, a ray aimed just under the hole passes down through
+    /// the near disk at about 9.5 r_s, bends under the hole and comes up
+    /// through the far side at about 4.8 r_s.
+    #[test]
+    fn edge_on_ray_crosses_the_near_and_far_disk() {
+        let r_s = BlackHole::sagittarius_a().r_s;
+        let elevation = 86f32.to_radians();
+        let pos = 20.0 * r_s as f32 * Vec3::new(elevation.sin(), elevation.cos(), 0.0);
+        let up = Vec3::new(-elevation.cos(), elevation.sin(), 0.0);
+        let dir = -pos.normalize() - 0.0625 * up - 0.005 * Vec3::Z;
+        let trace = |max_crossings| {
+            let mut disk = Disk::new(3.0 * r_s as f32, 12.0 * r_s as f32, 0.0);
+            disk.max_crossings = max_crossings;
+            let mut crossings = Vec::new();
+            let traced = trace_like_shader_with(
+                pos,
+                dir,
+                r_s,
+                DEFAULT_ESCAPE_RADIUS,
+                DEFAULT_MAX_STEPS,
+                &disk,
+                Vec3::ZERO,
+                0.0,
+                |step| {
+                    if let Some(t) = step.disk_crossing {
+                        crossings
+                            .push(((step.start + (step.end - step.start) * t) / r_s).as_vec3());
+                    }
+                },
+            );
+            (traced, crossings)
+        };
+        let radius = |p: Vec3| p.x.hypot(p.z);
+
+        // Opaque: the near disk hides everything behind it
+        let (near, crossings) = trace(1);
+        assert_eq!(near.result, TraceResult::HitDisk);
+        assert_eq!(crossings.len(), 1);
+        assert!(crossings[0].x > 0.0 && (8.5..10.5).contains(&radius(crossings[0])));
+
+        // Two crossings: the ray goes on through the near disk to the far side
+        let (far, crossings) = trace(2);
+        assert_eq!(far.result, TraceResult::HitDisk);
+        assert_eq!(crossings.len(), 2);
+        assert!(crossings[1].x < 0.0 && (4.0..6.0).contains(&radius(crossings[1])));
+        assert!((far.position / r_s as f32).distance(crossings[1]) < 1e-3);
+        assert!(far.path_length > near.path_length + 5.0 * r_s);
+
+        // With crossings to spare it passes both and escapes
+        let (escaped, crossings) = trace(3);
+        assert_eq!(escaped.result, TraceResult::Escaped);
+        assert_eq!(crossings.len(), 2);
+    }
+
+    #[test]
+    fn path_ends_where_the_trace_stops() {
+        let black_hole = BlackHole::sagittarius_a();
+        let disk = Disk::from_black_hole(&black_hole);
+        let pos = Camera::new().position();
+        let dir = (Vec3::new(0.0, 0.0, 4.2 * black_hole.r_s as f32) - pos).normalize();
+        let (trace, path) = trace_ray_path(
+            pos,
+            dir,
+            black_hole.r_s,
+            DEFAULT_ESCAPE_RADIUS,
+            DEFAULT_MAX_STEPS,
+            &disk,
+            Vec3::ZERO,
+            0.0,
+        );
+        assert_eq!(trace.result, TraceResult::HitDisk);
+        assert_eq!(path.len(), trace.steps as usize + 1);
+        assert_eq!(path[0], pos);
+        assert!(path[path.len() - 1].distance(trace.position) < 1.0);
+    }
+
+    #[test]
+    fn batch_matches_single_traces() {
+        let black_hole = BlackHole::sagittarius_a();
+        let r_s = black_hole.r_s;
+        let disk = Disk::from_black_hole(&black_hole);
+        let pos = Camera::new().position();
+        let radius = pos.length();
+        let directions = [
+            // Just off the hole's center, away from it, and at the disk's side
+            (-pos + 0.01 * radius * Vec3::Y).normalize(),
+            (pos + 0.3 * radius * Vec3::Y).normalize(),
+            (Vec3::new(0.0, 0.0, 4.2 * r_s as f32) - pos).normalize(),
+        ];
+        let flat: Vec<f32> = directions.iter().flat_map(|d| d.to_array()).collect();
+
+        let batch = trace_batch(
+            pos,
+            &flat,
+            r_s,
+            DEFAULT_ESCAPE_RADIUS,
+            DEFAULT_MAX_STEPS,
+            &disk,
+            Vec3::ZERO,
+            0.0,
+        );
+        let results: Vec<TraceResult> = batch.iter().map(|t| t.result).collect();
+        assert_eq!(
+            results,
+            [
+                TraceResult::HitBlackHole,
+                TraceResult::Escaped,
+                TraceResult::HitDisk
+            ]
+        );
+        for (batched, dir) in batch.iter().zip(directions) {
+            let single = trace_like_shader(
+                pos,
+                dir,
+                r_s,
+                DEFAULT_ESCAPE_RADIUS,
+                DEFAULT_MAX_STEPS,
+                &disk,
+                Vec3::ZERO,
+                0.0,
+            );
+            assert_eq!(batched.result, single.result);
+            assert_eq!(batched.position, single.position);
+        }
+    }
+
+    #[test]
+    fn pulse_photons_cover_c_per_unit_time_far_from_the_hole() {
+        let origin = Vec3::new(1e13, 0.0, 0.0);
+        let mut pulse = LightPulse::emit(origin, 16, 1.0, 1e10, 5.0);
+        pulse.advance_to(15.0, 3e8, 1e10, 1e15);
+        assert_eq!(pulse.photons.len(), 16);
+        for ray in &pulse.photons {
+            let travelled = ray.position().distance(origin.as_dvec3());
+            assert!((travelled / 3e9 - 1.0).abs() < 0.01, "{}", travelled);
+        }
+
+        // Going back in time leaves the photons where they are
+        let before = pulse.photons[0].position();
+        pulse.advance_to(10.0, 3e8, 1e10, 1e15);
+        assert_eq!(pulse.photons[0].position(), before);
+        assert_eq!(pulse.time, 15.0);
+    }
+
+    #[test]
+    fn disk_crossing_is_on_the_equator_and_bent_toward_the_hole() {
+        let r_s = BlackHole::sagittarius_a().r_s;
+        let disk = Disk::new(3.0 * r_s as f32, 12.0 * r_s as f32, 0.0);
+        // Straight down from 30 r_s above the disk at `x` r_s from the axis
+        let from = |x: f32| {
+            trace_to_disk(
+                Vec3::new(x, 30.0, 0.0) * r_s as f32,
+                -Vec3::Y,
+                r_s,
+                DEFAULT_ESCAPE_RADIUS,
+                DEFAULT_MAX_STEPS,
+                &disk,
+            )
+        };
+
+        let (point, direction) = from(8.0).unwrap();
+        let point = point / r_s as f32;
+        assert!(point.y.abs() < 1e-4 && point.z.abs() < 1e-4, "{:?}", point);
+        assert!((7.95..8.0).contains(&point.x), "crossed at {} r_s", point.x);
+        assert!((direction.length() - 1.0).abs() < 1e-5);
+        assert!(direction.y < -0.999 && direction.x < 0.0);
+
+        // Closer in, the ray is bent further
+        let (_, closer) = from(5.0).unwrap();
+        assert!(closer.x < direction.x);
+
+        // Captured before the disk, or missing its outer edge
+        assert!(from(0.3).is_none());
+        assert!(from(14.0).is_none());
+    }
+
+    #[test]
+    fn left_handed_camera_mirrors_the_traced_direction() {
+        let mut camera = Camera::new();
+        camera.azimuth = 0.7;
+        let r_s = BlackHole::sagittarius_a().r_s;
+        let trace = |camera: &Camera| {
+            let (pos, right, up, forward) = camera.basis();
+            trace_like_shader(
+                pos,
+                forward + 0.2 * right + 0.1 * up,
+                r_s,
+                DEFAULT_ESCAPE_RADIUS,
+                DEFAULT_MAX_STEPS,
+                &Disk::new(0.0, 0.0, 0.0),
+                Vec3::ZERO,
+                0.0,
+            )
+        };
+        let right_handed = trace(&camera);
+        camera.handedness = Handedness::LeftHanded;
+        let left_handed = trace(&camera);
+
+        assert_eq!(right_handed.result, TraceResult::Escaped);
+        assert_eq!(left_handed.result, right_handed.result);
+        assert_eq!(left_handed.steps, right_handed.steps);
+        let mirrored = Handedness::LeftHanded.apply(right_handed.direction);
+        assert!(left_handed.direction.angle_between(mirrored) < 1e-5);
+        assert!(right_handed.direction.z.abs() > 0.1);
+        assert!(left_handed.direction.angle_between(right_handed.direction) > 0.1);
+    }
+
+    #[test]
+    fn planet_just_above_the_disk_hides_it() {
+        let r_s = BlackHole::sagittarius_a().r_s;
+        let disk = Disk::new(3.0 * r_s as f32, 12.0 * r_s as f32, 0.0);
+        let pos = Vec3::new(8.0, 30.0, 0.0) * r_s as f32;
+        let (crossing, _) = trace_to_disk(
+            pos,
+            -Vec3::Y,
+            r_s,
+            DEFAULT_ESCAPE_RADIUS,
+            DEFAULT_MAX_STEPS,
+            &disk,
+        )
+        .unwrap();
+        // A planet smaller than a step near the disk, centered a little
+        // above or below where the ray crosses it
+        let radius = 0.015 * r_s as f32;
+        let trace = |height: f32| {
+            trace_like_shader(
+                pos,
+                -Vec3::Y,
+                r_s,
+                DEFAULT_ESCAPE_RADIUS,
+                DEFAULT_MAX_STEPS,
+                &disk,
+                crossing + Vec3::Y * height * r_s as f32,
+                radius,
+            )
+        };
+
+        let above = trace(0.02);
+        assert_eq!(above.result, TraceResult::HitObject);
+        assert!(above.position.y > 0.0);
+        let below = trace(-0.02);
+        assert_eq!(below.result, TraceResult::HitDisk);
+        assert!(below.position.distance(crossing) < 1e-4 * r_s as f32);
+    }
 }
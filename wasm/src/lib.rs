@@ -6,9 +6,19 @@
 #![allow(clippy::manual_div_ceil)]
 #![allow(clippy::wrong_self_convention)]
 
+mod buffers;
 mod camera;
 mod integrator;
 mod physics;
+mod readback;
+mod recording;
+mod reference;
+mod scene;
+mod settings;
+mod shader_source;
+mod share;
+mod step_budget;
+mod thresholds;
 
 use wasm_bindgen::prelude::*;
 use web_sys::HtmlCanvasElement;
@@ -17,8 +27,167 @@ use wgpu::{
     RequestAdapterOptions, Surface, SurfaceConfiguration, TextureUsages, TextureViewDescriptor,
 };
 
-use camera::Camera;
-use physics::{BlackHole, Disk, Planet};
+use buffers::BufferKind;
+use camera::{
+    Camera, CameraUniform, Handedness, MAX_FOV, MIN_FOV, POLE_EPSILON, Projection, VIEWPOINTS,
+    Viewpoint,
+};
+use glam::{DVec3, Vec2, Vec3};
+use integrator::{DEFAULT_MAX_STEPS, LightPulse};
+use physics::{
+    BlackHole, Disk, DiskColorKey, DiskColorMode, DiskStyle, PhysicsConstants, Planet, PlanetModel,
+    SOLAR_MASS,
+};
+use recording::RecordedFrames;
+use scene::{Capabilities, FrameStats, PixelInspection, SceneInfo};
+use settings::{Background, BackgroundFiltering, RenderSettings, ViewMode};
+use share::ViewParams;
+use step_budget::StepBudgetMonitor;
+use thresholds::ThresholdWatcher;
+
+/// Simulated seconds per wall-clock second. A real orbit a few r_s out from
+/// Sagittarius A* takes tens of minutes, so the clock is sped up to make the
+/// planet visibly move.
+const DEFAULT_TIME_SCALE: f64 = 1000.0;
+
+/// The compute shader used unless `set_shader_source` replaces it.
+const BUILTIN_COMPUTE_SHADER: &str = include_str!("shader.wgsl");
+
+/// Most step points of a `set_debug_ray` path drawn, matching
+/// `MAX_DEBUG_POINTS` in the display shader. Longer paths are decimated.
+const MAX_DEBUG_POINTS: usize = 64;
+
+/// A ray picked by `set_debug_ray`, fixed in world space so the camera can
+/// orbit to look at its path.
+#[derive(Debug, Clone)]
+struct DebugRay {
+    origin: Vec3,
+    direction: Vec3,
+    /// `r_s` the path was traced for; a mass change retraces it.
+    r_s: f64,
+    /// World positions of the kept step points, from the camera outward.
+    points: Vec<Vec3>,
+}
+
+/// The uniform buffers written every frame by `update_uniforms`.
+#[derive(Debug, Clone, Copy)]
+enum UniformSlot {
+    Camera,
+    Disk,
+    Planet,
+    Params,
+    Display,
+    Motion,
+}
+
+/// Projection code for one-shot equirectangular captures, past the ones
+/// `Projection` exposes for live rendering.
+const PANORAMA_PROJECTION: f32 = 2.0;
+
+/// Bounds for `set_integration_quality`.
+const MIN_TRACE_STEPS: u32 = 100;
+const MAX_TRACE_STEPS: u32 = 20000;
+
+/// Photons per emitted light pulse, and the cap across all live pulses.
+const PULSE_PHOTONS: usize = 128;
+const MAX_PULSE_PHOTONS: usize = 512;
+
+/// Most wall-clock time, in seconds, the per-frame simulation clock advances
+/// in one frame, so the planet doesn't jump after the tab was in the
+/// background.
+const MAX_FRAME_DELTA: f64 = 0.1;
+/// Most wall-clock time, in seconds, fed into the fixed-timestep accumulator
+/// per frame. After a tab stall the simulation slows down briefly instead of
+/// running thousands of catch-up ticks.
+const MAX_ACCUMULATED_TIME: f64 = 0.25;
+
+/// Points sampled around the planet's orbit for the orbit overlay, matching
+/// `ORBIT_POINTS` in `shader.wgsl`.
+const ORBIT_OVERLAY_POINTS: usize = 64;
+/// Half-width of the orbit overlay's line as a fraction of the semi-major
+/// axis.
+const ORBIT_LINE_HALF_WIDTH: f32 = 0.006;
+
+/// Entries in the disk color lookup table.
+const DISK_LUT_SIZE: usize = 256;
+
+/// Most hotspots the shader draws, matching `MAX_HOTSPOTS` in `shader.wgsl`.
+const MAX_DISK_HOTSPOTS: u32 = 16;
+
+/// Upper bound on frames captured by one `render_n_and_capture` or
+/// `record_session` call.
+const MAX_CAPTURE_FRAMES: u32 = 240;
+
+/// Frames the surface may queue ahead of the display, and the range
+/// `set_frame_latency` accepts. Backends honor 1 to 3.
+const DEFAULT_FRAME_LATENCY: u32 = 2;
+const MIN_FRAME_LATENCY: u32 = 1;
+const MAX_FRAME_LATENCY: u32 = 3;
+
+/// Cap on `magnification`, which diverges on the critical curve.
+const MAX_MAGNIFICATION: f64 = 1000.0;
+/// NDC offset between the neighboring rays `magnification` differences.
+const MAGNIFICATION_STEP_NDC: f32 = 1e-3;
+
+/// Cap on each direction of `retrace_error_rs`, keeping the call short.
+const MAX_RETRACE_STEPS: u32 = 100_000;
+
+/// Largest `capture_stereo` eye separation, as a fraction of the camera
+/// radius.
+const MAX_EYE_SEPARATION: f32 = 0.5;
+
+/// Default rate, per wall-clock second, at which the hole's mass closes the
+/// gap to the value last passed to `set_black_hole_mass`.
+const DEFAULT_MASS_TRANSITION_SPEED: f64 = 8.0;
+
+/// Range of `set_background_white_balance`, where the blackbody fit holds.
+const MIN_WHITE_BALANCE: f32 = 1000.0;
+const MAX_WHITE_BALANCE: f32 = 40000.0;
+
+/// Closest the camera may sit to the hole, in units of its current r_s.
+const CAMERA_MIN_RADIUS_RS: f32 = 1.1;
+/// Most disk crossings `set_disk_multi_crossing` accepts. A ray only loops
+/// the photon sphere a few times before the step budget runs out.
+const MAX_DISK_CROSSINGS: u32 = 8;
+
+/// With frame reuse on, planet movement below this fraction of its radius
+/// doesn't trigger a new trace.
+const REUSE_PLANET_TOLERANCE: f32 = 0.01;
+
+/// Width of the star map copy kept for CPU renders.
+const REFERENCE_SKY_WIDTH: u32 = 1024;
+/// Largest side `render_reference` accepts; the CPU tracer is slow.
+const MAX_REFERENCE_SIZE: u32 = 256;
+
+/// How finished frames are handed to the display.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PresentMode {
+    /// Wait for vertical blank: no tearing, lowest power.
+    Fifo,
+    /// Replace any queued frame with the newest: no tearing, lower latency.
+    Mailbox,
+    /// Present at once: lowest latency, may tear. For benchmarking.
+    Immediate,
+}
+
+impl PresentMode {
+    fn to_wgpu(self) -> wgpu::PresentMode {
+        match self {
+            PresentMode::Fifo => wgpu::PresentMode::Fifo,
+            PresentMode::Mailbox => wgpu::PresentMode::Mailbox,
+            PresentMode::Immediate => wgpu::PresentMode::Immediate,
+        }
+    }
+
+    fn from_wgpu(mode: wgpu::PresentMode) -> Self {
+        match mode {
+            wgpu::PresentMode::Mailbox => PresentMode::Mailbox,
+            wgpu::PresentMode::Immediate | wgpu::PresentMode::AutoNoVsync => PresentMode::Immediate,
+            _ => PresentMode::Fifo,
+        }
+    }
+}
 
 #[wasm_bindgen(start)]
 pub fn init_panic_hook() {
@@ -33,31 +202,171 @@ pub struct BlackHoleRenderer {
     queue: Queue,
     surface: Surface<'static>,
     config: SurfaceConfiguration,
+    /// Present modes the surface supports, from the adapter's capabilities.
+    present_modes: Vec<wgpu::PresentMode>,
+    /// Graphics API behind the adapter; the browser's own WebGPU ignores
+    /// the requested frame latency.
+    backend: wgpu::Backend,
+    capabilities: Capabilities,
     render_pipeline: wgpu::RenderPipeline,
+    render_bind_group_layout: wgpu::BindGroupLayout,
     render_bind_group: wgpu::BindGroup,
+    sampler: wgpu::Sampler,
     compute_pipeline: wgpu::ComputePipeline,
+    compute_pipeline_layout: wgpu::PipelineLayout,
+    compute_shader: wgpu::ShaderModule,
+    compute_bind_group_layout: wgpu::BindGroupLayout,
     compute_bind_group: wgpu::BindGroup,
     output_texture: wgpu::Texture,
     camera_buffer: wgpu::Buffer,
     disk_buffer: wgpu::Buffer,
     planet_buffer: wgpu::Buffer,
+    params_buffer: wgpu::Buffer,
+    pulse_buffer: wgpu::Buffer,
+    /// Disk base color by radius, rebuilt when `disk_lut_key` no longer
+    /// matches the disk or is cleared.
+    disk_lut_buffer: wgpu::Buffer,
+    disk_lut_key: Option<DiskColorKey>,
+    /// Ray outcome counters for `frame_stats`, cleared before every dispatch.
+    stats_buffer: wgpu::Buffer,
+    display_buffer: wgpu::Buffer,
+    /// Image from `set_overlay`, or a transparent 1x1 placeholder.
+    overlay_texture: wgpu::Texture,
+    overlay_enabled: bool,
+    /// Ray picked by `set_debug_ray`, drawn as dots at its integration steps.
+    debug_ray: Option<DebugRay>,
+    /// Per-pixel motion vectors, full compute size while enabled and a 1x1
+    /// placeholder otherwise.
+    motion_texture: wgpu::Texture,
+    motion_buffer: wgpu::Buffer,
+    motion_vectors: bool,
+    /// Per-pixel background UVs, full compute size while the lens map is
+    /// enabled and a 1x1 placeholder otherwise.
+    lens_texture: wgpu::Texture,
+    /// Camera position and basis, and planet position, as of the previous
+    /// traced frame.
+    prev_camera: Option<(Vec3, Vec3, Vec3, Vec3)>,
+    prev_planet_position: Vec3,
     background_texture: wgpu::Texture,
+    /// The star map scaled down to `REFERENCE_SKY_WIDTH` for
+    /// `render_reference` and `render_thumbnail`.
+    reference_sky: image::RgbaImage,
     camera: Camera,
+    /// Named radii whose crossings by the camera are reported by
+    /// `poll_events`.
+    thresholds: ThresholdWatcher,
+    /// How CPU-traced rays end, for warning when the step budget is short.
+    step_budget: StepBudgetMonitor,
     black_hole: BlackHole,
+    /// Mass the hole is easing toward, in its units; see
+    /// `set_black_hole_mass`.
+    target_mass: f64,
+    /// Rate per second at which the mass closes on `target_mass`; 0 snaps.
+    mass_transition_speed: f64,
+    last_mass_update: f64,
     disk: Disk,
     planet: Planet,
-    start_time: f64,
+    settings: RenderSettings,
+    /// Integration step budget per ray, shared by the shader's `NSTEPS`
+    /// override and the CPU traces made through `trace_like_view`.
+    max_steps: u32,
+    pulses: Vec<LightPulse>,
+    /// Simulation time without a fixed timestep, advanced once per frame by
+    /// the clamped wall-clock delta.
+    live_sim_time: f64,
+    /// Wall-clock seconds when `live_sim_time` last advanced.
+    last_live_clock_time: f64,
+    time_scale: f64,
+    /// Simulation time pinned by a scripted capture; `None` follows the wall
+    /// clock.
+    pinned_sim_time: Option<f64>,
+    /// Simulation time up to which the disk's precession has advanced.
+    last_precession_time: f64,
+    /// Wall-clock seconds per physics tick; `None` steps physics once per
+    /// frame from the wall clock.
+    fixed_timestep: Option<f64>,
+    tick_accumulator: f64,
+    last_tick_time: f64,
+    /// Simulation time as of the latest fixed tick.
+    ticked_sim_time: f64,
+    /// Planet position one tick before the current one, for interpolation.
+    planet_previous_tick: Vec3,
+    planet_visible: bool,
+    /// Simulated time the planet has spent hidden, subtracted from its orbit
+    /// clock so it reappears where it disappeared.
+    planet_time_offset: f64,
+    planet_hidden_at: f64,
+    /// Skip the compute pass when nothing it reads has changed.
+    frame_reuse: bool,
+    /// Compute uniforms of the frame being prepared, minus the planet
+    /// position and anything that changes without changing the image.
+    trace_inputs: Vec<f32>,
+    /// `trace_inputs` and planet position of the image in `output_texture`;
+    /// `None` when it must be traced again regardless.
+    last_traced: Option<(Vec<f32>, Vec3)>,
+    last_frame_time: f64,
+    fps: f64,
+    frame_count: u64,
     compute_width: u32,
     compute_height: u32,
+    css_width: u32,
+    css_height: u32,
+    pixel_ratio: f32,
+    /// Set by `destroy`; every GPU entry point checks it first.
+    destroyed: bool,
 }
 
 #[wasm_bindgen]
 impl BlackHoleRenderer {
     pub async fn new(canvas: HtmlCanvasElement) -> Result<BlackHoleRenderer, JsValue> {
+        Self::create(canvas, None).await
+    }
+
+    /// Like `new`, but on the adapter at `index` in `list_adapters`, for
+    /// machines where the default pick is the wrong GPU or a software
+    /// renderer. Falls back to the default selection, with a warning, if
+    /// that adapter can't be obtained for the canvas.
+    pub async fn new_with_adapter(
+        canvas: HtmlCanvasElement,
+        index: u32,
+    ) -> Result<BlackHoleRenderer, JsValue> {
+        Self::create(canvas, Some(index)).await
+    }
+
+    /// Describes the adapters the browser offers, as `"name (backend)"`,
+    /// indexed for `new_with_adapter`. The web exposes no full enumeration,
+    /// so this asks for a high-performance, a low-power and a fallback
+    /// adapter and lists each distinct one. Browsers may hide adapter names,
+    /// in which case the device type is shown instead.
+    pub async fn list_adapters() -> Result<Vec<String>, JsValue> {
+        let instance = Instance::new(InstanceDescriptor {
+            backends: Backends::all(),
+            ..Default::default()
+        });
+        Ok(request_adapters(&instance)
+            .await
+            .iter()
+            .map(|adapter| describe_adapter(&adapter.get_info()))
+            .collect())
+    }
+
+    async fn create(
+        canvas: HtmlCanvasElement,
+        adapter_index: Option<u32>,
+    ) -> Result<BlackHoleRenderer, JsValue> {
         log::info!("Initializing Black Hole Renderer");
 
-        let width = canvas.width();
-        let height = canvas.height();
+        // A canvas that hasn't been laid out yet reports 0x0, which the
+        // surface can't be configured with. Start at 1x1; the first `resize`
+        // reconfigures the surface at the real size.
+        let (width, height) = surface_size(canvas.width(), canvas.height(), 1.0, u32::MAX);
+        if canvas.width() == 0 || canvas.height() == 0 {
+            log::warn!(
+                "Canvas is {}x{} at construction; rendering at 1x1 until resize",
+                canvas.width(),
+                canvas.height()
+            );
+        }
 
         let instance = Instance::new(InstanceDescriptor {
             backends: Backends::all(),
@@ -80,16 +389,40 @@ impl BlackHoleRenderer {
             }
         };
 
-        let adapter = instance
-            .request_adapter(&RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::HighPerformance,
-                compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
-            })
-            .await
-            .ok_or_else(|| JsValue::from_str("Failed to find an appropriate adapter"))?;
+        let requested = match adapter_index {
+            // Enumerated as `list_adapters` does, so the index picks the
+            // adapter it listed
+            Some(index) => {
+                let mut adapters = request_adapters(&instance).await;
+                if (index as usize) >= adapters.len() {
+                    log::warn!(
+                        "Adapter {} is not available ({} found); using the default selection",
+                        index,
+                        adapters.len()
+                    );
+                    None
+                } else {
+                    let adapter = adapters.swap_remove(index as usize);
+                    if adapter.is_surface_supported(&surface) {
+                        Some(adapter)
+                    } else {
+                        log::warn!(
+                            "Adapter {} can't present to this canvas; using the default selection",
+                            describe_adapter(&adapter.get_info())
+                        );
+                        None
+                    }
+                }
+            }
+            None => None,
+        };
+        let adapter = match requested {
+            Some(adapter) => adapter,
+            None => request_default_adapter(&instance, &surface).await?,
+        };
 
         log::info!("Adapter info: {:?}", adapter.get_info());
+        let backend = adapter.get_info().backend;
 
         let (device, queue) = adapter
             .request_device(
@@ -123,10 +456,13 @@ impl BlackHoleRenderer {
             present_mode: surface_caps.present_modes[0],
             alpha_mode: surface_caps.alpha_modes[0],
             view_formats: vec![],
-            desired_maximum_frame_latency: 2,
+            desired_maximum_frame_latency: DEFAULT_FRAME_LATENCY,
         };
 
         surface.configure(&device, &config);
+        let present_modes = surface_caps.present_modes.clone();
+        let capabilities = gather_capabilities(&adapter, &surface_caps);
+        log::info!("Capabilities: {:?}", capabilities);
 
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Display Shader"),
@@ -153,6 +489,26 @@ impl BlackHoleRenderer {
                         ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
                         count: None,
                     },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
                 ],
             });
 
@@ -209,24 +565,11 @@ impl BlackHoleRenderer {
 
         let compute_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Compute Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("shader.wgsl").into()),
+            source: wgpu::ShaderSource::Wgsl(BUILTIN_COMPUTE_SHADER.into()),
         });
 
         // Create output texture
-        let output_texture = device.create_texture(&wgpu::TextureDescriptor {
-            label: Some("Output Texture"),
-            size: wgpu::Extent3d {
-                width: compute_width,
-                height: compute_height,
-                depth_or_array_layers: 1,
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8Unorm,
-            usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::TEXTURE_BINDING,
-            view_formats: &[],
-        });
+        let output_texture = create_output_texture(&device, compute_width, compute_height);
 
         // Create camera buffer (align to 16 bytes)
         let camera_buffer = device.create_buffer(&wgpu::BufferDescriptor {
@@ -252,6 +595,50 @@ impl BlackHoleRenderer {
             mapped_at_creation: false,
         });
 
+        let settings = RenderSettings::default();
+
+        // Create params buffer
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Params Buffer"),
+            size: (settings.uniform_data().len() * 4) as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        // Create pulse buffer, grown on demand as photons are emitted
+        let pulse_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Pulse Buffer"),
+            size: 32,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let motion_texture = create_motion_texture(&device, 1, 1);
+        let lens_texture = create_lens_texture(&device, 1, 1);
+        let motion_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Motion Buffer"),
+            size: 96,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        // Create disk color lookup table, filled by the first update_uniforms
+        let disk_lut_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Disk LUT Buffer"),
+            size: (DISK_LUT_SIZE * 16) as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let stats_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Stats Buffer"),
+            size: 32,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
         // Load background texture from embedded data
         log::info!("Loading background texture...");
         let bg_bytes = include_bytes!("../../public/milkyway.jpg");
@@ -299,6 +686,12 @@ impl BlackHoleRenderer {
             },
         );
         log::info!("Background texture ready");
+        let sky_width = REFERENCE_SKY_WIDTH.min(bg_width);
+        let reference_sky = image::imageops::thumbnail(
+            &bg_img,
+            sky_width,
+            (bg_height * sky_width / bg_width).max(1),
+        );
 
         let compute_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
@@ -354,39 +747,95 @@ impl BlackHoleRenderer {
                         },
                         count: None,
                     },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 5,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 6,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 7,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::StorageTexture {
+                            access: wgpu::StorageTextureAccess::WriteOnly,
+                            format: wgpu::TextureFormat::Rgba32Float,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 8,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 9,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 10,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: false },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 11,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::StorageTexture {
+                            access: wgpu::StorageTextureAccess::WriteOnly,
+                            format: wgpu::TextureFormat::Rg32Float,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                        },
+                        count: None,
+                    },
                 ],
             });
 
-        let compute_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Compute Bind Group"),
-            layout: &compute_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(
-                        &output_texture.create_view(&wgpu::TextureViewDescriptor::default()),
-                    ),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: camera_buffer.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: disk_buffer.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 3,
-                    resource: planet_buffer.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 4,
-                    resource: wgpu::BindingResource::TextureView(
-                        &background_texture.create_view(&wgpu::TextureViewDescriptor::default()),
-                    ),
-                },
-            ],
-        });
+        let compute_bind_group = create_compute_bind_group(
+            &device,
+            &compute_bind_group_layout,
+            &output_texture,
+            &camera_buffer,
+            &disk_buffer,
+            &planet_buffer,
+            &background_texture,
+            &params_buffer,
+            &pulse_buffer,
+            &motion_texture,
+            &motion_buffer,
+            &disk_lut_buffer,
+            &stats_buffer,
+            &lens_texture,
+        );
 
         let compute_pipeline_layout =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
@@ -395,17 +844,24 @@ impl BlackHoleRenderer {
                 push_constant_ranges: &[],
             });
 
-        let compute_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-            label: Some("Compute Pipeline"),
-            layout: Some(&compute_pipeline_layout),
-            module: &compute_shader,
-            entry_point: Some("main"),
-            compilation_options: Default::default(),
-            cache: None,
-        });
+        let max_steps = DEFAULT_MAX_STEPS;
+        let compute_pipeline = create_compute_pipeline(
+            &device,
+            &compute_pipeline_layout,
+            &compute_shader,
+            max_steps,
+        );
 
         log::info!("Compute pipeline created");
 
+        // Create display buffer for screen-space overlays
+        let display_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Display Buffer"),
+            size: 64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
         // Create sampler and render bind group
         let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
             label: Some("Texture Sampler"),
@@ -418,27 +874,30 @@ impl BlackHoleRenderer {
             ..Default::default()
         });
 
-        let render_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Render Bind Group"),
-            layout: &render_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(
-                        &output_texture.create_view(&wgpu::TextureViewDescriptor::default()),
-                    ),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&sampler),
-                },
-            ],
-        });
+        let overlay_texture = create_overlay_texture(&device, &queue, &[0; 4], 1, 1);
+        let render_bind_group = create_render_bind_group(
+            &device,
+            &render_bind_group_layout,
+            &output_texture,
+            &sampler,
+            &display_buffer,
+            &overlay_texture,
+        );
 
-        let camera = Camera::new();
+        let mut camera = Camera::new();
+        // Nothing is traced past the escape radius, so overlays needn't
+        // draw past it either
+        camera.set_clip_planes(camera.near, settings.escape_radius as f32);
         let black_hole = BlackHole::sagittarius_a();
-        let disk = Disk::default_accretion_disk();
-        let planet = Planet::new_elliptical_orbit(7.0, 0.5, 0.4, 8.54e36);
+
+        let mut thresholds = ThresholdWatcher::default();
+        thresholds.add("horizon", black_hole.r_s);
+        thresholds.add("photon_sphere", 1.5 * black_hole.r_s);
+        thresholds.add("isco", black_hole.isco_radius());
+        thresholds.observe(camera.radius as f64);
+        let disk = Disk::from_black_hole(&black_hole);
+        let planet = Planet::new_elliptical_orbit(7.0, 0.5, 0.4, &black_hole)
+            .map_err(|e| JsValue::from_str(&e))?;
 
         log::info!("Black hole: r_s = {} meters", black_hole.r_s);
         log::info!("Camera radius: {} meters", camera.radius);
@@ -453,27 +912,140 @@ impl BlackHoleRenderer {
             queue,
             surface,
             config,
+            present_modes,
+            backend,
+            capabilities,
             render_pipeline,
+            render_bind_group_layout,
             render_bind_group,
+            sampler,
             compute_pipeline,
+            compute_pipeline_layout,
+            compute_shader,
+            compute_bind_group_layout,
             compute_bind_group,
             output_texture,
             camera_buffer,
             disk_buffer,
             planet_buffer,
+            params_buffer,
+            pulse_buffer,
+            disk_lut_buffer,
+            disk_lut_key: None,
+            stats_buffer,
+            display_buffer,
+            overlay_texture,
+            overlay_enabled: false,
+            debug_ray: None,
+            motion_texture,
+            motion_buffer,
+            motion_vectors: false,
+            lens_texture,
+            prev_camera: None,
+            prev_planet_position: Vec3::ZERO,
             background_texture,
+            reference_sky,
             camera,
+            thresholds,
+            step_budget: StepBudgetMonitor::default(),
             black_hole,
+            target_mass: black_hole.mass,
+            mass_transition_speed: DEFAULT_MASS_TRANSITION_SPEED,
+            last_mass_update: js_sys::Date::now() / 1000.0,
             disk,
             planet,
-            start_time: js_sys::Date::now() / 1000.0,
+            settings,
+            max_steps,
+            pulses: Vec::new(),
+            live_sim_time: 0.0,
+            last_live_clock_time: js_sys::Date::now() / 1000.0,
+            time_scale: DEFAULT_TIME_SCALE,
+            pinned_sim_time: None,
+            last_precession_time: 0.0,
+            fixed_timestep: None,
+            tick_accumulator: 0.0,
+            last_tick_time: 0.0,
+            ticked_sim_time: 0.0,
+            planet_previous_tick: Vec3::ZERO,
+            planet_visible: true,
+            planet_time_offset: 0.0,
+            planet_hidden_at: 0.0,
+            frame_reuse: false,
+            trace_inputs: Vec::new(),
+            last_traced: None,
+            last_frame_time: js_sys::Date::now() / 1000.0,
+            fps: 0.0,
+            frame_count: 0,
             compute_width,
             compute_height,
+            css_width: width,
+            css_height: height,
+            pixel_ratio: 1.0,
+            destroyed: false,
         })
     }
 
+    /// Like `new`, but opens on the given view instead of `Camera::new()`'s,
+    /// so the first frame is already the intended shot. Angles are in
+    /// radians and `fov` in degrees. The elevation is clamped to the
+    /// elevation limits, `radius_rs` as in `set_camera_radius_rs` and `fov`
+    /// to the supported range; non-finite values are rejected.
+    pub async fn new_with_camera(
+        canvas: HtmlCanvasElement,
+        azimuth: f32,
+        elevation: f32,
+        radius_rs: f32,
+        fov: f32,
+    ) -> Result<BlackHoleRenderer, JsValue> {
+        if ![azimuth, elevation, radius_rs, fov]
+            .iter()
+            .all(|v| v.is_finite())
+        {
+            return Err(JsValue::from_str(&format!(
+                "Camera values must be finite, got azimuth {}, elevation {}, radius {} r_s, fov {}",
+                azimuth, elevation, radius_rs, fov
+            )));
+        }
+        if radius_rs <= 0.0 {
+            return Err(JsValue::from_str(&format!(
+                "Camera radius must be a positive multiple of r_s, got {}",
+                radius_rs
+            )));
+        }
+
+        let mut renderer = Self::new(canvas).await?;
+        renderer.camera.azimuth = azimuth.rem_euclid(2.0 * std::f32::consts::PI);
+        renderer.camera.elevation =
+            elevation.clamp(renderer.camera.min_elevation, renderer.camera.max_elevation);
+        renderer.camera.fov = fov.clamp(MIN_FOV, MAX_FOV);
+        renderer.set_camera_radius_rs(radius_rs)?;
+        // Starting inside a threshold is not a crossing
+        renderer.thresholds.drain();
+        log::info!(
+            "Initial camera: az {:.3}, el {:.3}, r {:.2} r_s, fov {}",
+            renderer.camera.azimuth,
+            renderer.camera.elevation,
+            renderer.camera_radius_rs(),
+            renderer.camera.fov
+        );
+        Ok(renderer)
+    }
+
     pub fn render(&mut self) -> Result<(), JsValue> {
-        self.update_uniforms();
+        self.ensure_alive()?;
+        self.update_uniforms()?;
+
+        let now = js_sys::Date::now() / 1000.0;
+        let frame_time = now - self.last_frame_time;
+        self.last_frame_time = now;
+        if frame_time > 0.0 {
+            // Exponential smoothing so the HUD readout doesn't flicker
+            self.fps = if self.fps > 0.0 {
+                self.fps * 0.9 + (1.0 / frame_time) * 0.1
+            } else {
+                1.0 / frame_time
+            };
+        }
 
         let output = self.surface.get_current_texture().map_err(|e| {
             JsValue::from_str(&format!("Failed to acquire next swap chain: {:?}", e))
@@ -491,20 +1063,14 @@ impl BlackHoleRenderer {
 
         // Note: clear_texture clears to (0,0,0,0) which is transparent
         // The compute shader will write opaque colors to all pixels
-
-        // Compute pass
-        {
-            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
-                label: Some("Compute Pass"),
-                timestamp_writes: None,
-            });
-
-            compute_pass.set_pipeline(&self.compute_pipeline);
-            compute_pass.set_bind_group(0, &self.compute_bind_group, &[]);
-
-            let workgroup_count_x = (self.compute_width + 15) / 16;
-            let workgroup_count_y = (self.compute_height + 15) / 16;
-            compute_pass.dispatch_workgroups(workgroup_count_x, workgroup_count_y, 1);
+        if !self.can_reuse_frame() {
+            self.encode_compute_pass(
+                &mut encoder,
+                &self.compute_bind_group,
+                self.compute_width,
+                self.compute_height,
+            );
+            self.last_traced = Some((self.trace_inputs.clone(), self.prev_planet_position));
         }
 
         // Render pass - display the computed texture
@@ -536,117 +1102,3497 @@ impl BlackHoleRenderer {
 
         self.queue.submit(std::iter::once(encoder.finish()));
         output.present();
+        self.frame_count += 1;
 
         Ok(())
     }
 
-    fn update_uniforms(&mut self) {
-        use glam::Vec3;
-
-        let pos = self.camera.position();
-        let target = self.camera.target;
-        let up = Vec3::Y;
+    /// Rough relative GPU cost of tracing a frame with the current settings,
+    /// for warning before a change is likely to be slow: 1.0 is the default
+    /// 800x600 compute resolution at 2000 steps with the camera close enough
+    /// to use the whole step budget, and cost scales linearly from there. A
+    /// heuristic, not a measurement; what counts as slow depends on the GPU.
+    ///
+    /// The weighting:
+    /// - compute pixels, relative to 800x600;
+    /// - the steps each ray may take: the step budget, cut by the shader's
+    ///   distance factor (down to a tenth for far cameras);
+    /// - edge refinement adds 30%, for four extra samples on edge pixels;
+    /// - jets add 15% and planet lensing 10%, both tested every step;
+    /// - hotspots add up to 10% at the maximum count, for disk hits;
+    /// - motion vectors and the lens map add 5% each for their extra writes;
+    /// - high precision adds 50%, for the double-single arithmetic each step.
+    pub fn estimated_cost(&self) -> f32 {
+        let pixels = (self.compute_width * self.compute_height) as f32 / (800.0 * 600.0);
 
-        let forward = (target - pos).normalize();
-        let right = forward.cross(up).normalize();
-        let up = right.cross(forward).normalize();
+        let r0 = self.camera_basis().0.length() as f64 / (self.black_hole.r_s / 2.0);
+        let distance_factor = (100.0 / r0).clamp(0.1, 1.0) as f32;
+        let steps = self.max_steps as f32 * distance_factor / DEFAULT_MAX_STEPS as f32;
 
-        let fov = 60.0f32;
-        let aspect = self.config.width as f32 / self.config.height as f32;
-        let tan_half_fov = (fov.to_radians() / 2.0).tan();
-
-        let camera_data: Vec<f32> = vec![
-            pos.x,
-            pos.y,
-            pos.z,
-            0.0,
-            right.x,
-            right.y,
-            right.z,
-            0.0,
-            up.x,
-            up.y,
-            up.z,
-            0.0,
-            forward.x,
-            forward.y,
-            forward.z,
-            0.0,
-            tan_half_fov,
-            aspect,
-            if self.camera.moving { 1.0 } else { 0.0 },
-            0.0,
-        ];
+        let settings = &self.settings;
+        let mut features = 1.0;
+        if settings.edge_refinement {
+            features += 0.3;
+        }
+        if settings.jets_enabled && !settings.background_only {
+            features += 0.15;
+        }
+        if settings.planet_lensing && self.planet_visible && !settings.background_only {
+            features += 0.1;
+        }
+        features += 0.1 * self.disk.hotspot_count as f32 / MAX_DISK_HOTSPOTS as f32;
+        if self.motion_vectors {
+            features += 0.05;
+        }
+        if settings.lens_map {
+            features += 0.05;
+        }
+        if settings.high_precision {
+            features += 0.5;
+        }
 
-        self.queue
-            .write_buffer(&self.camera_buffer, 0, bytemuck::cast_slice(&camera_data));
+        pixels * steps * features
+    }
 
-        let disk_data: Vec<f32> = vec![
-            self.disk.inner_radius,
-            self.disk.outer_radius,
-            0.0,
-            self.disk.thickness,
-        ];
+    /// Number of frames traced so far, counting both presented frames and
+    /// `render_to_bytes` captures.
+    pub fn frame_count(&self) -> f64 {
+        self.frame_count as f64
+    }
 
-        self.queue
-            .write_buffer(&self.disk_buffer, 0, bytemuck::cast_slice(&disk_data));
+    /// Renders `n` frames, advancing the simulation clock by exactly `sim_dt`
+    /// simulated seconds between them, and reads each one back. The promise
+    /// resolves to an `Array` of `Uint8Array`s of RGBA8 rows, so a short
+    /// animation can be reproduced from a single call.
+    ///
+    /// The first frame is at the current simulation time; afterwards the
+    /// live clock carries on from there. All frames are held in memory at
+    /// once, `n * width * height * 4` bytes in total (about 1 MB per frame at
+    /// 640x400), so `n` is capped at `MAX_CAPTURE_FRAMES`.
+    pub fn render_n_and_capture(
+        &mut self,
+        n: u32,
+        sim_dt: f64,
+    ) -> Result<js_sys::Promise, JsValue> {
+        self.ensure_alive()?;
+        if n == 0 || n > MAX_CAPTURE_FRAMES {
+            return Err(JsValue::from_str(&format!(
+                "Frame count must be between 1 and {}, got {}",
+                MAX_CAPTURE_FRAMES, n
+            )));
+        }
+        if !sim_dt.is_finite() {
+            return Err(JsValue::from_str("Simulation time step must be finite"));
+        }
 
-        // Update planet orbit
-        let current_time = js_sys::Date::now() / 1000.0;
-        let elapsed_time = (current_time - self.start_time) as f32;
-        self.planet.update(elapsed_time);
+        let readbacks = self.trace_sequence(n, sim_dt)?;
+        Ok(wasm_bindgen_futures::future_to_promise(async move {
+            let frames = js_sys::Array::new();
+            for readback in readbacks {
+                let pixels = readback.await.map_err(|e| JsValue::from_str(&e))?;
+                frames.push(&js_sys::Uint8Array::from(pixels.as_slice()));
+            }
+            Ok(frames.into())
+        }))
+    }
 
-        let planet_data: Vec<f32> = vec![
-            self.planet.position.x,
-            self.planet.position.y,
-            self.planet.position.z,
-            self.planet.radius,
-        ];
+    /// Records `duration_s` seconds of video at `fps` frames per second.
+    /// Each frame advances the simulation by exactly one frame's worth of
+    /// time at the current time scale, so the video plays at the live view's
+    /// speed without the jitter of real frame times. The promise resolves to
+    /// a `RecordedFrames` whose frames carry microsecond timestamps ready for
+    /// a WebCodecs `VideoEncoder`.
+    ///
+    /// Like `render_n_and_capture`, the frames are held in memory and capped
+    /// at `MAX_CAPTURE_FRAMES`, and the live clock resumes afterwards.
+    pub fn record_session(
+        &mut self,
+        duration_s: f64,
+        fps: f64,
+    ) -> Result<js_sys::Promise, JsValue> {
+        self.ensure_alive()?;
+        if !(duration_s > 0.0 && duration_s.is_finite() && fps > 0.0 && fps.is_finite()) {
+            return Err(JsValue::from_str(&format!(
+                "Duration and frame rate must be positive, got {} s at {} fps",
+                duration_s, fps
+            )));
+        }
+        let n = (duration_s * fps).round().max(1.0);
+        if n > MAX_CAPTURE_FRAMES as f64 {
+            return Err(JsValue::from_str(&format!(
+                "Recording would take {} frames; at most {} are supported",
+                n, MAX_CAPTURE_FRAMES
+            )));
+        }
 
-        self.queue
-            .write_buffer(&self.planet_buffer, 0, bytemuck::cast_slice(&planet_data));
+        let readbacks = self.trace_sequence(n as u32, self.time_scale / fps)?;
+        let (width, height) = (self.compute_width, self.compute_height);
+        Ok(wasm_bindgen_futures::future_to_promise(async move {
+            let mut frames = Vec::with_capacity(readbacks.len());
+            for readback in readbacks {
+                frames.push(readback.await.map_err(|e| JsValue::from_str(&e))?);
+            }
+            Ok(RecordedFrames::new(width, height, fps, frames).into())
+        }))
     }
 
-    pub fn resize(&mut self, width: u32, height: u32) -> Result<(), JsValue> {
-        if width > 0 && height > 0 {
-            self.config.width = width;
-            self.config.height = height;
-            self.surface.configure(&self.device, &self.config);
-            log::info!("Resized to {}x{}", width, height);
+    /// Traces `n` frames with the simulation clock pinned `sim_dt` simulated
+    /// seconds apart, starting now, then hands the clock back to the wall.
+    fn trace_sequence(
+        &mut self,
+        n: u32,
+        sim_dt: f64,
+    ) -> Result<Vec<readback::PendingReadback>, JsValue> {
+        let start = self.simulation_time();
+        let mut readbacks = Vec::with_capacity(n as usize);
+        for i in 0..n {
+            self.pinned_sim_time = Some(start + sim_dt * i as f64);
+            let readback = self.trace_to_readback();
+            if readback.is_err() {
+                self.pinned_sim_time = None;
+            }
+            readbacks.push(readback?);
         }
-        Ok(())
+        self.pinned_sim_time = None;
+        Ok(readbacks)
     }
 
-    pub fn on_mouse_move(&mut self, x: f64, y: f64) {
-        let old_az = self.camera.azimuth;
-        let old_el = self.camera.elevation;
-        self.camera.process_mouse_move(x, y);
-        if self.camera.dragging {
-            log::info!(
-                "Mouse move: az {:.4} -> {:.4}, el {:.4} -> {:.4}",
-                old_az,
-                self.camera.azimuth,
-                old_el,
-                self.camera.elevation
-            );
+    /// Simulated seconds since start, as used for the planet's orbit.
+    fn simulation_time(&self) -> f64 {
+        if let Some(time) = self.pinned_sim_time {
+            return time;
+        }
+        if self.fixed_timestep.is_some() {
+            return self.ticked_sim_time;
         }
+        self.live_sim_time
     }
 
-    pub fn on_mouse_button(&mut self, button: u8, pressed: bool, x: f64, y: f64) {
-        self.camera.process_mouse_button(button, pressed, x, y);
+    /// Moves the per-frame simulation clock on by the wall-clock time since
+    /// the last frame, at most `MAX_FRAME_DELTA`, so a throttled or hidden
+    /// tab resumes where it left off instead of teleporting the planet.
+    fn advance_live_clock(&mut self) {
+        let now = js_sys::Date::now() / 1000.0;
+        let delta = (now - self.last_live_clock_time).clamp(0.0, MAX_FRAME_DELTA);
+        self.last_live_clock_time = now;
+        if self.fixed_timestep.is_none() {
+            self.live_sim_time += delta * self.time_scale;
+        }
     }
 
-    pub fn on_wheel(&mut self, delta_y: f64) {
+    /// Runs the planet and light pulse physics and returns where to draw the
+    /// planet this frame. With a fixed timestep, physics advances in whole
+    /// ticks and the planet is drawn interpolated between the last two.
+    fn step_simulation(&mut self) -> Vec3 {
+        let r_s = self.black_hole.r_s;
+        let c = self.black_hole.constants.c;
+        let escape_r = self.settings.escape_radius;
+
+        let dt = match self.fixed_timestep {
+            Some(dt) if self.pinned_sim_time.is_none() => dt,
+            _ => {
+                if self.planet_visible {
+                    self.planet.update(self.planet_clock());
+                }
+                let now = self.simulation_time();
+                for pulse in &mut self.pulses {
+                    pulse.advance_to(now, c, r_s, escape_r);
+                }
+                self.pulses.retain(|pulse| !pulse.photons.is_empty());
+                return self.planet.position;
+            }
+        };
+
+        let now = js_sys::Date::now() / 1000.0;
+        self.tick_accumulator += (now - self.last_tick_time).clamp(0.0, MAX_ACCUMULATED_TIME);
+        self.last_tick_time = now;
+
+        while self.tick_accumulator >= dt {
+            self.tick_accumulator -= dt;
+            self.ticked_sim_time += dt * self.time_scale;
+            self.planet_previous_tick = self.planet.position;
+            if self.planet_visible {
+                let elapsed_time = (self.ticked_sim_time - self.planet_time_offset) as f32;
+                self.planet.update(elapsed_time);
+            }
+            for pulse in &mut self.pulses {
+                pulse.advance_to(self.ticked_sim_time, c, r_s, escape_r);
+            }
+        }
+        self.pulses.retain(|pulse| !pulse.photons.is_empty());
+
+        let alpha = (self.tick_accumulator / dt) as f32;
+        self.planet_previous_tick.lerp(self.planet.position, alpha)
+    }
+
+    /// Traces a frame and reads it back as tightly packed RGBA8 rows
+    /// (`compute_width * compute_height * 4` bytes), bypassing the swapchain.
+    /// The promise resolves to a `Uint8Array` that can be wrapped in an
+    /// `ImageData` and drawn onto a 2D canvas.
+    ///
+    /// Every call waits on a full-frame GPU to CPU copy, so this is much
+    /// slower than `render` and should only be used when the embedder cannot
+    /// present a WebGPU surface.
+    pub fn render_to_bytes(&mut self) -> Result<js_sys::Promise, JsValue> {
+        self.ensure_alive()?;
+        let readback = self.trace_to_readback()?;
+        Ok(wasm_bindgen_futures::future_to_promise(async move {
+            let pixels = readback.await.map_err(|e| JsValue::from_str(&e))?;
+            Ok(js_sys::Uint8Array::from(pixels.as_slice()).into())
+        }))
+    }
+
+    /// Renders a one-shot equirectangular panorama of the lensed sky around
+    /// the camera, `width` x `height` pixels (2:1 is the usual layout). Each
+    /// pixel's longitude/latitude is turned into a ray from the camera
+    /// position and traced through the same geodesic integrator as the live
+    /// view. The promise resolves to a `Uint8Array` of RGBA8 rows.
+    ///
+    /// Pixel centers never land exactly on the poles, and a ray along the
+    /// camera's up axis falls back to a valid orbital plane in the shader, so
+    /// the top and bottom rows stay finite.
+    pub fn capture_panorama(
+        &mut self,
+        width: u32,
+        height: u32,
+    ) -> Result<js_sys::Promise, JsValue> {
+        self.ensure_alive()?;
+        self.update_uniforms()?;
+        let camera_data = self.camera_uniform_data(PANORAMA_PROJECTION);
+        if self.upload(UniformSlot::Camera, &camera_data)? {
+            self.rebuild_compute_bind_group();
+        }
+
+        let readback = self.trace_offscreen(width, height)?;
+        Ok(wasm_bindgen_futures::future_to_promise(async move {
+            let pixels = readback.await.map_err(|e| JsValue::from_str(&e))?;
+            Ok(js_sys::Uint8Array::from(pixels.as_slice()).into())
+        }))
+    }
+
+    /// Renders a one-shot stereo pair for VR or cross-eyed viewing: two
+    /// `width` x `height` perspective images from eyes offset to either side
+    /// of the camera along its right axis, looking parallel. Each eye's rays
+    /// are traced separately, so the lensing (and the depth cues near the
+    /// ring) is correct for where that eye sits. `eye_separation` is the
+    /// distance between the eyes as a fraction of the camera's distance from
+    /// the hole, e.g. 0.03. The promise resolves to `[left, right]`, each a
+    /// `Uint8Array` of RGBA8 rows.
+    pub fn capture_stereo(
+        &mut self,
+        eye_separation: f32,
+        width: u32,
+        height: u32,
+    ) -> Result<js_sys::Promise, JsValue> {
+        self.ensure_alive()?;
+        if !(eye_separation > 0.0 && eye_separation <= MAX_EYE_SEPARATION) {
+            return Err(JsValue::from_str(&format!(
+                "Eye separation must be in (0, {}] of the camera radius, got {}",
+                MAX_EYE_SEPARATION, eye_separation
+            )));
+        }
+        self.update_uniforms()?;
+
+        let (pos, right, up, forward) = self.camera_basis();
+        let aspect = width as f32 / height.max(1) as f32;
+        let lens = (self.camera.tan_half_fov(aspect), aspect);
+        let half_offset = right * (0.5 * eye_separation * self.camera.radius);
+
+        let mut eyes = Vec::with_capacity(2);
+        for eye in [pos - half_offset, pos + half_offset] {
+            let camera_data = self.camera_uniform_data_for((eye, right, up, forward), lens, 0.0);
+            if self.upload(UniformSlot::Camera, &camera_data)? {
+                self.rebuild_compute_bind_group();
+            }
+            eyes.push(self.trace_offscreen(width, height)?);
+        }
+
+        Ok(wasm_bindgen_futures::future_to_promise(async move {
+            let pair = js_sys::Array::new();
+            for readback in eyes {
+                let pixels = readback.await.map_err(|e| JsValue::from_str(&e))?;
+                pair.push(&js_sys::Uint8Array::from(pixels.as_slice()));
+            }
+            Ok(pair.into())
+        }))
+    }
+
+    fn encode_compute_pass(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        bind_group: &wgpu::BindGroup,
+        width: u32,
+        height: u32,
+    ) {
+        if self.settings.frame_stats {
+            encoder.clear_buffer(&self.stats_buffer, 0, None);
+        }
+
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Compute Pass"),
+            timestamp_writes: None,
+        });
+
+        compute_pass.set_pipeline(&self.compute_pipeline);
+        compute_pass.set_bind_group(0, bind_group, &[]);
+
+        let workgroup_count_x = (width + 15) / 16;
+        let workgroup_count_y = (height + 15) / 16;
+        compute_pass.dispatch_workgroups(workgroup_count_x, workgroup_count_y, 1);
+    }
+
+    /// Dispatches the compute shader into `texture` and starts reading it back.
+    /// Uniforms must already be written.
+    fn dispatch_and_read(
+        &self,
+        bind_group: &wgpu::BindGroup,
+        texture: &wgpu::Texture,
+        width: u32,
+        height: u32,
+    ) -> readback::PendingReadback {
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Readback Encoder"),
+            });
+
+        self.encode_compute_pass(&mut encoder, bind_group, width, height);
+        let buffer =
+            readback::copy_texture_to_buffer(&self.device, &mut encoder, texture, width, height);
+
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        let pending = readback::PendingReadback::new(
+            buffer,
+            width,
+            height,
+            readback::bytes_per_pixel(texture),
+        );
+        self.device.poll(wgpu::Maintain::Wait);
+        pending
+    }
+
+    fn trace_to_readback(&mut self) -> Result<readback::PendingReadback, JsValue> {
+        self.update_uniforms()?;
+        self.frame_count += 1;
+        // Scripted captures pin their own clock, so don't match them later
+        self.last_traced = None;
+        Ok(self.dispatch_and_read(
+            &self.compute_bind_group,
+            &self.output_texture,
+            self.compute_width,
+            self.compute_height,
+        ))
+    }
+
+    /// Traces into a temporary texture of the given size, independent of the
+    /// live compute resolution. Uniforms must already be written.
+    fn trace_offscreen(
+        &self,
+        width: u32,
+        height: u32,
+    ) -> Result<readback::PendingReadback, JsValue> {
+        let max_dim = self.device.limits().max_texture_dimension_2d;
+        if width == 0 || height == 0 || width > max_dim || height > max_dim {
+            return Err(JsValue::from_str(&format!(
+                "Capture size must be between 1 and {} pixels per side, got {}x{}",
+                max_dim, width, height
+            )));
+        }
+
+        let texture = create_output_texture(&self.device, width, height);
+        // Keep one-off captures from overwriting the live motion vectors and
+        // lens map
+        let motion_texture = create_motion_texture(&self.device, 1, 1);
+        let lens_texture = create_lens_texture(&self.device, 1, 1);
+        let bind_group = create_compute_bind_group(
+            &self.device,
+            &self.compute_bind_group_layout,
+            &texture,
+            &self.camera_buffer,
+            &self.disk_buffer,
+            &self.planet_buffer,
+            &self.background_texture,
+            &self.params_buffer,
+            &self.pulse_buffer,
+            &motion_texture,
+            &self.motion_buffer,
+            &self.disk_lut_buffer,
+            &self.stats_buffer,
+            &lens_texture,
+        );
+        Ok(self.dispatch_and_read(&bind_group, &texture, width, height))
+    }
+
+    /// Writes `data` into a uniform buffer, growing the buffer first if the
+    /// data no longer fits. Returns whether the buffer was reallocated.
+    fn upload(&mut self, slot: UniformSlot, data: &[f32]) -> Result<bool, JsValue> {
+        let (buffer, label) = match slot {
+            UniformSlot::Camera => (&mut self.camera_buffer, "Camera Buffer"),
+            UniformSlot::Disk => (&mut self.disk_buffer, "Disk Buffer"),
+            UniformSlot::Planet => (&mut self.planet_buffer, "Planet Buffer"),
+            UniformSlot::Params => (&mut self.params_buffer, "Params Buffer"),
+            UniformSlot::Display => (&mut self.display_buffer, "Display Buffer"),
+            UniformSlot::Motion => (&mut self.motion_buffer, "Motion Buffer"),
+        };
+        let grown = buffers::ensure_capacity(
+            &self.device,
+            buffer,
+            label,
+            BufferKind::Uniform,
+            std::mem::size_of_val(data) as u64,
+        )
+        .map_err(|e| JsValue::from_str(&e))?;
+        self.queue
+            .write_buffer(buffer, 0, bytemuck::cast_slice(data));
+        Ok(grown)
+    }
+
+    fn rebuild_compute_bind_group(&mut self) {
+        self.last_traced = None;
+        if self.destroyed {
+            return;
+        }
+        self.compute_bind_group = create_compute_bind_group(
+            &self.device,
+            &self.compute_bind_group_layout,
+            &self.output_texture,
+            &self.camera_buffer,
+            &self.disk_buffer,
+            &self.planet_buffer,
+            &self.background_texture,
+            &self.params_buffer,
+            &self.pulse_buffer,
+            &self.motion_texture,
+            &self.motion_buffer,
+            &self.disk_lut_buffer,
+            &self.stats_buffer,
+            &self.lens_texture,
+        );
+    }
+
+    fn rebuild_render_bind_group(&mut self) {
+        if self.destroyed {
+            return;
+        }
+        self.render_bind_group = create_render_bind_group(
+            &self.device,
+            &self.render_bind_group_layout,
+            &self.output_texture,
+            &self.sampler,
+            &self.display_buffer,
+            &self.overlay_texture,
+        );
+    }
+
+    /// Camera position and orthonormal `(right, up, forward)` basis in the
+    /// hole's frame, where the integrator, shader, disk, planet and pulses
+    /// all put the hole at the origin. The camera itself orbits the world
+    /// origin, so an offset hole (`set_black_hole_position`) shifts the
+    /// position by `-black_hole.position`.
+    fn camera_basis(&self) -> (Vec3, Vec3, Vec3, Vec3) {
+        let (pos, right, up, forward) = self.camera.basis();
+        (self.black_hole.to_local(pos), right, up, forward)
+    }
+
+    /// The camera's distance from the hole, wherever the hole is.
+    fn camera_distance(&self) -> f32 {
+        self.black_hole.to_local(self.camera.position()).length()
+    }
+
+    /// Keeps the camera `CAMERA_MIN_RADIUS_RS` from the hole, pushing it
+    /// out along its orbit radius if needed, and reports its distance from
+    /// the hole to the radius thresholds.
+    fn clamp_camera_to_hole(&mut self) {
+        let min_distance = self.black_hole.r_s as f32 * CAMERA_MIN_RADIUS_RS;
+        self.camera
+            .keep_away_from(self.black_hole.position, min_distance);
+        self.thresholds.observe(self.camera_distance() as f64);
+    }
+
+    /// `(tan(fov / 2), aspect)` for primary rays.
+    fn lens(&self) -> (f32, f32) {
+        let aspect = self.config.width as f32 / self.config.height as f32;
+        (self.camera.tan_half_fov(aspect), aspect)
+    }
+
+    /// Origin and direction of the live view's primary ray through `ndc`
+    /// (y up), built exactly as the shader builds it.
+    fn primary_ray(&self, ndc_x: f32, ndc_y: f32) -> (Vec3, Vec3) {
+        self.camera_uniform_for(
+            self.camera_basis(),
+            self.lens(),
+            self.camera.projection.shader_code(),
+        )
+        .primary_ray(ndc_x, ndc_y)
+    }
+
+    /// The disk and planet radius the shader currently traces against: a
+    /// bare disk with nothing to hit when only the background is shown, and
+    /// radius 0 while the planet is hidden.
+    fn shown_scene(&self) -> (Disk, f32) {
+        if self.settings.background_only {
+            return (Disk::new(0.0, 0.0, 0.0), 0.0);
+        }
+        let planet_radius = if self.planet_visible {
+            self.planet.radius
+        } else {
+            0.0
+        };
+        (self.disk, planet_radius)
+    }
+
+    /// Traces a ray from `origin` along `dir`, both in the hole's frame, on
+    /// the CPU exactly as the shader traces it: its integrator, adaptive
+    /// step count, escape radius, and the scene from `shown_scene`.
+    fn trace_like_view(&self, origin: Vec3, dir: Vec3) -> integrator::ShaderTrace {
+        let (disk, planet_radius) = self.shown_scene();
+        integrator::trace_like_shader(
+            origin,
+            dir,
+            self.black_hole.r_s,
+            self.settings.escape_radius,
+            integrator::adaptive_steps(origin, self.black_hole.r_s, self.max_steps),
+            &disk,
+            self.planet.position,
+            planet_radius,
+        )
+    }
+
+    /// Packs the `Camera` uniform. `projection` is the shader's projection
+    /// code: 0 perspective, 1 orthographic, 2 equirectangular panorama.
+    fn camera_uniform_data(&self, projection: f32) -> Vec<f32> {
+        self.camera_uniform_data_for(self.camera_basis(), self.lens(), projection)
+    }
+
+    /// `camera_uniform_data` for an arbitrary basis and `(tan(fov / 2),
+    /// aspect)`, for captures that look from somewhere other than the live
+    /// camera.
+    fn camera_uniform_data_for(
+        &self,
+        basis: (Vec3, Vec3, Vec3, Vec3),
+        lens: (f32, f32),
+        projection: f32,
+    ) -> Vec<f32> {
+        self.camera_uniform_for(basis, lens, projection).to_floats()
+    }
+
+    /// The `Camera` uniform for `basis`, `lens` and `projection`, with the
+    /// rest from the live camera and hole.
+    fn camera_uniform_for(
+        &self,
+        (pos, right, up, forward): (Vec3, Vec3, Vec3, Vec3),
+        (tan_half_fov, aspect): (f32, f32),
+        projection: f32,
+    ) -> CameraUniform {
+        CameraUniform {
+            pos,
+            right,
+            up,
+            forward,
+            tan_half_fov,
+            aspect,
+            moving: self.camera.moving,
+            projection,
+            ortho_scale: self.camera.ortho_scale,
+            black_hole_rs: self.black_hole.r_s as f32,
+            c_light: self.black_hole.constants.c as f32,
+        }
+    }
+
+    /// Time on the planet's orbit clock, where its position was last
+    /// computed or will resume from: paused while the planet is hidden.
+    /// Rescaling the orbit keeps the planet's phase at this time, so it
+    /// doesn't jump along its path when the mass changes.
+    fn planet_clock(&self) -> f32 {
+        let now = if self.planet_visible {
+            self.simulation_time()
+        } else {
+            self.planet_hidden_at
+        };
+        (now - self.planet_time_offset) as f32
+    }
+
+    /// Eases the hole's mass toward `target_mass` and rescales everything
+    /// measured in r_s along with it.
+    fn advance_mass_transition(&mut self) {
+        let now = js_sys::Date::now() / 1000.0;
+        let dt = (now - self.last_mass_update).max(0.0);
+        self.last_mass_update = now;
+
+        let current = self.black_hole.mass;
+        if current == self.target_mass {
+            return;
+        }
+        let remaining = (-self.mass_transition_speed * dt).exp();
+        let mut mass = self.target_mass + (current - self.target_mass) * remaining;
+        if self.mass_transition_speed <= 0.0
+            || ((mass - self.target_mass) / self.target_mass).abs() < 1e-4
+        {
+            mass = self.target_mass;
+        }
+        self.rescale_to_mass(mass);
+    }
+
+    /// Sets the hole's mass to `mass` at once, scaling the disk, the planet's
+    /// orbit and everything else measured in r_s with it.
+    fn rescale_to_mass(&mut self, mass: f64) {
+        let factor = mass / self.black_hole.mass;
+        self.black_hole =
+            BlackHole::with_constants(self.black_hole.position, mass, self.black_hole.constants);
+        let r_s = self.black_hole.r_s;
+
+        // The ISCO scales with r_s, so a disk starting there stays there
+        self.disk.inner_radius *= factor as f32;
+        self.disk.outer_radius *= factor as f32;
+        self.disk.thickness *= factor as f32;
+        self.disk.central_mass = mass;
+        let planet_time = self.planet_clock();
+        self.planet.rescale(factor, planet_time);
+        self.planet_previous_tick *= factor as f32;
+        self.prev_planet_position *= factor as f32;
+
+        self.camera.fit_clip_planes(r_s);
+        self.thresholds.add("horizon", r_s);
+        self.thresholds.add("photon_sphere", 1.5 * r_s);
+        self.thresholds.add("isco", self.black_hole.isco_radius());
+    }
+
+    fn update_uniforms(&mut self) -> Result<(), JsValue> {
+        self.advance_live_clock();
+        self.camera.advance_dolly_zoom(js_sys::Date::now() / 1000.0);
+        self.advance_mass_transition();
+        // Re-clamped every frame so a growing horizon never swallows the camera
+        self.clamp_camera_to_hole();
+
+        let (pos, right, up, forward) = self.camera_basis();
+        let camera_data = self.camera_uniform_data(self.camera.projection.shader_code());
+
+        let mut compute_grown = self.upload(UniformSlot::Camera, &camera_data)?;
+
+        // Advanced from the sim clock so pausing or slowing time does the same
+        // to the precession
+        let sim_time = self.simulation_time();
+        self.disk.precession_angle = (self.disk.precession_angle
+            + self.disk.precession_rate * (sim_time - self.last_precession_time) as f32)
+            % std::f32::consts::TAU;
+        self.last_precession_time = sim_time;
+        let to_disk = self.disk.orientation().transpose();
+
+        let mut disk_data: Vec<f32> = vec![
+            self.disk.inner_radius,
+            self.disk.outer_radius,
+            if self.disk.two_sided { 1.0 } else { 0.0 },
+            self.disk.thickness,
+            self.disk.style as u32 as f32,
+            // Keep the seed small enough to survive the trip through f32
+            (self.disk.seed % 65536) as f32,
+            self.disk.saturation,
+            self.disk.contrast,
+            self.disk.hotspot_count as f32,
+            self.disk.hotspot_brightness,
+            self.disk.hotspot_lifetime,
+            sim_time as f32,
+        ];
+        for column in [to_disk.x_axis, to_disk.y_axis, to_disk.z_axis] {
+            disk_data.extend_from_slice(&[column.x, column.y, column.z, 0.0]);
+        }
+        disk_data.extend_from_slice(&[
+            self.disk.edge_softness,
+            self.disk.vertical_sharpness,
+            self.disk.max_crossings as f32,
+            0.0,
+        ]);
+
+        compute_grown |= self.upload(UniformSlot::Disk, &disk_data)?;
+
+        let key = self.disk.color_key();
+        if self.disk_lut_key != Some(key) {
+            let table = self.disk.color_table(DISK_LUT_SIZE);
+            self.queue
+                .write_buffer(&self.disk_lut_buffer, 0, bytemuck::cast_slice(&table));
+            self.disk_lut_key = Some(key);
+            self.last_traced = None;
+        }
+
+        let planet_position = self.step_simulation();
+
+        let mut planet_data: Vec<f32> = vec![
+            planet_position.x,
+            planet_position.y,
+            planet_position.z,
+            self.planet.radius,
+            self.planet.schwarzschild_radius() as f32,
+            if self.settings.planet_lensing {
+                1.0
+            } else {
+                0.0
+            },
+            if self.planet_visible { 1.0 } else { 0.0 },
+            0.0,
+            self.settings.planet_light_direction[0],
+            self.settings.planet_light_direction[1],
+            self.settings.planet_light_direction[2],
+            self.settings.planet_light_intensity,
+            self.settings.planet_light_color[0],
+            self.settings.planet_light_color[1],
+            self.settings.planet_light_color[2],
+            0.0,
+        ];
+        planet_data.extend_from_slice(&self.settings.orbit_overlay_color);
+        planet_data.push(if self.settings.orbit_overlay {
+            1.0
+        } else {
+            0.0
+        });
+        planet_data.extend_from_slice(&self.planet.orbit_normal().to_array());
+        planet_data.push(ORBIT_LINE_HALF_WIDTH * self.planet.semi_major_axis);
+        for point in self.planet.orbit_points(ORBIT_OVERLAY_POINTS) {
+            planet_data.extend_from_slice(&[point.x, point.y, point.z, 0.0]);
+        }
+
+        compute_grown |= self.upload(UniformSlot::Planet, &planet_data)?;
+
+        let params_data = self.settings.uniform_data();
+        compute_grown |= self.upload(UniformSlot::Params, &params_data)?;
+
+        let photon_count: usize = self.pulses.iter().map(|p| p.photons.len()).sum();
+        let mut pulse_data: Vec<f32> = Vec::with_capacity(4 + photon_count * 4);
+        pulse_data.extend_from_slice(&[photon_count as f32, 0.0, 0.0, 0.0]);
+        for ray in self.pulses.iter().flat_map(|p| &p.photons) {
+            let p = ray.position().as_vec3();
+            pulse_data.extend_from_slice(&[p.x, p.y, p.z, 1.0]);
+        }
+        let grown = buffers::ensure_capacity(
+            &self.device,
+            &mut self.pulse_buffer,
+            "Pulse Buffer",
+            BufferKind::Storage,
+            std::mem::size_of_val(pulse_data.as_slice()) as u64,
+        )
+        .map_err(|e| JsValue::from_str(&e))?;
+        compute_grown |= grown;
+        self.queue
+            .write_buffer(&self.pulse_buffer, 0, bytemuck::cast_slice(&pulse_data));
+
+        let (prev_pos, prev_right, prev_up, prev_forward) =
+            self.prev_camera.unwrap_or((pos, right, up, forward));
+        let planet_delta = planet_position - self.prev_planet_position;
+        let mut motion_data: Vec<f32> = Vec::with_capacity(24);
+        for v in [prev_pos, prev_right, prev_up, prev_forward] {
+            motion_data.extend_from_slice(&[v.x, v.y, v.z, 0.0]);
+        }
+        motion_data.extend_from_slice(&[
+            planet_delta.x,
+            planet_delta.y,
+            planet_delta.z,
+            if self.motion_vectors { 1.0 } else { 0.0 },
+        ]);
+        motion_data.extend_from_slice(&[0.0; 4]);
+        compute_grown |= self.upload(UniformSlot::Motion, &motion_data)?;
+        self.prev_camera = Some((pos, right, up, forward));
+        self.prev_planet_position = planet_position;
+
+        let mut trace_inputs = camera_data;
+        // Only hotspots animate the disk over time
+        let disk_time = if self.disk.hotspot_count > 0 {
+            disk_data[11]
+        } else {
+            0.0
+        };
+        trace_inputs.extend_from_slice(&disk_data[..11]);
+        trace_inputs.push(disk_time);
+        trace_inputs.extend_from_slice(&disk_data[12..]);
+        trace_inputs.extend_from_slice(&planet_data[3..]);
+        trace_inputs.extend_from_slice(&params_data);
+        trace_inputs.extend_from_slice(&pulse_data);
+        self.trace_inputs = trace_inputs;
+
+        if compute_grown {
+            self.rebuild_compute_bind_group();
+        }
+
+        // Screen-space directions of the world axes for the gnomon. The compute
+        // shader builds rays as `u * right - v * up`, so screen-up is `-up`.
+        let axis = |a: Vec3| [a.dot(right), -a.dot(up), a.dot(forward), 0.0];
+        let mut debug_points = self.debug_point_data();
+        let debug_point_count = (debug_points.len() / 4) as f32;
+        debug_points.resize(MAX_DEBUG_POINTS * 4, 0.0);
+        let mut display_data: Vec<f32> = Vec::with_capacity(20 + MAX_DEBUG_POINTS * 4);
+        display_data.extend_from_slice(&axis(Vec3::X));
+        display_data.extend_from_slice(&axis(Vec3::Y));
+        display_data.extend_from_slice(&axis(Vec3::Z));
+        display_data.extend_from_slice(&[
+            self.config.width as f32,
+            self.config.height as f32,
+            if self.settings.axes_overlay { 1.0 } else { 0.0 },
+            if self.settings.compute_grid_overlay {
+                1.0
+            } else {
+                0.0
+            },
+            if self.settings.dithering { 1.0 } else { 0.0 },
+            if self.overlay_enabled { 1.0 } else { 0.0 },
+            debug_point_count,
+            0.0,
+        ]);
+        display_data.extend_from_slice(&debug_points);
+
+        if self.upload(UniformSlot::Display, &display_data)? {
+            self.rebuild_render_bind_group();
+        }
+
+        Ok(())
+    }
+
+    /// Moves the black hole to `(x, y, z)` meters from the world origin,
+    /// taking the disk, jets, planet orbit and background sphere with it;
+    /// the camera keeps orbiting the world origin, so the hole can sit off
+    /// center. Rays are traced in the hole's frame, so the shadow and its
+    /// lensing are unchanged apart from where they appear.
+    ///
+    /// The camera's orbit radius (`set_camera_radius_rs`, zooming) stays
+    /// measured from the world origin, but its horizon clamp,
+    /// `add_radius_threshold` events, `scene_info` and
+    /// `accessible_description` measure from the hole.
+    pub fn set_black_hole_position(&mut self, x: f32, y: f32, z: f32) -> Result<(), JsValue> {
+        let position = Vec3::new(x, y, z);
+        if !position.is_finite() {
+            return Err(JsValue::from_str("Black hole position must be finite"));
+        }
+        self.black_hole.position = position;
+        self.clamp_camera_to_hole();
+        Ok(())
+    }
+
+    /// Sets the black hole's mass in solar masses. The hole grows or shrinks
+    /// toward it over the next few frames rather than snapping, at the rate
+    /// set by `set_mass_transition_speed`. The disk and the planet's orbit
+    /// keep their size in units of r_s, and the camera is pushed out if the
+    /// horizon would reach it. The planet's period changes with the orbit's
+    /// size, but it stays at the same point along its orbit (the same
+    /// eccentric anomaly), hidden or not, instead of jumping.
+    pub fn set_black_hole_mass(&mut self, solar_masses: f64) -> Result<(), JsValue> {
+        if !solar_masses.is_finite() || solar_masses <= 0.0 {
+            return Err(JsValue::from_str(&format!(
+                "Black hole mass must be positive and finite, got {} solar masses",
+                solar_masses
+            )));
+        }
+        self.target_mass = solar_masses * self.black_hole.constants.solar_mass();
+        log::info!(
+            "Black hole mass target set to {} solar masses",
+            solar_masses
+        );
+        Ok(())
+    }
+
+    /// Sets how fast the mass follows `set_black_hole_mass`: the remaining
+    /// difference shrinks by a factor of e every `1 / rate` seconds. 0 makes
+    /// mass changes immediate.
+    pub fn set_mass_transition_speed(&mut self, rate: f64) -> Result<(), JsValue> {
+        if !rate.is_finite() || rate < 0.0 {
+            return Err(JsValue::from_str(&format!(
+                "Mass transition speed must be finite and non-negative, got {}",
+                rate
+            )));
+        }
+        self.mass_transition_speed = rate;
+        Ok(())
+    }
+
+    /// Sets the speed of light and gravitational constant the simulation is
+    /// expressed in, e.g. `(1, 1)` for geometrized units. SI by default.
+    /// Lengths stay in meters and the scene keeps its size: the hole's mass
+    /// is re-expressed in the new units with the same `GM/c^2`, so the image
+    /// and every deflection are unchanged. What changes is the units of
+    /// mass (`set_black_hole_mass` still takes solar masses) and of the
+    /// simulation clock, which then counts the new time unit; with `c = 1`
+    /// that is a meter of light travel, so orbits run about 3e8 times slower
+    /// per tick. Disk temperatures are computed in SI either way.
+    pub fn set_physics_constants(&mut self, c: f64, g: f64) -> Result<(), JsValue> {
+        if !c.is_finite() || c <= 0.0 || !g.is_finite() || g <= 0.0 {
+            return Err(JsValue::from_str(&format!(
+                "Speed of light and gravitational constant must be positive, got c = {} and G = {}",
+                c, g
+            )));
+        }
+        let old = self.black_hole.constants;
+        let constants = PhysicsConstants { c, g };
+        self.black_hole = BlackHole::with_constants(
+            self.black_hole.position,
+            old.convert_mass(self.black_hole.mass, &constants),
+            constants,
+        );
+        self.target_mass = old.convert_mass(self.target_mass, &constants);
+        self.disk.central_mass = self.black_hole.mass;
+        self.disk.constants = constants;
+        let planet_time = self.planet_clock();
+        self.planet.set_constants(constants, planet_time);
+        self.disk_lut_key = None;
+        self.last_traced = None;
+        log::info!("Physics constants set to c = {}, G = {}", c, g);
+        Ok(())
+    }
+
+    /// Sets the radius in meters at which rays are considered escaped, for
+    /// both the shader and the CPU integrator, and moves the overlay far clip
+    /// plane there. Smaller values truncate the lensing; larger values cost
+    /// steps.
+    pub fn set_escape_radius(&mut self, meters: f64) -> Result<(), JsValue> {
+        if !meters.is_finite() || meters <= self.black_hole.r_s {
+            return Err(JsValue::from_str(&format!(
+                "Escape radius must be finite and outside the horizon ({} m), got {}",
+                self.black_hole.r_s, meters
+            )));
+        }
+        self.settings.escape_radius = meters;
+        self.camera.set_clip_planes(self.camera.near, meters as f32);
+        log::info!("Escape radius set to {} meters", meters);
+        Ok(())
+    }
+
+    /// Places the background on a sphere `meters` from the hole instead of at
+    /// infinity, as for a gas cloud at a known distance. Escaped rays then
+    /// sample the background at the point where they cross that sphere, not
+    /// along their final direction. Light from a nearby source is bent only
+    /// over the part of its path inside the sphere, so the background is
+    /// magnified less near the photon ring than a sky at infinity would be.
+    /// Pass `Infinity` for the default sky at infinity. Has no effect while
+    /// the camera is outside the sphere.
+    pub fn set_background_distance(&mut self, meters: f64) -> Result<(), JsValue> {
+        if meters.is_nan() || meters <= self.black_hole.r_s {
+            return Err(JsValue::from_str(&format!(
+                "Background distance must be outside the horizon ({} m), got {}",
+                self.black_hole.r_s, meters
+            )));
+        }
+        self.settings.background_distance = meters;
+        Ok(())
+    }
+
+    /// Enables bipolar jets along the disk's axis, following its tilt and
+    /// precession. `length` is in meters from the disk plane, raised to the
+    /// disk's thickness if shorter, and `opening_angle_deg` is the full cone
+    /// angle, clamped to [0.1, 90]. Jet light is Doppler beamed and follows
+    /// the same bent rays as the rest of the scene.
+    pub fn set_jets(
+        &mut self,
+        enabled: bool,
+        length: f32,
+        opening_angle_deg: f32,
+        r: f32,
+        g: f32,
+        b: f32,
+    ) -> Result<(), JsValue> {
+        if !length.is_finite() || length < 0.0 {
+            return Err(JsValue::from_str(&format!(
+                "Jet length must be finite and non-negative, got {}",
+                length
+            )));
+        }
+        if !opening_angle_deg.is_finite() || opening_angle_deg < 0.0 {
+            return Err(JsValue::from_str(&format!(
+                "Jet opening angle must be finite and non-negative, got {}",
+                opening_angle_deg
+            )));
+        }
+        if [r, g, b].iter().any(|c| !c.is_finite() || *c < 0.0) {
+            return Err(JsValue::from_str(&format!(
+                "Jet color components must be finite and non-negative, got ({}, {}, {})",
+                r, g, b
+            )));
+        }
+        self.settings.jets_enabled = enabled;
+        self.settings.jet_length = length.max(self.disk.thickness);
+        self.settings.jet_opening_angle_deg = opening_angle_deg.clamp(0.1, 90.0);
+        self.settings.jet_color = [r, g, b];
+        Ok(())
+    }
+
+    /// Shows a small RGB gnomon in the bottom-left corner indicating the world
+    /// X/Y/Z axes as seen from the current camera. Axes pointing away from the
+    /// viewer are drawn dimmed. Drawn in the display pass only.
+    pub fn set_axes_overlay(&mut self, enabled: bool) {
+        self.settings.axes_overlay = enabled;
+    }
+
+    /// Dithers the displayed image to hide banding in smooth gradients. On
+    /// by default; the noise is below one 8-bit step and fades out in black
+    /// regions.
+    pub fn set_dithering(&mut self, enabled: bool) {
+        self.settings.dithering = enabled;
+    }
+
+    /// Draws faint lines along the edges of the compute texels, making the
+    /// upscale from the traced image to the canvas visible. Hidden when each
+    /// texel covers less than two display pixels.
+    pub fn set_show_compute_grid(&mut self, enabled: bool) {
+        self.settings.compute_grid_overlay = enabled;
+    }
+
+    /// Blends an RGBA8 image over the final frame, stretched to the canvas
+    /// and composited by its alpha. `bytes` is tightly packed rows of
+    /// `width` x `height` sRGB pixels, as from `ImageData.data`.
+    pub fn set_overlay(&mut self, bytes: &[u8], width: u32, height: u32) -> Result<(), JsValue> {
+        self.ensure_alive()?;
+        let max_dim = self.device.limits().max_texture_dimension_2d;
+        if width == 0 || height == 0 || width > max_dim || height > max_dim {
+            return Err(JsValue::from_str(&format!(
+                "Overlay size must be between 1 and {} pixels per side, got {}x{}",
+                max_dim, width, height
+            )));
+        }
+        let expected = width as usize * height as usize * 4;
+        if bytes.len() != expected {
+            return Err(JsValue::from_str(&format!(
+                "Overlay of {}x{} needs {} bytes, got {}",
+                width,
+                height,
+                expected,
+                bytes.len()
+            )));
+        }
+
+        self.overlay_texture =
+            create_overlay_texture(&self.device, &self.queue, bytes, width, height);
+        self.overlay_enabled = true;
+        self.rebuild_render_bind_group();
+        Ok(())
+    }
+
+    /// Aberrates the background for the camera's velocity (see
+    /// `set_camera_velocity`): a fast observer sees the sky crowd toward the
+    /// direction of motion. Only the background is transformed, after
+    /// lensing; the hole, disk and planet are drawn as seen at rest. Off by
+    /// default, since the orbit camera itself hardly moves.
+    pub fn set_aberration(&mut self, enabled: bool) {
+        self.settings.aberration = enabled;
+    }
+
+    /// Sets the camera's velocity relative to the hole's static frame as a
+    /// fraction of the speed of light along the world axes, for
+    /// `set_aberration`. The camera does not track a velocity of its own,
+    /// so callers flying or dropping it set this to match. Its length must
+    /// be below 1.
+    pub fn set_camera_velocity(&mut self, x: f32, y: f32, z: f32) -> Result<(), JsValue> {
+        let beta = Vec3::new(x, y, z);
+        if !beta.is_finite() || beta.length() >= 1.0 {
+            return Err(JsValue::from_str(&format!(
+                "Camera velocity must be below the speed of light, got {} c",
+                beta.length()
+            )));
+        }
+        self.settings.observer_velocity = beta.to_array();
+        Ok(())
+    }
+
+    /// Picks the ray through `ndc` (y up) and draws a dot at each point the
+    /// shader's integrator stepped it to (see `trace_like_view`). Steps are
+    /// equal turns of the ray about the hole, so the dots crowd together
+    /// where it passes close and spread out far away. The ray is fixed in
+    /// world space when picked, so the camera can orbit to view the path
+    /// from the side. Long paths are thinned to `MAX_DEBUG_POINTS` evenly
+    /// spaced steps; dots fade from cyan at the camera to orange at the end.
+    pub fn set_debug_ray(&mut self, ndc_x: f32, ndc_y: f32) -> Result<(), JsValue> {
+        if !ndc_x.is_finite() || !ndc_y.is_finite() {
+            return Err(JsValue::from_str("Debug ray position must be finite"));
+        }
+        let (origin, direction) = self.primary_ray(ndc_x, ndc_y);
+        let mut ray = DebugRay {
+            origin,
+            direction,
+            r_s: 0.0,
+            points: Vec::new(),
+        };
+        self.trace_debug_ray(&mut ray);
+        self.debug_ray = Some(ray);
+        Ok(())
+    }
+
+    /// Removes the path drawn by `set_debug_ray`.
+    pub fn clear_debug_ray(&mut self) {
+        self.debug_ray = None;
+    }
+
+    fn trace_debug_ray(&self, ray: &mut DebugRay) {
+        let (disk, planet_radius) = self.shown_scene();
+        let (trace, path) = integrator::trace_ray_path(
+            ray.origin,
+            ray.direction,
+            self.black_hole.r_s,
+            self.settings.escape_radius,
+            integrator::adaptive_steps(ray.origin, self.black_hole.r_s, self.max_steps),
+            &disk,
+            self.planet.position,
+            planet_radius,
+        );
+        let stride = path.len().div_ceil(MAX_DEBUG_POINTS).max(1);
+        ray.points = path.into_iter().step_by(stride).collect();
+        ray.r_s = self.black_hole.r_s;
+        log::info!(
+            "Debug ray: {:?} with {} points drawn every {} steps",
+            trace.result,
+            ray.points.len(),
+            stride
+        );
+    }
+
+    /// The debug ray's visible points for the display uniform: NDC x and y
+    /// (y up), then the fraction of the way along the path, then padding.
+    fn debug_point_data(&mut self) -> Vec<f32> {
+        let Some(mut ray) = self.debug_ray.take() else {
+            return Vec::new();
+        };
+        if ray.r_s != self.black_hole.r_s {
+            self.trace_debug_ray(&mut ray);
+        }
+        let last = ray.points.len().saturating_sub(1).max(1) as f32;
+        let data = ray
+            .points
+            .iter()
+            .enumerate()
+            .filter_map(|(i, &point)| {
+                let (x, y) = self.project_unlensed(point)?;
+                Some([x, y, i as f32 / last, 0.0])
+            })
+            .flatten()
+            .collect();
+        self.debug_ray = Some(ray);
+        data
+    }
+
+    /// Removes the overlay set by `set_overlay`.
+    pub fn clear_overlay(&mut self) {
+        self.overlay_enabled = false;
+        if self.destroyed {
+            return;
+        }
+        self.overlay_texture = create_overlay_texture(&self.device, &self.queue, &[0; 4], 1, 1);
+        self.rebuild_render_bind_group();
+    }
+
+    /// Emits an expanding shell of photons from `(x, y, z)` in meters. The
+    /// photons follow their geodesics as simulated time passes, so the pulse
+    /// can be watched bending around the hole; far from it they cover the
+    /// speed of light times `speed_scale`. Live photons are capped, dropping
+    /// the oldest pulses first.
+    pub fn emit_light_pulse(
+        &mut self,
+        x: f32,
+        y: f32,
+        z: f32,
+        speed_scale: f64,
+    ) -> Result<(), JsValue> {
+        let origin = self.black_hole.to_local(Vec3::new(x, y, z));
+        if (origin.length() as f64) <= self.black_hole.r_s {
+            return Err(JsValue::from_str(
+                "Light pulse origin must be outside the horizon",
+            ));
+        }
+        // The spherical integrator is singular on the Z axis
+        let origin = if origin.x.abs() + origin.y.abs() < origin.length() * 1e-4 {
+            origin + Vec3::X * origin.length() * 1e-3
+        } else {
+            origin
+        };
+
+        self.pulses.push(LightPulse::emit(
+            origin,
+            PULSE_PHOTONS,
+            speed_scale.max(0.0),
+            self.black_hole.r_s,
+            self.simulation_time(),
+        ));
+        while self.pulses.iter().map(|p| p.photons.len()).sum::<usize>() > MAX_PULSE_PHOTONS {
+            self.pulses.remove(0);
+        }
+        Ok(())
+    }
+
+    /// Removes every active light pulse.
+    pub fn clear_pulses(&mut self) {
+        self.pulses.clear();
+    }
+
+    /// Switches between perspective and orthographic ray generation. In
+    /// orthographic mode all rays start parallel to the view direction from a
+    /// plane `2 * ortho_scale` meters tall; lensing still bends them.
+    pub fn set_projection(
+        &mut self,
+        projection: Projection,
+        ortho_scale: f32,
+    ) -> Result<(), JsValue> {
+        if !ortho_scale.is_finite() || ortho_scale <= 0.0 {
+            return Err(JsValue::from_str(&format!(
+                "Orthographic scale must be positive, got {}",
+                ortho_scale
+            )));
+        }
+        self.camera.projection = projection;
+        self.camera.ortho_scale = ortho_scale;
+        Ok(())
+    }
+
+    /// Replaces the planet's orbit, keeping its size, mass and orbit model.
+    /// The semi-major axis is in Schwarzschild radii. Fails, leaving the old
+    /// orbit in place, if the eccentricity is outside `[0, 1)`, if periapsis
+    /// `a (1 - e)` would be inside the ISCO at 3 r_s, or if the geodesic
+    /// model is active and the orbit is not stable.
+    pub fn set_planet_orbit(
+        &mut self,
+        semi_major_axis_rs: f32,
+        eccentricity: f32,
+    ) -> Result<(), JsValue> {
+        self.planet = self
+            .planet
+            .with_orbit(semi_major_axis_rs, eccentricity, &self.black_hole)
+            .map_err(|e| JsValue::from_str(&e))?;
+        log::info!(
+            "Planet orbit set: a = {} r_s, e = {}",
+            semi_major_axis_rs,
+            eccentricity
+        );
+        Ok(())
+    }
+
+    /// Sets the planet's own mass in kg. Only affects its lensing.
+    pub fn set_planet_mass(&mut self, kg: f64) -> Result<(), JsValue> {
+        if !kg.is_finite() || kg < 0.0 {
+            return Err(JsValue::from_str(&format!(
+                "Planet mass must be non-negative, got {}",
+                kg
+            )));
+        }
+        self.planet.mass = self.black_hole.constants.mass_from_si(kg);
+        Ok(())
+    }
+
+    /// Lets rays passing near the planet pick up its weak-field deflection
+    /// `2 r_s / b`. Invisible for Earth-like masses; costs extra shader work.
+    pub fn set_planet_lensing(&mut self, enabled: bool) {
+        self.settings.planet_lensing = enabled;
+    }
+
+    /// Draws the planet's full orbital ellipse as a faint line of color
+    /// `(r, g, b)`. The line lies in the scene, so it is lensed like the disk
+    /// and shows secondary images; it is hidden with the planet. With
+    /// `PlanetModel::GeodesicGR` the ellipse turns to each new periapsis as
+    /// the orbit precesses.
+    pub fn set_orbit_overlay(
+        &mut self,
+        enabled: bool,
+        r: f32,
+        g: f32,
+        b: f32,
+    ) -> Result<(), JsValue> {
+        if [r, g, b].iter().any(|c| !c.is_finite() || *c < 0.0) {
+            return Err(JsValue::from_str(&format!(
+                "Orbit overlay color components must be finite and non-negative, got ({}, {}, {})",
+                r, g, b
+            )));
+        }
+        self.settings.orbit_overlay = enabled;
+        self.settings.orbit_overlay_color = [r, g, b];
+        Ok(())
+    }
+
+    /// Orients the background sky. Angles are in degrees, applied as yaw about
+    /// Y, then pitch about X, then roll about Z. All zero is the original
+    /// orientation.
+    pub fn set_background_rotation(&mut self, yaw: f32, pitch: f32, roll: f32) {
+        self.settings.background_rotation = [yaw, pitch, roll];
+    }
+
+    /// Shows a flat color wherever rays escape instead of the star map, for
+    /// clean diagrams. `set_background_texture` restores the stars.
+    pub fn set_background_solid(&mut self, r: f32, g: f32, b: f32) -> Result<(), JsValue> {
+        if [r, g, b].iter().any(|c| !c.is_finite() || *c < 0.0) {
+            return Err(JsValue::from_str(&format!(
+                "Background color components must be finite and non-negative, got ({}, {}, {})",
+                r, g, b
+            )));
+        }
+        self.settings.background = Background::Solid([r, g, b]);
+        Ok(())
+    }
+
+    /// Shows a vertical gradient wherever rays escape instead of the star
+    /// map: the top color straight up (+Y, after `set_background_rotation`)
+    /// blending to the bottom color straight down.
+    pub fn set_background_gradient(
+        &mut self,
+        top_r: f32,
+        top_g: f32,
+        top_b: f32,
+        bottom_r: f32,
+        bottom_g: f32,
+        bottom_b: f32,
+    ) -> Result<(), JsValue> {
+        let (top, bottom) = ([top_r, top_g, top_b], [bottom_r, bottom_g, bottom_b]);
+        if top
+            .iter()
+            .chain(&bottom)
+            .any(|c| !c.is_finite() || *c < 0.0)
+        {
+            return Err(JsValue::from_str(&format!(
+                "Background color components must be finite and non-negative, got top ({}, {}, {}) \
+                 and bottom ({}, {}, {})",
+                top_r, top_g, top_b, bottom_r, bottom_g, bottom_b
+            )));
+        }
+        self.settings.background = Background::Gradient { top, bottom };
+        Ok(())
+    }
+
+    /// Goes back to sampling the star map for escaped rays.
+    pub fn set_background_texture(&mut self) {
+        self.settings.background = Background::Texture;
+    }
+
+    /// How the star map is sampled in the lensed lookup. `Nearest`, the
+    /// default, takes the single texel each ray lands on, so a low
+    /// resolution map looks blocky and stars stay hard-edged as lensing
+    /// stretches them; `Linear` blends the four around it for a smooth
+    /// sky. The compute pass reads the texture directly rather than through
+    /// the display pass's sampler, so this is a uniform flag: switching
+    /// rebuilds nothing. Solid and gradient backgrounds are unaffected.
+    pub fn set_background_filtering(&mut self, filtering: BackgroundFiltering) {
+        self.settings.background_filtering = filtering;
+    }
+
+    /// Tints the background toward the color of light at `kelvin`, to match
+    /// a sky shot under different light to the disk's warmth: lower is
+    /// warmer, higher cooler, and 6500 leaves it untouched (the default).
+    /// Applies to solid and gradient backgrounds too.
+    pub fn set_background_white_balance(&mut self, kelvin: f32) -> Result<(), JsValue> {
+        if !(MIN_WHITE_BALANCE..=MAX_WHITE_BALANCE).contains(&kelvin) {
+            return Err(JsValue::from_str(&format!(
+                "White balance must be between {} and {} K, got {}",
+                MIN_WHITE_BALANCE, MAX_WHITE_BALANCE, kelvin
+            )));
+        }
+        self.settings.background_white_balance = kelvin;
+        Ok(())
+    }
+
+    /// Antialiases the shadow's edge without supersampling the whole frame:
+    /// pixels whose neighbors disagree on whether they fall into the hole
+    /// trace four extra sub-samples and average them. Only the thin band
+    /// around the shadow and photon ring pays for it.
+    pub fn set_edge_refinement(&mut self, enabled: bool) {
+        self.settings.edge_refinement = enabled;
+    }
+
+    /// Switches between the lensed scene and an embedding diagram: the Flamm
+    /// paraboloid `z(r) = 2 sqrt(r_s (r - r_s))`, the classic funnel picture
+    /// of the hole's curved space, drawn out to 12 r_s as a gridded surface
+    /// with its rim level with the hole's center. Grid circles are one r_s
+    /// apart, the throat at r = r_s is drawn in red, and spokes are 15
+    /// degrees apart. It uses the current camera and mass, but rays are
+    /// straight, so nothing is lensed. The frame statistics, lens map and
+    /// motion vectors are not written in this mode.
+    pub fn set_view_mode(&mut self, mode: ViewMode) {
+        self.settings.view_mode = mode;
+    }
+
+    /// Integrates each ray's `u = 1/r` and `du/dphi` in the shader in
+    /// double-single arithmetic, pairs of f32s carrying about 48 bits, as
+    /// the CPU integrator does in f64. This cuts the error f32 builds up
+    /// over thousands of steps near the horizon, which shows as banding and
+    /// speckle right at the shadow's edge. The rest of the trace stays in
+    /// f32. Each step does several times the arithmetic, so frames trace
+    /// roughly half again as slowly; see `estimated_cost`. Off by default.
+    /// Drivers that don't fuse `fma` or that reassociate float math lose
+    /// part of the gain.
+    pub fn set_high_precision(&mut self, enabled: bool) {
+        self.settings.high_precision = enabled;
+    }
+
+    /// Shows only how the hole warps the background: the disk, planet and
+    /// jets are hidden (the planet no longer lenses either) and captured
+    /// rays stay black. Their own settings are kept for when it is turned
+    /// off again.
+    pub fn set_background_only(&mut self, enabled: bool) {
+        self.settings.background_only = enabled;
+    }
+
+    /// Multiplies the light of rays that wound around the hole before
+    /// escaping or landing, by `strength`, so the photon ring stands out.
+    /// Rays count once they sweep more than three quarters of a turn about
+    /// the hole, which takes in the ring's nested images of the disk and sky
+    /// and fades in over the last tenth of a turn. This is emphasis for
+    /// visualization, not a physical correction: the ring's real brightness
+    /// is already in the traced image. 1.0, the default, turns it off.
+    pub fn set_photon_ring_boost(&mut self, strength: f32) -> Result<(), JsValue> {
+        if !strength.is_finite() || strength < 0.0 {
+            return Err(JsValue::from_str(&format!(
+                "Photon ring boost must be finite and non-negative, got {}",
+                strength
+            )));
+        }
+        self.settings.photon_ring_boost = strength;
+        Ok(())
+    }
+
+    /// Draws a thin line of the given color along the critical curve, the
+    /// edge between rays the shader traces into the hole and those that
+    /// escape. It follows the shadow as rendered, which sits a little inside
+    /// the analytic `BlackHole::critical_impact_parameter`, and is hidden
+    /// where the disk or planet covers the edge. Each pixel traces four more
+    /// rays while it is on. `thickness` is in compute pixels.
+    pub fn set_critical_curve(
+        &mut self,
+        enabled: bool,
+        r: f32,
+        g: f32,
+        b: f32,
+        thickness: f32,
+    ) -> Result<(), JsValue> {
+        if !thickness.is_finite() || thickness <= 0.0 {
+            return Err(JsValue::from_str(&format!(
+                "Critical curve thickness must be positive, got {}",
+                thickness
+            )));
+        }
+        if [r, g, b].iter().any(|c| !c.is_finite() || *c < 0.0) {
+            return Err(JsValue::from_str(&format!(
+                "Critical curve color components must be finite and non-negative, got ({}, {}, {})",
+                r, g, b
+            )));
+        }
+        self.settings.critical_curve = enabled;
+        self.settings.critical_curve_color = [r, g, b];
+        self.settings.critical_curve_thickness = thickness;
+        Ok(())
+    }
+
+    /// Splits the lensed background into color fringes by bending red less
+    /// and blue more than the true deflection. This is a stylistic effect:
+    /// real gravitational lensing is the same for every wavelength. Around
+    /// 0.05 gives a visible rainbow ring; 0 disables it.
+    pub fn set_dispersion(&mut self, strength: f32) -> Result<(), JsValue> {
+        if !strength.is_finite() || strength < 0.0 {
+            return Err(JsValue::from_str(&format!(
+                "Dispersion strength must be finite and non-negative, got {}",
+                strength
+            )));
+        }
+        self.settings.dispersion = strength;
+        Ok(())
+    }
+
+    /// Sets the vertical field of view of the perspective projection, in
+    /// degrees. Defaults to 60.
+    pub fn set_fov(&mut self, degrees: f32) -> Result<(), JsValue> {
+        if !(MIN_FOV..=MAX_FOV).contains(&degrees) {
+            return Err(JsValue::from_str(&format!(
+                "Field of view must be between {} and {} degrees, got {}",
+                MIN_FOV, MAX_FOV, degrees
+            )));
+        }
+        self.camera.fov = degrees;
+        Ok(())
+    }
+
+    /// Starts a "vertigo" dolly zoom: over `duration_s` seconds the camera
+    /// moves to `radius_end` meters while the field of view goes to
+    /// `fov_end` degrees, with the view's width at the hole interpolated in
+    /// between so its apparent size changes smoothly rather than with the
+    /// distance. Picking `radius_end * tan(fov_end / 2)` equal to the current
+    /// value keeps the hole the same size throughout. Dragging or scrolling
+    /// cancels it.
+    pub fn start_dolly_zoom(
+        &mut self,
+        duration_s: f64,
+        fov_end: f32,
+        radius_end: f32,
+    ) -> Result<(), JsValue> {
+        if !duration_s.is_finite() || duration_s < 0.0 {
+            return Err(JsValue::from_str(&format!(
+                "Dolly zoom duration must be finite and non-negative, got {}",
+                duration_s
+            )));
+        }
+        if !(MIN_FOV..=MAX_FOV).contains(&fov_end) || !radius_end.is_finite() {
+            return Err(JsValue::from_str(&format!(
+                "Dolly zoom needs a field of view between {} and {} degrees and a finite \
+                 radius, got {} and {}",
+                MIN_FOV, MAX_FOV, fov_end, radius_end
+            )));
+        }
+        self.camera.start_dolly_zoom(
+            js_sys::Date::now() / 1000.0,
+            duration_s,
+            fov_end,
+            radius_end,
+        );
+        Ok(())
+    }
+
+    /// Stops a running dolly zoom where it is.
+    pub fn stop_dolly_zoom(&mut self) {
+        self.camera.dolly = None;
+    }
+
+    /// Limits how far the camera can orbit toward each pole, in radians from
+    /// +Y. Limits are kept a hair away from the exact poles. Defaults to
+    /// `(0.01, PI - 0.01)`.
+    pub fn set_elevation_limits(&mut self, min: f32, max: f32) -> Result<(), JsValue> {
+        if !(0.0..=std::f32::consts::PI).contains(&min)
+            || !(0.0..=std::f32::consts::PI).contains(&max)
+            || min >= max
+        {
+            return Err(JsValue::from_str(&format!(
+                "Elevation limits must satisfy 0 <= min < max <= PI, got ({}, {})",
+                min, max
+            )));
+        }
+        let pi = std::f32::consts::PI;
+        self.camera.min_elevation = min.max(POLE_EPSILON);
+        self.camera.max_elevation = max.min(pi - POLE_EPSILON);
+        self.camera.constrain_elevation();
+        Ok(())
+    }
+
+    /// Moves the camera to `multiple` Schwarzschild radii from the hole, e.g.
+    /// 1.5 for the photon sphere or 3 for the ISCO. Unlike meters, this
+    /// keeps meaning the same view when the mass changes, so it is clamped
+    /// in r_s only: to at least 1.1 r_s from the hole, and at most the zoom
+    /// bound `max_radius`. With the hole moved by `set_black_hole_position` this is
+    /// the orbit radius about the world origin instead.
+    pub fn set_camera_radius_rs(&mut self, multiple: f32) -> Result<(), JsValue> {
+        if !multiple.is_finite() || multiple <= 0.0 {
+            return Err(JsValue::from_str(&format!(
+                "Camera radius must be a positive multiple of r_s, got {}",
+                multiple
+            )));
+        }
+        self.camera.set_radius_rs(multiple, self.black_hole.r_s);
+        self.clamp_camera_to_hole();
+        Ok(())
+    }
+
+    /// Jumps to a named starting shot from `viewpoint_names`: "edge-on-disk"
+    /// and "face-on-disk" frame the disk from its plane and from along its
+    /// normal, following its tilt, "polar" looks down world +Y, and
+    /// "near-photon-sphere" sits at 2 r_s where the shadow fills most of the
+    /// view. Each sets the orbit angles, field of view and a radius in
+    /// Schwarzschild radii, so the framing holds at any mass. The radius is
+    /// clamped as in `set_camera_radius_rs`, the elevation to the camera's
+    /// limits as usual, and a running dolly zoom or look-at target is
+    /// dropped.
+    pub fn set_viewpoint(&mut self, preset: &str) -> Result<(), JsValue> {
+        let viewpoint = Viewpoint::find(preset).ok_or_else(|| {
+            JsValue::from_str(&format!(
+                "Unknown viewpoint '{}', expected one of: {}",
+                preset,
+                Self::viewpoint_names().join(", ")
+            ))
+        })?;
+        let (azimuth, elevation) =
+            viewpoint.orbit_angles(self.disk.orientation(), self.camera.handedness);
+        self.camera.dolly = None;
+        self.camera.target = Vec3::ZERO;
+        self.camera.pole_flipped = false;
+        self.camera.azimuth = azimuth;
+        self.camera.elevation = elevation;
+        self.camera.constrain_elevation();
+        self.camera.fov = viewpoint.fov;
+        self.set_camera_radius_rs(viewpoint.radius_rs)
+    }
+
+    /// Names accepted by `set_viewpoint`.
+    pub fn viewpoint_names() -> Vec<String> {
+        VIEWPOINTS
+            .iter()
+            .map(|viewpoint| viewpoint.name.to_string())
+            .collect()
+    }
+
+    /// Moves the camera to `eye` looking at `target`, both world-space xyz in
+    /// meters, for poses imported from other tools. The orbit angles and
+    /// radius are solved from `eye` and clamped to the camera's limits as
+    /// usual; `target` is kept as the look-at point until the user next
+    /// drags or scrolls, which returns to orbiting and looking at the world
+    /// origin.
+    pub fn set_camera_from_lookat(&mut self, eye: &[f32], target: &[f32]) -> Result<(), JsValue> {
+        let (&[ex, ey, ez], &[tx, ty, tz]) = (eye, target) else {
+            return Err(JsValue::from_str(&format!(
+                "Eye and target must be xyz triples, got {} and {} values",
+                eye.len(),
+                target.len()
+            )));
+        };
+        let (eye, target) = (Vec3::new(ex, ey, ez), Vec3::new(tx, ty, tz));
+        if !eye.is_finite() || !target.is_finite() {
+            return Err(JsValue::from_str("Eye and target must be finite"));
+        }
+        if !self.camera.look_from(eye, target) {
+            return Err(JsValue::from_str(
+                "Eye must differ from both the target and the black hole's center",
+            ));
+        }
+        self.clamp_camera_to_hole();
+        Ok(())
+    }
+
+    /// The camera as a conventional view and projection matrix pair, for
+    /// drawing unlensed 3D annotations (orbit ellipses, labels) from another
+    /// renderer in line with this one. Returns 32 floats: the view matrix
+    /// then the projection matrix, each column-major. `aspect` is the
+    /// overlay's width over height.
+    ///
+    /// The lensed image is not drawn with these: rays are bent, so they
+    /// only line up with where things would be seen without lensing, the
+    /// same as `project_unlensed`. The projection uses the field of view
+    /// rays are generated with (narrowed for very wide aspects) and the
+    /// overlay clip planes, and includes a flip of clip-space y, since the
+    /// ray generator puts the camera's up vector at the bottom of the image.
+    /// Coordinates are world space, so with an offset hole its center is at
+    /// `set_black_hole_position`.
+    pub fn view_projection_matrices(&self, aspect: f32) -> Result<Vec<f32>, JsValue> {
+        if !aspect.is_finite() || aspect <= 0.0 {
+            return Err(JsValue::from_str(&format!(
+                "Aspect ratio must be positive, got {}",
+                aspect
+            )));
+        }
+        let projection = match self.camera.projection {
+            Projection::Perspective => {
+                let fov = 2.0 * self.camera.tan_half_fov(aspect).atan().to_degrees();
+                self.camera.projection_matrix(aspect, fov)
+            }
+            Projection::Orthographic => self.camera.orthographic_matrix(aspect),
+        };
+        let flip_y = glam::Mat4::from_scale(Vec3::new(1.0, -1.0, 1.0));
+        let mut data = self.camera.view_matrix().to_cols_array().to_vec();
+        data.extend_from_slice(&(flip_y * projection).to_cols_array());
+        Ok(data)
+    }
+
+    /// Camera distance from the hole in Schwarzschild radii.
+    pub fn camera_radius_rs(&self) -> f32 {
+        self.camera_distance() / self.black_hole.r_s as f32
+    }
+
+    /// Chooses the handedness of the frame camera angles are read in, for
+    /// orientations imported from left-handed tools. Left-handed negates z
+    /// in the camera position, in its right, up and forward vectors (so in
+    /// every primary ray's origin and direction) and in the overlay view and
+    /// projection matrices, which switch to glam's `_lh` forms. The hole,
+    /// disk, planet and background stay put, so the same azimuth looks from
+    /// the mirrored side. Right-handed by default.
+    pub fn set_handedness(&mut self, handedness: Handedness) {
+        self.camera.handedness = handedness;
+    }
+
+    /// Lets the camera orbit straight over the poles instead of stopping at
+    /// the elevation limits. Off by default. Turning it off while past a
+    /// pole puts +Y back at the top of the view and clamps the elevation.
+    pub fn set_pole_crossing(&mut self, allow: bool) {
+        self.camera.set_pole_crossing(allow);
+    }
+
+    /// Sets the maximum integration steps per ray, used both for the shader
+    /// (rebuilding the compute pipeline with a new `NSTEPS` override) and for
+    /// every CPU query that traces rays, which all run the shader's
+    /// integrator with its adaptive step count, so they agree with the
+    /// rendered image.
+    ///
+    /// High values make every pixel's loop longer; past a few thousand steps a
+    /// frame can exceed the browser's GPU watchdog and lose the device.
+    pub fn set_integration_quality(&mut self, max_steps: u32) {
+        let max_steps = max_steps.clamp(MIN_TRACE_STEPS, MAX_TRACE_STEPS);
+        if max_steps == self.max_steps {
+            return;
+        }
+        self.max_steps = max_steps;
+        self.last_traced = None;
+        log::info!("Integration quality set to {} steps", max_steps);
+        if self.destroyed {
+            return;
+        }
+        self.compute_pipeline = create_compute_pipeline(
+            &self.device,
+            &self.compute_pipeline_layout,
+            &self.compute_shader,
+            max_steps,
+        );
+    }
+
+    /// Replaces the compute shader with `wgsl` and rebuilds the pipeline, for
+    /// experimenting with the lensing math live. The shader is parsed and
+    /// validated first and must fit the existing bind group layout: a
+    /// compute entry point `main`, an `override NSTEPS: u32`, and only the
+    /// bindings `shader.wgsl` declares, each of the same kind. On error the
+    /// current shader keeps running.
+    ///
+    /// The browser compiles the shader again asynchronously and can still
+    /// reject what passed here (an unsupported feature, say); that is logged
+    /// as a warning, and `reset_shader_source` restores the built-in shader.
+    pub fn set_shader_source(&mut self, wgsl: &str) -> Result<(), JsValue> {
+        self.ensure_alive()?;
+        shader_source::validate_compute_shader(wgsl)
+            .map_err(|e| JsValue::from_str(&format!("Shader rejected: {}", e)))?;
+        self.replace_compute_shader(wgsl, "Custom Compute Shader");
+        log::info!("Compute shader replaced");
+        Ok(())
+    }
+
+    /// Goes back to the built-in compute shader after `set_shader_source`.
+    pub fn reset_shader_source(&mut self) -> Result<(), JsValue> {
+        self.ensure_alive()?;
+        self.replace_compute_shader(BUILTIN_COMPUTE_SHADER, "Compute Shader");
+        Ok(())
+    }
+
+    fn replace_compute_shader(&mut self, source: &str, label: &str) {
+        self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let module = self
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some(label),
+                source: wgpu::ShaderSource::Wgsl(source.into()),
+            });
+        let pipeline = create_compute_pipeline(
+            &self.device,
+            &self.compute_pipeline_layout,
+            &module,
+            self.max_steps,
+        );
+        let error = self.device.pop_error_scope();
+        wasm_bindgen_futures::spawn_local(async move {
+            if let Some(e) = error.await {
+                log::warn!(
+                    "Compute shader failed to compile on the GPU; \
+                     call reset_shader_source to restore the built-in one: {}",
+                    e
+                );
+            }
+        });
+        self.compute_shader = module;
+        self.compute_pipeline = pipeline;
+        self.last_traced = None;
+    }
+
+    /// Selects the disk's procedural pattern. `seed` varies the noise so a
+    /// particular look can be reproduced.
+    pub fn set_disk_style(&mut self, style: DiskStyle, seed: u32) {
+        self.disk.style = style;
+        self.disk.seed = seed;
+    }
+
+    /// Paints the disk a single color instead of the radial gradient, for
+    /// stylized renders. The color is linear RGB like every other color in
+    /// this API; the display pass encodes it for the sRGB surface. The style
+    /// pattern and face shading still modulate it.
+    pub fn set_disk_color(&mut self, r: f32, g: f32, b: f32) -> Result<(), JsValue> {
+        if [r, g, b].iter().any(|c| !c.is_finite() || *c < 0.0) {
+            return Err(JsValue::from_str(&format!(
+                "Disk color components must be finite and non-negative, got ({}, {}, {})",
+                r, g, b
+            )));
+        }
+        self.disk.flat_color = [r, g, b];
+        self.set_disk_color_mode(DiskColorMode::Flat);
+        Ok(())
+    }
+
+    /// Switches between the radial gradient, the color from
+    /// `set_disk_color` and the Novikov-Thorne temperature profile.
+    pub fn set_disk_color_mode(&mut self, mode: DiskColorMode) {
+        self.disk.color_mode = mode;
+    }
+
+    /// Sets the disk's accretion rate in kg/s, which sets its temperature in
+    /// `DiskColorMode::NovikovThorne` (as the fourth root). Defaults to
+    /// 1e15 kg/s, a peak near 4800 K around Sagittarius A*.
+    pub fn set_disk_accretion_rate(&mut self, kg_per_s: f64) -> Result<(), JsValue> {
+        if !kg_per_s.is_finite() || kg_per_s <= 0.0 {
+            return Err(JsValue::from_str(&format!(
+                "Accretion rate must be positive, got {}",
+                kg_per_s
+            )));
+        }
+        self.disk.accretion_rate = kg_per_s;
+        Ok(())
+    }
+
+    /// Grades the disk's light on its own, leaving the background as is:
+    /// `saturation` scales how far colors sit from gray (0 is monochrome)
+    /// and `contrast` steepens (above 1) or flattens (below 1) brightness
+    /// about mid-gray. Both default to 1.0, which leaves the disk unchanged.
+    pub fn set_disk_grade(&mut self, saturation: f32, contrast: f32) -> Result<(), JsValue> {
+        if !saturation.is_finite() || saturation < 0.0 || !contrast.is_finite() || contrast <= 0.0 {
+            return Err(JsValue::from_str(&format!(
+                "Disk saturation must be non-negative and contrast positive, got {} and {}",
+                saturation, contrast
+            )));
+        }
+        self.disk.saturation = saturation;
+        self.disk.contrast = contrast;
+        Ok(())
+    }
+
+    /// Feathers the disk's inner and outer edges over about `pixels` compute
+    /// pixels, fading its emission out instead of cutting it off, so the
+    /// rings don't alias at low resolution. Cheaper than edge refinement,
+    /// which only helps the shadow's edge. Defaults to 1.0; 0 turns it off.
+    pub fn set_disk_edge_softness(&mut self, pixels: f32) -> Result<(), JsValue> {
+        if !pixels.is_finite() || pixels < 0.0 {
+            return Err(JsValue::from_str(&format!(
+                "Disk edge softness must be non-negative, got {}",
+                pixels
+            )));
+        }
+        self.disk.edge_softness = pixels;
+        Ok(())
+    }
+
+    /// How fast the disk's glow fades from its midplane toward the top and
+    /// bottom of its `thickness`, seen where rays skim the slab without
+    /// reaching the midplane, as when the disk is nearly edge-on. Density
+    /// goes as `(1 - |height| / half_thickness)^sharpness`: 0 fills the slab
+    /// uniformly, 1 falls off linearly, and larger values pack the material
+    /// toward the midplane. Defaults to 2.0.
+    pub fn set_disk_vertical_profile(&mut self, sharpness: f32) -> Result<(), JsValue> {
+        if !sharpness.is_finite() || sharpness < 0.0 {
+            return Err(JsValue::from_str(&format!(
+                "Disk vertical profile sharpness must be non-negative, got {}",
+                sharpness
+            )));
+        }
+        self.disk.vertical_sharpness = sharpness;
+        Ok(())
+    }
+
+    /// How many times a ray may pass through the disk's midplane, up to
+    /// `MAX_DISK_CROSSINGS`. With 1, the default, the disk is opaque and a
+    /// ray stops at the first crossing, which above and below the shadow is
+    /// already the lensed far side. Larger values make every crossing but
+    /// the last translucent, composited front to back in the order the bent
+    /// ray meets them, so the far side's arcs and the higher-order images
+    /// inside them show through the near disk instead of being hidden by it.
+    ///
+    /// To check it, look from `set_viewpoint("edge-on-disk")`. The near disk
+    /// crosses the shadow as a band, the far side's top surface arcs over
+    /// the shadow and its underside under it. With 2, the arcs carry on
+    /// behind the band where they meet it at either side of the shadow,
+    /// dimmed but visible; with 1 the band cuts them off there.
+    pub fn set_disk_multi_crossing(&mut self, max_crossings: u32) -> Result<(), JsValue> {
+        if !(1..=MAX_DISK_CROSSINGS).contains(&max_crossings) {
+            return Err(JsValue::from_str(&format!(
+                "Disk crossings must be between 1 and {}, got {}",
+                MAX_DISK_CROSSINGS, max_crossings
+            )));
+        }
+        self.disk.max_crossings = max_crossings;
+        Ok(())
+    }
+
+    /// Tilts the disk's normal away from +Y by `degrees` about the X axis,
+    /// before any precession. 0 keeps the disk in the equatorial plane.
+    pub fn set_disk_tilt(&mut self, degrees: f32) -> Result<(), JsValue> {
+        if !degrees.is_finite() {
+            return Err(JsValue::from_str(&format!(
+                "Disk tilt must be finite, got {}",
+                degrees
+            )));
+        }
+        self.disk.tilt = degrees.to_radians();
+        Ok(())
+    }
+
+    /// Swings the tilted disk's normal around +Y at `rate_deg_per_s` degrees
+    /// per simulated second. It runs on the same clock as the planet's orbit,
+    /// so it holds still whenever simulated time does. Has no visible effect
+    /// on an untilted disk. Defaults to 0.
+    pub fn set_disk_precession(&mut self, rate_deg_per_s: f32) -> Result<(), JsValue> {
+        if !rate_deg_per_s.is_finite() {
+            return Err(JsValue::from_str(&format!(
+                "Disk precession rate must be finite, got {}",
+                rate_deg_per_s
+            )));
+        }
+        self.disk.precession_rate = rate_deg_per_s.to_radians();
+        Ok(())
+    }
+
+    /// Sets the near and far clip planes, in meters, used for rasterized
+    /// overlays. The ray-traced image does not use them: rays run until they
+    /// hit something or pass the escape radius.
+    pub fn set_clip_planes(&mut self, near: f32, far: f32) -> Result<(), JsValue> {
+        if !self.camera.set_clip_planes(near, far) {
+            return Err(JsValue::from_str(&format!(
+                "Clip planes must satisfy 0 < near < far, got near = {}, far = {}",
+                near, far
+            )));
+        }
+        Ok(())
+    }
+
+    /// Chooses between a closed Kepler ellipse and a relativistic geodesic
+    /// orbit, which shows periapsis precession. The geodesic model rejects
+    /// orbits that are not stable in Schwarzschild spacetime.
+    pub fn set_planet_model(&mut self, model: PlanetModel) -> Result<(), JsValue> {
+        self.planet
+            .set_model(model)
+            .map_err(|e| JsValue::from_str(&e))?;
+        log::info!("Planet orbit model set to {:?}", model);
+        Ok(())
+    }
+
+    /// Steps the planet and light pulses in fixed ticks of `seconds` of wall
+    /// time instead of once per frame, drawing the planet interpolated
+    /// between ticks. This keeps the geodesic orbit integration stable when
+    /// the frame rate varies. Elapsed time is clamped per frame, so after a
+    /// stall the simulation resumes rather than jumping. 0 returns to
+    /// per-frame stepping.
+    pub fn set_fixed_timestep(&mut self, seconds: f64) -> Result<(), JsValue> {
+        if !seconds.is_finite() || seconds < 0.0 {
+            return Err(JsValue::from_str(&format!(
+                "Fixed timestep must be finite and non-negative, got {}",
+                seconds
+            )));
+        }
+
+        let sim_time = self.simulation_time();
+        let now = js_sys::Date::now() / 1000.0;
+        if seconds > 0.0 {
+            self.ticked_sim_time = sim_time;
+            self.tick_accumulator = 0.0;
+            self.last_tick_time = now;
+            self.planet_previous_tick = self.planet.position;
+            self.fixed_timestep = Some(seconds);
+        } else {
+            // Carry on from the ticked clock rather than jumping
+            self.live_sim_time = sim_time;
+            self.last_live_clock_time = now;
+            self.fixed_timestep = None;
+        }
+        Ok(())
+    }
+
+    /// Where the planet's lensed image appears on screen, as `[x, y]` in
+    /// normalized device coordinates (y up), or `None` if it is hidden or no
+    /// image was found. When bending produces several images, the one
+    /// reached by the shortest light path (the primary image) wins.
+    ///
+    /// Rays are traced on the CPU with the shader's integrator over a coarse
+    /// grid and then refined around the best hit, so the result is accurate
+    /// to about 1% of the screen width. An image smaller than a coarse grid
+    /// cell can be missed unless it lies near the planet's unlensed
+    /// position, which is always probed.
+    pub fn planet_screen_position(&self) -> Option<Vec<f32>> {
+        const COARSE: usize = 24;
+        const FINE: usize = 8;
+
+        if !self.planet_visible {
+            return None;
+        }
+
+        let trace = |ndc: (f32, f32)| {
+            let (origin, dir) = self.primary_ray(ndc.0, ndc.1);
+            let traced = self.trace_like_view(origin, dir);
+            self.step_budget.record(traced.result);
+            traced
+        };
+        let hit_length = |ndc: (f32, f32)| {
+            let traced = trace(ndc);
+            (traced.result == integrator::TraceResult::HitObject).then_some(traced.path_length)
+        };
+
+        let mut candidates: Vec<(f32, f32)> = Vec::with_capacity(COARSE * COARSE + 1);
+        if let Some(ndc) = self.project_unlensed(self.planet.position) {
+            candidates.push(ndc);
+        }
+        let cell = 2.0 / COARSE as f32;
+        for j in 0..COARSE {
+            for i in 0..COARSE {
+                candidates.push((
+                    -1.0 + (i as f32 + 0.5) * cell,
+                    -1.0 + (j as f32 + 0.5) * cell,
+                ));
+            }
+        }
+
+        let best = candidates
+            .into_iter()
+            .filter_map(|ndc| hit_length(ndc).map(|length| (ndc, length)))
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        // The coarse grid spans the screen, so it is a fair sample
+        self.warn_if_steps_exhausted();
+        let (seed, seed_length) = best?;
+
+        // Average the hits of the same image on a finer grid around the seed
+        let fine_cell = cell / FINE as f32;
+        let mut sum = (0.0, 0.0);
+        let mut count = 0;
+        for j in 0..=2 * FINE {
+            for i in 0..=2 * FINE {
+                let ndc = (
+                    seed.0 + (i as f32 - FINE as f32) * fine_cell,
+                    seed.1 + (j as f32 - FINE as f32) * fine_cell,
+                );
+                if let Some(length) = hit_length(ndc) {
+                    if (length - seed_length).abs() <= 0.1 * seed_length {
+                        sum.0 += ndc.0;
+                        sum.1 += ndc.1;
+                        count += 1;
+                    }
+                }
+            }
+        }
+        if count == 0 {
+            return Some(vec![seed.0, seed.1]);
+        }
+        Some(vec![sum.0 / count as f32, sum.1 / count as f32])
+    }
+
+    /// Radius in canvas pixels of the shadow as rendered, for checking it
+    /// against `critical_impact_parameter` seen through the current lens.
+    /// Rays are traced on the CPU with the shader's integrator along 16
+    /// directions from the hole's on-screen center, and the edge of the
+    /// captured region is found by bisection on each; the disk and planet
+    /// are ignored so they can't hide the edge. Returns `NaN` when the hole
+    /// is behind the camera or the shadow runs off the screen.
+    pub fn measure_shadow_radius_px(&self) -> f32 {
+        const DIRECTIONS: usize = 16;
+        const BISECTIONS: usize = 16;
+
+        // The hole's frame puts it at the origin
+        let Some(center) = self.project_unlensed(Vec3::ZERO) else {
+            return f32::NAN;
+        };
+        let bare_disk = Disk::new(0.0, 0.0, 0.0);
+        let captured = |ndc: Vec2| {
+            let (origin, dir) = self.primary_ray(ndc.x, ndc.y);
+            integrator::trace_like_shader(
+                origin,
+                dir,
+                self.black_hole.r_s,
+                self.settings.escape_radius,
+                integrator::adaptive_steps(origin, self.black_hole.r_s, self.max_steps),
+                &bare_disk,
+                Vec3::ZERO,
+                0.0,
+            )
+            .result
+                == integrator::TraceResult::HitBlackHole
+        };
+
+        let center = Vec2::new(center.0, center.1);
+        if center.abs().max_element() > 1.0 || !captured(center) {
+            return f32::NAN;
+        }
+
+        let half_size = Vec2::new(self.config.width as f32, self.config.height as f32) / 2.0;
+        let mut total = 0.0;
+        for i in 0..DIRECTIONS {
+            let angle = std::f32::consts::TAU * i as f32 / DIRECTIONS as f32;
+            let step = Vec2::new(angle.cos(), angle.sin());
+            // Distance along `step` to the screen edge, in NDC
+            let to_edge = |c: f32, s: f32| {
+                if s > 0.0 {
+                    (1.0 - c) / s
+                } else if s < 0.0 {
+                    (-1.0 - c) / s
+                } else {
+                    f32::INFINITY
+                }
+            };
+            let mut outside = to_edge(center.x, step.x).min(to_edge(center.y, step.y));
+            if captured(center + step * outside) {
+                return f32::NAN;
+            }
+            let mut inside = 0.0;
+            for _ in 0..BISECTIONS {
+                let mid = 0.5 * (inside + outside);
+                if captured(center + step * mid) {
+                    inside = mid;
+                } else {
+                    outside = mid;
+                }
+            }
+            total += (step * 0.5 * (inside + outside) * half_size).length();
+        }
+        total / DIRECTIONS as f32
+    }
+
+    /// Straight-line (unlensed) NDC position of a world point, or `None` if
+    /// it is behind the camera.
+    fn project_unlensed(&self, point: Vec3) -> Option<(f32, f32)> {
+        let (pos, right, up, forward) = self.camera_basis();
+        let (tan_half_fov, aspect) = self.lens();
+        let rel = point - pos;
+        match self.camera.projection {
+            Projection::Orthographic => {
+                let scale = self.camera.ortho_scale;
+                Some((rel.dot(right) / (aspect * scale), -rel.dot(up) / scale))
+            }
+            Projection::Perspective => {
+                let depth = rel.dot(forward);
+                if depth <= 0.0 {
+                    return None;
+                }
+                Some((
+                    rel.dot(right) / (depth * aspect * tan_half_fov),
+                    -rel.dot(up) / (depth * tan_half_fov),
+                ))
+            }
+        }
+    }
+
+    /// Lights the planet with a distant directional light, such as a nearby
+    /// star, giving it a day/night terminator. `(x, y, z)` points from the
+    /// planet toward the light and need not be normalized. The default is a
+    /// soft white fill from above; an intensity of 0 turns it off.
+    pub fn set_planet_light(
+        &mut self,
+        x: f32,
+        y: f32,
+        z: f32,
+        r: f32,
+        g: f32,
+        b: f32,
+        intensity: f32,
+    ) -> Result<(), JsValue> {
+        let direction = Vec3::new(x, y, z);
+        if !direction.is_finite() || direction.length_squared() == 0.0 {
+            return Err(JsValue::from_str(
+                "Light direction must be a finite, non-zero vector",
+            ));
+        }
+        if !intensity.is_finite() || intensity < 0.0 {
+            return Err(JsValue::from_str(&format!(
+                "Light intensity must be finite and non-negative, got {}",
+                intensity
+            )));
+        }
+        if [r, g, b].iter().any(|c| !c.is_finite() || *c < 0.0) {
+            return Err(JsValue::from_str(&format!(
+                "Light color components must be finite and non-negative, got ({}, {}, {})",
+                r, g, b
+            )));
+        }
+        self.settings.planet_light_direction = direction.normalize().to_array();
+        self.settings.planet_light_color = [r, g, b];
+        self.settings.planet_light_intensity = intensity;
+        Ok(())
+    }
+
+    /// Shows or hides the planet. While hidden the shader skips it entirely
+    /// and its orbit is paused, so showing it again resumes from the same
+    /// point on the orbit.
+    pub fn set_planet_visible(&mut self, visible: bool) {
+        if visible == self.planet_visible {
+            return;
+        }
+        let now = self.simulation_time();
+        if visible {
+            self.planet_time_offset += now - self.planet_hidden_at;
+        } else {
+            self.planet_hidden_at = now;
+        }
+        self.planet_visible = visible;
+    }
+
+    /// Base disk color `[r, g, b]` at `radius` meters, as the shader computes
+    /// it before the style pattern, for drawing a matching legend.
+    pub fn disk_color_at(&self, radius: f32) -> Vec<f32> {
+        self.disk.color_at(radius, self.disk.color_mode).to_vec()
+    }
+
+    /// Writes screen-space motion vectors alongside each traced frame, for
+    /// external temporal upscalers. Costs an extra full-size float texture
+    /// and some shader work, so it is off by default.
+    pub fn set_motion_vectors(&mut self, enabled: bool) {
+        if enabled == self.motion_vectors {
+            return;
+        }
+        self.motion_vectors = enabled;
+        if self.destroyed {
+            return;
+        }
+        let (width, height) = if enabled {
+            (self.compute_width, self.compute_height)
+        } else {
+            (1, 1)
+        };
+        self.motion_texture = create_motion_texture(&self.device, width, height);
+        self.rebuild_compute_bind_group();
+    }
+
+    /// Redisplays the previous frame instead of tracing a new one while the
+    /// camera is still and nothing the shader reads has changed (the planet
+    /// moving less than 1% of its radius counts as unchanged). Saves power
+    /// for mostly static viewing; any setter that changes the image forces
+    /// the next frame to be traced.
+    pub fn set_frame_reuse(&mut self, enabled: bool) {
+        self.frame_reuse = enabled;
+    }
+
+    fn can_reuse_frame(&self) -> bool {
+        if !self.frame_reuse || self.camera.moving {
+            return false;
+        }
+        match &self.last_traced {
+            Some((inputs, planet_position)) => {
+                *inputs == self.trace_inputs
+                    && planet_position.distance(self.prev_planet_position)
+                        <= self.planet.radius * REUSE_PLANET_TOLERANCE
+            }
+            None => false,
+        }
+    }
+
+    /// Counts ray outcomes in the compute pass for `frame_stats`. Off by
+    /// default, since every pixel then does a few atomic adds.
+    pub fn set_frame_stats(&mut self, enabled: bool) {
+        self.settings.frame_stats = enabled;
+    }
+
+    /// Reads back how the rays of the last traced frame ended: the fraction
+    /// captured, hitting the disk or planet, escaped, or still going when
+    /// the step budget ran out, and their mean closest approach in r_s.
+    /// Resolves to a JSON string. Needs `set_frame_stats(true)` before the
+    /// frame is traced.
+    pub fn frame_stats(&self) -> Result<js_sys::Promise, JsValue> {
+        self.ensure_alive()?;
+        if !self.settings.frame_stats {
+            return Err(JsValue::from_str(
+                "Frame statistics are disabled; call set_frame_stats(true) first",
+            ));
+        }
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Stats Readback Encoder"),
+            });
+        let buffer =
+            readback::copy_buffer_to_buffer(&self.device, &mut encoder, &self.stats_buffer);
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        let counters = (self.stats_buffer.size() / 4) as u32;
+        let readback = readback::PendingReadback::new(buffer, counters, 1, 4);
+        let max_steps_threshold = self.step_budget.threshold();
+        self.device.poll(wgpu::Maintain::Wait);
+
+        Ok(wasm_bindgen_futures::future_to_promise(async move {
+            let bytes = readback.await.map_err(|e| JsValue::from_str(&e))?;
+            let counters: Vec<u32> = bytes
+                .chunks_exact(4)
+                .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect();
+            let stats = FrameStats::from_counters(&counters);
+            if stats.max_steps_fraction > max_steps_threshold {
+                log::warn!(
+                    "{:.0}% of traced pixels ran out of steps before being captured or escaping; \
+                     raise the step count with set_integration_quality or lower the escape radius",
+                    stats.max_steps_fraction * 100.0
+                );
+            }
+            let json = serde_json::to_string(&stats).map_err(|e| {
+                JsValue::from_str(&format!("Failed to serialize frame stats: {}", e))
+            })?;
+            Ok(JsValue::from_str(&json))
+        }))
+    }
+
+    /// Reads back the motion vectors of the last traced frame. Resolves to a
+    /// `Float32Array` of four floats per compute pixel: how far the point
+    /// seen there has moved since the previous frame, as previous minus
+    /// current pixel position in x and y; then 1 if it could be reprojected
+    /// (0 for points that were behind the camera); then 0.
+    pub fn read_motion_vectors(&self) -> Result<js_sys::Promise, JsValue> {
+        self.ensure_alive()?;
+        if !self.motion_vectors {
+            return Err(JsValue::from_str(
+                "Motion vectors are disabled; call set_motion_vectors(true) first",
+            ));
+        }
+
+        Ok(self.read_float_texture(&self.motion_texture))
+    }
+
+    /// Writes, for every pixel, the background UV its ray ended up sampling,
+    /// so any equirectangular image can be lensed in post without redoing
+    /// the physics. Costs an extra full-size float texture, so it is off by
+    /// default.
+    pub fn set_lens_map(&mut self, enabled: bool) {
+        if enabled == self.settings.lens_map {
+            return;
+        }
+        self.settings.lens_map = enabled;
+        if self.destroyed {
+            return;
+        }
+        let (width, height) = if enabled {
+            (self.compute_width, self.compute_height)
+        } else {
+            (1, 1)
+        };
+        self.lens_texture = create_lens_texture(&self.device, width, height);
+        self.rebuild_compute_bind_group();
+    }
+
+    /// Reads back the lens map of the last traced frame. Resolves to a
+    /// `Float32Array` of two floats per compute pixel, row by row from the
+    /// top left: the `(u, v)` in `[0, 1]` of the equirectangular background
+    /// that pixel showed (after `set_background_rotation`), with u along
+    /// longitude and v = 0 at the top. Pixels that show the shadow, the
+    /// disk or the planet hold `(-1, -1)`.
+    pub fn export_lens_map(&self) -> Result<js_sys::Promise, JsValue> {
+        self.ensure_alive()?;
+        if !self.settings.lens_map {
+            return Err(JsValue::from_str(
+                "The lens map is disabled; call set_lens_map(true) first",
+            ));
+        }
+        Ok(self.read_float_texture(&self.lens_texture))
+    }
+
+    /// Reads a compute-sized 32-bit float texture back as a `Float32Array`.
+    fn read_float_texture(&self, texture: &wgpu::Texture) -> js_sys::Promise {
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Float Readback Encoder"),
+            });
+        let buffer = readback::copy_texture_to_buffer(
+            &self.device,
+            &mut encoder,
+            texture,
+            self.compute_width,
+            self.compute_height,
+        );
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        let readback = readback::PendingReadback::new(
+            buffer,
+            self.compute_width,
+            self.compute_height,
+            readback::bytes_per_pixel(texture),
+        );
+        self.device.poll(wgpu::Maintain::Wait);
+
+        wasm_bindgen_futures::future_to_promise(async move {
+            let bytes = readback.await.map_err(|e| JsValue::from_str(&e))?;
+            let floats: Vec<f32> = bytes
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect();
+            Ok(js_sys::Float32Array::from(floats.as_slice()).into())
+        })
+    }
+
+    /// Adds `count` bright spots orbiting in the disk at the local Keplerian
+    /// rate, so inner spots lap outer ones. Each fades in and out over
+    /// `lifetime` simulated seconds and then respawns elsewhere; `brightness`
+    /// scales their emission before Doppler beaming. A count of 0 disables
+    /// them.
+    pub fn set_disk_hotspots(
+        &mut self,
+        count: u32,
+        brightness: f32,
+        lifetime: f32,
+    ) -> Result<(), JsValue> {
+        if count > MAX_DISK_HOTSPOTS {
+            return Err(JsValue::from_str(&format!(
+                "At most {} hotspots are supported, got {}",
+                MAX_DISK_HOTSPOTS, count
+            )));
+        }
+        if !brightness.is_finite() || brightness < 0.0 || !lifetime.is_finite() || lifetime <= 0.0 {
+            return Err(JsValue::from_str(&format!(
+                "Hotspot brightness must be non-negative and lifetime positive, got {} and {}",
+                brightness, lifetime
+            )));
+        }
+        self.disk.hotspot_count = count;
+        self.disk.hotspot_brightness = brightness;
+        self.disk.hotspot_lifetime = lifetime;
+        Ok(())
+    }
+
+    /// Shades the disk face on the camera's side brighter than the face seen
+    /// from below (or above) through lensing.
+    pub fn set_disk_two_sided(&mut self, enabled: bool) {
+        self.disk.two_sided = enabled;
+    }
+
+    /// Releases the GPU memory held by the renderer: every texture and
+    /// buffer is destroyed, then the device itself, which also drops the
+    /// surface's swapchain. Call it before discarding the renderer, since
+    /// `free()` is easy to miss and `Drop` is not guaranteed to run across
+    /// the WASM boundary.
+    ///
+    /// The instance is unusable afterwards: `render`, the captures and
+    /// readbacks, `resize`, `set_overlay` and the shader source setters
+    /// return an error. Every other setter still records its value but
+    /// skips the GPU work it would do (`clear_overlay`, `set_motion_vectors`,
+    /// `set_lens_map`, `set_integration_quality`, `set_present_mode`,
+    /// `set_frame_latency` and `set_pixel_ratio` included), and CPU-side
+    /// queries keep working. Calling `destroy` again does nothing.
+    pub fn destroy(&mut self) {
+        if self.destroyed {
+            return;
+        }
+        self.destroyed = true;
+
+        for texture in [
+            &self.output_texture,
+            &self.overlay_texture,
+            &self.motion_texture,
+            &self.lens_texture,
+            &self.background_texture,
+        ] {
+            texture.destroy();
+        }
+        for buffer in [
+            &self.camera_buffer,
+            &self.disk_buffer,
+            &self.planet_buffer,
+            &self.params_buffer,
+            &self.pulse_buffer,
+            &self.disk_lut_buffer,
+            &self.stats_buffer,
+            &self.display_buffer,
+            &self.motion_buffer,
+        ] {
+            buffer.destroy();
+        }
+        self.last_traced = None;
+        self.pulses.clear();
+        self.device.destroy();
+        log::info!("Renderer destroyed");
+    }
+
+    fn ensure_alive(&self) -> Result<(), JsValue> {
+        if self.destroyed {
+            return Err(JsValue::from_str(
+                "The renderer has been destroyed; create a new one",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Resizes the surface to `width` x `height` CSS pixels. The backing store
+    /// is configured at that size times the pixel ratio, so the canvas element
+    /// should be sized to match. The compute resolution is unaffected.
+    pub fn resize(&mut self, width: u32, height: u32) -> Result<(), JsValue> {
+        self.ensure_alive()?;
+        if width > 0 && height > 0 {
+            self.css_width = width;
+            self.css_height = height;
+            self.configure_surface();
+        }
+        Ok(())
+    }
+
+    /// Chooses how frames are presented and reconfigures the surface. Falls
+    /// back to `Fifo`, which every surface supports, if the requested mode
+    /// isn't available; returns the mode actually in use.
+    pub fn set_present_mode(&mut self, mode: PresentMode) -> PresentMode {
+        let requested = mode.to_wgpu();
+        let present_mode = if self.present_modes.contains(&requested) {
+            requested
+        } else if self.present_modes.contains(&wgpu::PresentMode::Fifo) {
+            log::warn!("Present mode {:?} is unsupported; using Fifo", mode);
+            wgpu::PresentMode::Fifo
+        } else {
+            log::warn!(
+                "Present mode {:?} is unsupported; using {:?}",
+                mode,
+                self.present_modes[0]
+            );
+            self.present_modes[0]
+        };
+
+        if present_mode != self.config.present_mode {
+            self.config.present_mode = present_mode;
+            if !self.destroyed {
+                self.surface.configure(&self.device, &self.config);
+            }
+            log::info!("Present mode set to {:?}", present_mode);
+        }
+        PresentMode::from_wgpu(present_mode)
+    }
+
+    /// The present mode currently in use.
+    pub fn present_mode(&self) -> PresentMode {
+        PresentMode::from_wgpu(self.config.present_mode)
+    }
+
+    /// Sets how many frames may be queued ahead of the display and
+    /// reconfigures the surface. 1 shows input soonest but can stall the
+    /// GPU waiting on the display; 3 keeps it busier for smoother
+    /// throughput at up to two more frames of lag. Defaults to 2.
+    ///
+    /// The request is clamped to 1 to 3 and the value used is returned, or
+    /// 0 when the browser's WebGPU is in use: it paces frames itself and
+    /// ignores the request.
+    pub fn set_frame_latency(&mut self, frames: u32) -> u32 {
+        let frames = frames.clamp(MIN_FRAME_LATENCY, MAX_FRAME_LATENCY);
+        if frames != self.config.desired_maximum_frame_latency {
+            self.config.desired_maximum_frame_latency = frames;
+            if !self.destroyed {
+                self.surface.configure(&self.device, &self.config);
+            }
+            log::info!("Maximum frame latency set to {}", frames);
+        }
+        self.frame_latency()
+    }
+
+    /// Which optional features this device supports, as JSON (see
+    /// `Capabilities`), for hiding toggles it can't run. Gathered once when
+    /// the renderer was created.
+    pub fn capabilities(&self) -> Result<String, JsValue> {
+        serde_json::to_string(&self.capabilities)
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize capabilities: {}", e)))
+    }
+
+    /// Maximum frame latency in effect, or 0 when the browser sets it (see
+    /// `set_frame_latency`).
+    pub fn frame_latency(&self) -> u32 {
+        if self.backend == wgpu::Backend::BrowserWebGpu {
+            0
+        } else {
+            self.config.desired_maximum_frame_latency
+        }
+    }
+
+    /// Sets the device pixel ratio (`window.devicePixelRatio`) used to turn
+    /// CSS sizes passed to `resize` into surface pixels.
+    pub fn set_pixel_ratio(&mut self, ratio: f32) -> Result<(), JsValue> {
+        if !ratio.is_finite() || ratio <= 0.0 {
+            return Err(JsValue::from_str(&format!(
+                "Pixel ratio must be positive, got {}",
+                ratio
+            )));
+        }
+        self.pixel_ratio = ratio;
+        self.configure_surface();
+        Ok(())
+    }
+
+    fn configure_surface(&mut self) {
+        if self.destroyed {
+            return;
+        }
+        let max_dim = self.device.limits().max_texture_dimension_2d;
+        (self.config.width, self.config.height) =
+            surface_size(self.css_width, self.css_height, self.pixel_ratio, max_dim);
+        self.surface.configure(&self.device, &self.config);
+        log::info!(
+            "Resized to {}x{} ({}x{} CSS px at ratio {})",
+            self.config.width,
+            self.config.height,
+            self.css_width,
+            self.css_height,
+            self.pixel_ratio
+        );
+    }
+
+    pub fn on_mouse_move(&mut self, x: f64, y: f64) {
+        let old_az = self.camera.azimuth;
+        let old_el = self.camera.elevation;
+        self.camera.process_mouse_move(x, y);
+        if self.camera.dragging {
+            log::info!(
+                "Mouse move: az {:.4} -> {:.4}, el {:.4} -> {:.4}",
+                old_az,
+                self.camera.azimuth,
+                old_el,
+                self.camera.elevation
+            );
+        }
+    }
+
+    pub fn on_mouse_button(&mut self, button: u8, pressed: bool, x: f64, y: f64) {
+        self.camera.process_mouse_button(button, pressed, x, y);
+    }
+
+    pub fn on_wheel(&mut self, delta_y: f64) {
         self.camera.process_scroll(delta_y);
+        self.clamp_camera_to_hole();
+    }
+
+    /// Reports when the camera's distance from the hole crosses `radius_m`
+    /// meters. Events
+    /// read `"<name>:inward"` or `"<name>:outward"`. `horizon`,
+    /// `photon_sphere` and `isco` are registered by default; reusing a name
+    /// replaces that threshold.
+    pub fn add_radius_threshold(&mut self, name: &str, radius_m: f64) -> Result<(), JsValue> {
+        if !radius_m.is_finite() || radius_m <= 0.0 {
+            return Err(JsValue::from_str(&format!(
+                "Threshold radius must be positive and finite, got {}",
+                radius_m
+            )));
+        }
+        self.thresholds.add(name, radius_m);
+        Ok(())
+    }
+
+    pub fn clear_radius_thresholds(&mut self) {
+        self.thresholds.clear();
+    }
+
+    /// Drains the threshold-crossing events since the last call, oldest
+    /// first. Meant to be called once per frame.
+    pub fn poll_events(&mut self) -> Vec<String> {
+        self.thresholds.drain()
+    }
+
+    /// Fraction of the far-away clock rate experienced by a static observer
+    /// at the camera's current radius.
+    pub fn time_dilation_at_camera(&self) -> f64 {
+        self.black_hole
+            .time_dilation(self.camera_basis().0.length() as f64)
+    }
+
+    /// Fraction of the far-away clock rate at the inner edge of the disk.
+    pub fn time_dilation_at_disk_inner_edge(&self) -> f64 {
+        self.black_hole.time_dilation(self.disk.inner_radius as f64)
+    }
+
+    /// Escape speed in m/s for a static observer `radius` meters from the
+    /// hole.
+    pub fn local_escape_velocity(&self, radius: f64) -> f64 {
+        self.black_hole.local_escape_velocity(radius)
+    }
+
+    /// Locally measured circular-orbit speed at `radius` meters as a fraction
+    /// of c; infinity inside the photon sphere.
+    pub fn orbit_speed_fraction(&self, radius: f64) -> f64 {
+        self.black_hole.local_orbit_speed_fraction(radius)
+    }
+
+    /// Encodes the mass, camera view, field of view and disk radii as a
+    /// compact query string for shareable links, e.g.
+    /// `m=4.29e6&az=0&el=1.66&r=1.67e11&fov=60&di=2.79e10&do=6.6e10`.
+    pub fn export_url_params(&self) -> String {
+        self.view_params().to_query()
+    }
+
+    /// Restores a view from `export_url_params` output; a leading `?` or `#`
+    /// is accepted. The mass is applied at once rather than eased toward. If
+    /// the string is malformed, the mass, camera and disk are reset to their
+    /// defaults and an error describing the problem is returned.
+    pub fn import_url_params(&mut self, params: &str) -> Result<(), JsValue> {
+        match ViewParams::from_query(params, self.view_params()) {
+            Ok(view) => {
+                self.apply_view_params(view);
+                Ok(())
+            }
+            Err(e) => {
+                let camera = Camera::new();
+                let constants = self.black_hole.constants;
+                let black_hole = BlackHole::with_constants(
+                    self.black_hole.position,
+                    constants.mass_from_si(BlackHole::sagittarius_a().mass),
+                    constants,
+                );
+                let disk = Disk::from_black_hole(&black_hole);
+                self.apply_view_params(ViewParams {
+                    solar_masses: black_hole.mass / constants.solar_mass(),
+                    azimuth: camera.azimuth,
+                    elevation: camera.elevation,
+                    radius: camera.radius,
+                    fov: camera.fov,
+                    disk_inner_radius: disk.inner_radius,
+                    disk_outer_radius: disk.outer_radius,
+                });
+                Err(JsValue::from_str(&format!("Invalid URL parameters: {}", e)))
+            }
+        }
+    }
+
+    /// Renders a `size` x `size` preview of the view in `params`, in
+    /// `export_url_params` form, on the CPU without touching the live view,
+    /// for galleries of saved links. Keys missing from `params` take the live
+    /// view's values, and the disk's style, colors and tilt are the live
+    /// disk's. Returns `size * size` RGBA8 pixels row by row from the top
+    /// left; see `reference::render_thumbnail` for how it differs from the
+    /// shader's image.
+    pub fn render_thumbnail(&self, params: &str, size: u32) -> Result<Vec<u8>, JsValue> {
+        if !(1..=reference::MAX_THUMBNAIL_SIZE).contains(&size) {
+            return Err(JsValue::from_str(&format!(
+                "Thumbnail size must be between 1 and {}, got {}",
+                reference::MAX_THUMBNAIL_SIZE,
+                size
+            )));
+        }
+        let view = ViewParams::from_query(params, self.view_params())
+            .map_err(|e| JsValue::from_str(&format!("Invalid URL parameters: {}", e)))?;
+        let image = reference::render_thumbnail(&view, &self.disk, size, &self.reference_sky);
+        Ok(image.into_raw())
+    }
+
+    fn view_params(&self) -> ViewParams {
+        ViewParams {
+            solar_masses: self.target_mass / self.black_hole.constants.solar_mass(),
+            azimuth: self.camera.azimuth,
+            elevation: self.camera.elevation,
+            radius: self.camera.radius,
+            fov: self.camera.fov,
+            disk_inner_radius: self.disk.inner_radius,
+            disk_outer_radius: self.disk.outer_radius,
+        }
+    }
+
+    fn apply_view_params(&mut self, view: ViewParams) {
+        self.target_mass = view.solar_masses * self.black_hole.constants.solar_mass();
+        self.rescale_to_mass(self.target_mass);
+        self.camera.dolly = None;
+        self.camera.fov = view.fov;
+        self.camera.target = Vec3::ZERO;
+        self.camera.azimuth = view.azimuth;
+        self.camera.elevation = view.elevation;
+        self.camera.constrain_elevation();
+        self.camera.radius = view
+            .radius
+            .clamp(self.camera.min_radius, self.camera.max_radius);
+        self.disk.inner_radius = view.disk_inner_radius;
+        self.disk.outer_radius = view.disk_outer_radius;
+    }
+
+    /// Logs a warning, at most every few seconds, when too many CPU-traced
+    /// rays have been running out of steps.
+    fn warn_if_steps_exhausted(&self) {
+        if let Some(fraction) = self.step_budget.check(js_sys::Date::now() / 1000.0) {
+            log::warn!(
+                "{:.0}% of CPU-traced rays ran out of steps before being captured or escaping; \
+                 raise the step count with set_integration_quality or lower the escape radius",
+                fraction * 100.0
+            );
+        }
+    }
+
+    /// Sets the fraction of traced rays, in `[0, 1]`, that may run out of
+    /// steps before a warning is logged suggesting a larger step budget or a
+    /// smaller escape radius. Applies to CPU traces (`trace_batch`,
+    /// `winding_number`, `magnification`, `planet_screen_position`), checked
+    /// at most every few seconds, and to the shader's rays whenever
+    /// `frame_stats` is read. Defaults to 0.05.
+    pub fn set_max_steps_warning_threshold(&mut self, fraction: f64) -> Result<(), JsValue> {
+        if !(0.0..=1.0).contains(&fraction) {
+            return Err(JsValue::from_str(&format!(
+                "Warning threshold must be a fraction between 0 and 1, got {}",
+                fraction
+            )));
+        }
+        self.step_budget.set_threshold(fraction);
+        Ok(())
+    }
+
+    /// Traces many rays from the camera position in one call, avoiding a JS
+    /// round trip per ray. `directions` holds world-space xyz triples, already
+    /// normalized; each yields one byte: 0 captured by the hole, 1 disk,
+    /// 2 object, 3 escaped, 4 ran out of steps. Rays are traced as the
+    /// shader traces them (see `trace_like_view`).
+    pub fn trace_batch(&self, directions: &[f32]) -> Result<Vec<u8>, JsValue> {
+        if directions.len() % 3 != 0 {
+            return Err(JsValue::from_str(&format!(
+                "Directions must be xyz triples, got {} floats",
+                directions.len()
+            )));
+        }
+        let origin = self.camera_basis().0;
+        let (disk, planet_radius) = self.shown_scene();
+        let results = integrator::trace_batch(
+            origin,
+            directions,
+            self.black_hole.r_s,
+            self.settings.escape_radius,
+            integrator::adaptive_steps(origin, self.black_hole.r_s, self.max_steps),
+            &disk,
+            self.planet.position,
+            planet_radius,
+        )
+        .into_iter()
+        .map(|traced| {
+            self.step_budget.record(traced.result);
+            traced.result as u8
+        })
+        .collect();
+        self.warn_if_steps_exhausted();
+        Ok(results)
+    }
+
+    /// Renders the live view on the CPU with `reference::reference_render`,
+    /// returning `width * height` RGBA8 pixels row by row from the top left.
+    /// Comparing it with a capture shows where the shader has drifted from
+    /// the Rust side. The sky is the star map at `REFERENCE_SKY_WIDTH`, and
+    /// the scene is the one `shown_scene` traces, less everything the
+    /// reference leaves out (jets, the planet, overlays and the rest).
+    pub fn render_reference(&self, width: u32, height: u32) -> Result<Vec<u8>, JsValue> {
+        if !(1..=MAX_REFERENCE_SIZE).contains(&width) || !(1..=MAX_REFERENCE_SIZE).contains(&height)
+        {
+            return Err(JsValue::from_str(&format!(
+                "Reference size must be between 1 and {} on each side, got {}x{}",
+                MAX_REFERENCE_SIZE, width, height
+            )));
+        }
+        let (disk, _) = self.shown_scene();
+        let pixels = reference::reference_render(
+            width,
+            height,
+            &self.camera,
+            &self.black_hole,
+            &disk,
+            &self.reference_sky,
+            self.max_steps,
+            self.settings.escape_radius,
+        );
+        Ok(pixels.into_iter().flatten().collect())
+    }
+
+    /// How many times the ray through `ndc` (y up) winds around the hole, in
+    /// turns, traced as the shader traces it (see `trace_like_view`). Near 0
+    /// for rays aimed straight away from the hole and about 0.5 for rays
+    /// bent past it once; each loop around the photon sphere adds one, so
+    /// the value gives the order of the lensed image the pixel shows. Rays
+    /// that fall in, hit something or run out of steps report the turns
+    /// made so far.
+    pub fn winding_number(&self, ndc_x: f32, ndc_y: f32) -> f32 {
+        let (origin, direction) = self.primary_ray(ndc_x, ndc_y);
+        let traced = self.trace_like_view(origin, direction);
+        self.step_budget.record(traced.result);
+        self.warn_if_steps_exhausted();
+        traced.winding as f32
+    }
+
+    /// Traces the ray through `ndc` (y up) on the CPU as the shader would
+    /// (see `trace_like_view`) and returns everything about it as JSON (see
+    /// `PixelInspection`): the geodesic state at the camera and where it
+    /// stopped, how it ended, the steps taken out of the ray's adaptive
+    /// budget, closest approach, winding, and either the background texture
+    /// coordinates it lands on or the disk radius it hits. Inspecting does
+    /// not count toward the step budget warning.
+    pub fn inspect_pixel(&self, ndc_x: f32, ndc_y: f32) -> Result<String, JsValue> {
+        if !ndc_x.is_finite() || !ndc_y.is_finite() {
+            return Err(JsValue::from_str("Inspected pixel position must be finite"));
+        }
+        let (origin, direction) = self.primary_ray(ndc_x, ndc_y);
+        let r_s = self.black_hole.r_s;
+        let traced = self.trace_like_view(origin, direction);
+
+        let final_direction = if traced.direction == Vec3::ZERO {
+            direction
+        } else {
+            traced.direction
+        };
+        let final_ray = (traced.result != integrator::TraceResult::HitBlackHole)
+            .then(|| integrator::init_ray(traced.position, final_direction, r_s));
+        let background_uv = if traced.result == integrator::TraceResult::Escaped {
+            self.settings.background_uv(traced.position)
+        } else {
+            [-1.0, -1.0]
+        };
+        let disk_hit_radius_rs = if traced.result == integrator::TraceResult::HitDisk {
+            let local = self.disk.to_local(traced.position);
+            (local.x as f64).hypot(local.z as f64) / r_s
+        } else {
+            -1.0
+        };
+
+        let inspection = PixelInspection {
+            ndc: [ndc_x, ndc_y],
+            result: format!("{:?}", traced.result),
+            steps: traced.steps,
+            max_steps: integrator::adaptive_steps(origin, r_s, self.max_steps),
+            min_radius_rs: traced.min_r / r_s,
+            winding: traced.winding,
+            path_length_rs: traced.path_length / r_s,
+            initial_ray: integrator::init_ray(origin, direction, r_s),
+            final_ray,
+            final_position: traced.position.to_array(),
+            background_uv,
+            disk_hit_radius_rs,
+        };
+        serde_json::to_string(&inspection)
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize pixel inspection: {}", e)))
+    }
+
+    /// How much lensing magnifies the background seen through `ndc` (y up):
+    /// the solid angle a small patch of the image covers divided by the
+    /// solid angle of sky it shows. 1 means no lensing; it exceeds 1 near
+    /// the Einstein ring and falls below 1 for demagnified inner images.
+    ///
+    /// It is the inverse Jacobian determinant of the map from ray direction
+    /// to escape direction, from finite differences of three rays traced as
+    /// the shader traces them (see `trace_like_view`),
+    /// `MAGNIFICATION_STEP_NDC` apart. On the critical curve the true value
+    /// diverges, so results are capped at `MAX_MAGNIFICATION`; within a few
+    /// steps of the curve the difference spans it and the value is only a
+    /// rough guide. `NaN` if any of the rays fails to escape or with an
+    /// orthographic camera, whose rays all start parallel.
+    pub fn magnification(&self, ndc_x: f32, ndc_y: f32) -> f32 {
+        if self.camera.projection == Projection::Orthographic {
+            return f32::NAN;
+        }
+        let h = MAGNIFICATION_STEP_NDC;
+        let samples = [(ndc_x, ndc_y), (ndc_x + h, ndc_y), (ndc_x, ndc_y + h)];
+        let mut directions = [DVec3::ZERO; 3];
+        let mut escapes = [DVec3::ZERO; 3];
+        for (i, (x, y)) in samples.into_iter().enumerate() {
+            let (origin, direction) = self.primary_ray(x, y);
+            let traced = self.trace_like_view(origin, direction);
+            self.step_budget.record(traced.result);
+            if traced.result != integrator::TraceResult::Escaped {
+                self.warn_if_steps_exhausted();
+                return f32::NAN;
+            }
+            directions[i] = direction.normalize().as_dvec3();
+            escapes[i] = traced.direction.as_dvec3();
+        }
+        self.warn_if_steps_exhausted();
+        integrator::magnification(directions, escapes).min(MAX_MAGNIFICATION) as f32
+    }
+
+    /// Checks the CPU integrator's time symmetry on the ray through `ndc`
+    /// (y up): traces it `steps` steps forward, reverses it and traces the
+    /// same number back, returning how far from the camera it ends up, in
+    /// Schwarzschild radii. A few thousandths of r_s is expected for rays
+    /// that pass near the hole over ten thousand or so steps; see
+    /// `integrator::retrace_error`. `NaN` if the ray falls in. `steps` is
+    /// capped at `MAX_RETRACE_STEPS`.
+    pub fn retrace_error_rs(&self, ndc_x: f32, ndc_y: f32, steps: u32) -> f64 {
+        let (origin, direction) = self.primary_ray(ndc_x, ndc_y);
+        let steps = steps.min(MAX_RETRACE_STEPS) as usize;
+        integrator::retrace_error(origin, direction, self.black_hole.r_s, steps)
+            .map_or(f64::NAN, |meters| meters / self.black_hole.r_s)
+    }
+
+    /// Samples the photon effective potential `V(r) = L^2 (1 - r_s/r) / r^2`
+    /// of the black hole at `samples` radii from `r_min_rs` to `r_max_rs`,
+    /// given in Schwarzschild radii, with `L` in meters. Returns a flat
+    /// `[r0, V0, r1, V1, ...]` array with radii in Schwarzschild radii, for
+    /// plotting the barrier that peaks at the photon sphere (1.5 r_s).
+    pub fn effective_potential_curve(
+        &self,
+        r_min_rs: f64,
+        r_max_rs: f64,
+        samples: u32,
+        angular_momentum: f64,
+    ) -> Result<Vec<f64>, JsValue> {
+        check_curve_range(r_min_rs, r_max_rs, samples)?;
+        let r_s = self.black_hole.r_s;
+        Ok(self
+            .black_hole
+            .effective_potential_curve(
+                r_min_rs * r_s,
+                r_max_rs * r_s,
+                samples as usize,
+                angular_momentum,
+            )
+            .into_iter()
+            .flat_map(|(r, v)| [r / r_s, v])
+            .collect())
+    }
+
+    /// Samples the metric factor `f(r) = 1 - r_s/r` at `samples` radii from
+    /// `r_min_rs` to `r_max_rs`, in Schwarzschild radii. Returns a flat
+    /// `[r0, f0, r1, f1, ...]` array with radii in Schwarzschild radii, for
+    /// plotting the curve through zero at the horizon; start below 1 to
+    /// show it going negative inside.
+    pub fn metric_curve(
+        &self,
+        r_min_rs: f64,
+        r_max_rs: f64,
+        samples: u32,
+    ) -> Result<Vec<f64>, JsValue> {
+        check_curve_range(r_min_rs, r_max_rs, samples)?;
+        let r_s = self.black_hole.r_s;
+        Ok(self
+            .black_hole
+            .metric_samples(r_min_rs * r_s, r_max_rs * r_s, samples as usize)
+            .into_iter()
+            .flat_map(|(r, f)| [r / r_s, f])
+            .collect())
+    }
+
+    /// Current scene as JSON (see `SceneInfo`) for the frontend to format.
+    pub fn scene_info(&self) -> Result<String, JsValue> {
+        serde_json::to_string(&self.scene_info_data())
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize scene info: {}", e)))
     }
 
     pub fn camera_info(&self) -> String {
-        let pos = self.camera.position();
+        let info = self.scene_info_data();
+        let pos = info.camera_position_m;
         format!(
             "Camera: pos=({:.2e}, {:.2e}, {:.2e}), radius={:.2e}m, az={:.2}, el={:.2}",
-            pos.x, pos.y, pos.z, self.camera.radius, self.camera.azimuth, self.camera.elevation
+            pos[0],
+            pos[1],
+            pos[2],
+            info.camera_radius_m,
+            info.camera_azimuth,
+            info.camera_elevation
+        )
+    }
+
+    /// One-sentence plain-language summary of the view for screen readers,
+    /// e.g. "Viewing Sagittarius A* from 1.2 times the photon sphere radius,
+    /// disk seen 5 degrees from edge-on, planet near periapsis." Built from
+    /// the live state on every call, so it can back an `aria-live` region.
+    pub fn accessible_description(&self) -> String {
+        let sagittarius = BlackHole::sagittarius_a();
+        let mass_kg = self.black_hole.constants.mass_to_si(self.black_hole.mass);
+        let subject = if (mass_kg / sagittarius.mass - 1.0).abs() < 1e-6 {
+            "Sagittarius A*".to_string()
+        } else {
+            format!(
+                "a black hole of {:.3} million solar masses",
+                mass_kg / SOLAR_MASS / 1e6
+            )
+        };
+
+        let photon_sphere = 1.5 * self.black_hole.r_s;
+        let distance = self.camera_distance() as f64 / photon_sphere;
+
+        // Angle of the line of sight from the disk's normal, tilt and
+        // precession included, so 90 degrees is edge-on
+        let normal = self.disk.orientation() * Vec3::Y;
+        let to_camera = self.camera_basis().0.normalize();
+        let from_normal = normal.dot(to_camera).clamp(-1.0, 1.0).acos().to_degrees();
+        let tilt = (from_normal - 90.0).abs().round();
+        let disk = if tilt < 1.0 {
+            "disk seen edge-on".to_string()
+        } else if tilt > 89.0 {
+            "disk seen face-on".to_string()
+        } else {
+            format!("disk seen {} degrees from edge-on", tilt)
+        };
+
+        let planet = if !self.planet_visible {
+            "planet hidden".to_string()
+        } else if self.planet.eccentricity < 0.01 {
+            "planet on a circular orbit".to_string()
+        } else {
+            let a = self.planet.semi_major_axis;
+            let e = self.planet.eccentricity;
+            let periapsis = a * (1.0 - e);
+            let apoapsis = a * (1.0 + e);
+            let progress = (self.planet.position.length() - periapsis) / (apoapsis - periapsis);
+            if progress < 0.25 {
+                "planet near periapsis".to_string()
+            } else if progress > 0.75 {
+                "planet near apoapsis".to_string()
+            } else if self.planet.position.dot(self.planet.velocity) < 0.0 {
+                "planet falling toward periapsis".to_string()
+            } else {
+                "planet climbing toward apoapsis".to_string()
+            }
+        };
+
+        format!(
+            "Viewing {} from {:.1} times the photon sphere radius, {}, {}.",
+            subject, distance, disk, planet
         )
     }
+
+    fn scene_info_data(&self) -> SceneInfo {
+        let pos = self.black_hole.to_local(self.camera.position());
+        let r_s = self.black_hole.r_s as f32;
+        SceneInfo {
+            camera_position_m: pos.to_array(),
+            camera_position_rs: (pos / r_s).to_array(),
+            camera_radius_m: pos.length(),
+            camera_radius_rs: pos.length() / r_s,
+            camera_azimuth: self.camera.azimuth,
+            camera_elevation: self.camera.elevation,
+            black_hole_mass_kg: self.black_hole.constants.mass_to_si(self.black_hole.mass),
+            black_hole_mass_solar: self.black_hole.constants.mass_to_si(self.black_hole.mass)
+                / SOLAR_MASS,
+            schwarzschild_radius_m: self.black_hole.r_s,
+            disk_inner_radius_m: self.disk.inner_radius,
+            disk_outer_radius_m: self.disk.outer_radius,
+            planet_position_m: self.planet.position.to_array(),
+            planet_semi_major_axis_m: self.planet.semi_major_axis,
+            planet_eccentricity: self.planet.eccentricity,
+            planet_mean_motion: self.planet.mean_motion,
+            fps: self.fps,
+        }
+    }
+}
+
+/// Builds the compute pipeline with the shader's `NSTEPS` override set to
+/// `max_steps`.
+fn create_compute_pipeline(
+    device: &Device,
+    layout: &wgpu::PipelineLayout,
+    module: &wgpu::ShaderModule,
+    max_steps: u32,
+) -> wgpu::ComputePipeline {
+    let constants = std::collections::HashMap::from([("NSTEPS".to_string(), max_steps as f64)]);
+    device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some("Compute Pipeline"),
+        layout: Some(layout),
+        module,
+        entry_point: Some("main"),
+        compilation_options: wgpu::PipelineCompilationOptions {
+            constants: &constants,
+            ..Default::default()
+        },
+        cache: None,
+    })
+}
+
+fn create_output_texture(device: &Device, width: u32, height: u32) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Output Texture"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba8Unorm,
+        usage: wgpu::TextureUsages::STORAGE_BINDING
+            | wgpu::TextureUsages::TEXTURE_BINDING
+            | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    })
+}
+
+/// Per-pixel background UVs for the lens map.
+fn create_lens_texture(device: &Device, width: u32, height: u32) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Lens Texture"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rg32Float,
+        usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    })
+}
+
+/// Adapter requests in order of preference: the discrete GPU first, then
+/// the integrated GPUs and fallback (software) adapters that locked-down
+/// browsers may only offer.
+const ADAPTER_REQUESTS: [(wgpu::PowerPreference, bool); 3] = [
+    (wgpu::PowerPreference::HighPerformance, false),
+    (wgpu::PowerPreference::LowPower, false),
+    (wgpu::PowerPreference::LowPower, true),
+];
+
+async fn request_adapter(
+    instance: &Instance,
+    surface: Option<&Surface<'static>>,
+    (power_preference, force_fallback_adapter): (wgpu::PowerPreference, bool),
+) -> Option<wgpu::Adapter> {
+    log::info!(
+        "Requesting adapter: power_preference={:?}, force_fallback_adapter={}",
+        power_preference,
+        force_fallback_adapter
+    );
+    instance
+        .request_adapter(&RequestAdapterOptions {
+            power_preference,
+            compatible_surface: surface,
+            force_fallback_adapter,
+        })
+        .await
+}
+
+/// The first adapter `ADAPTER_REQUESTS` yields.
+async fn request_default_adapter(
+    instance: &Instance,
+    surface: &Surface<'static>,
+) -> Result<wgpu::Adapter, JsValue> {
+    for request in ADAPTER_REQUESTS {
+        if let Some(adapter) = request_adapter(instance, Some(surface), request).await {
+            log::info!("Adapter request succeeded");
+            return Ok(adapter);
+        }
+        log::warn!("Adapter request failed");
+    }
+    Err(JsValue::from_str(
+        "Failed to find an appropriate adapter (tried high-performance, low-power \
+         and fallback adapters); WebGPU may be disabled or unsupported in this browser",
+    ))
+}
+
+/// Every distinct adapter `ADAPTER_REQUESTS` yields, in request order. No
+/// surface is given, so the list doesn't depend on a canvas.
+async fn request_adapters(instance: &Instance) -> Vec<wgpu::Adapter> {
+    let mut adapters: Vec<wgpu::Adapter> = Vec::new();
+    for request in ADAPTER_REQUESTS {
+        let Some(adapter) = request_adapter(instance, None, request).await else {
+            continue;
+        };
+        let info = adapter.get_info();
+        let seen = adapters.iter().any(|known| {
+            let known = known.get_info();
+            known.name == info.name
+                && known.backend == info.backend
+                && known.device_type == info.device_type
+                && known.device == info.device
+        });
+        if !seen {
+            adapters.push(adapter);
+        }
+    }
+    adapters
+}
+
+/// Checks the arguments of the radius-sampled curves
+/// (`effective_potential_curve`, `metric_curve`): `0 < r_min < r_max`,
+/// finite, with at least 2 samples.
+fn check_curve_range(r_min_rs: f64, r_max_rs: f64, samples: u32) -> Result<(), JsValue> {
+    if !(r_min_rs > 0.0 && r_max_rs > r_min_rs && r_max_rs.is_finite()) || samples < 2 {
+        return Err(JsValue::from_str(&format!(
+            "Need 0 < r_min < r_max and at least 2 samples, got {}..{} with {}",
+            r_min_rs, r_max_rs, samples
+        )));
+    }
+    Ok(())
+}
+
+/// Surface size in device pixels for `css_width` x `css_height` CSS pixels
+/// at `pixel_ratio`. Each side is kept within `1..=max_dim`, so a canvas that
+/// reports 0x0 before layout still gets a surface it can configure.
+fn surface_size(css_width: u32, css_height: u32, pixel_ratio: f32, max_dim: u32) -> (u32, u32) {
+    let scale = |css: u32| ((css as f32 * pixel_ratio).round() as u32).clamp(1, max_dim);
+    (scale(css_width), scale(css_height))
+}
+
+fn describe_adapter(info: &wgpu::AdapterInfo) -> String {
+    let name = if info.name.is_empty() {
+        format!("{:?}", info.device_type)
+    } else {
+        info.name.clone()
+    };
+    format!("{} ({:?})", name, info.backend)
+}
+
+fn gather_capabilities(
+    adapter: &wgpu::Adapter,
+    surface_caps: &wgpu::SurfaceCapabilities,
+) -> Capabilities {
+    let backend = adapter.get_info().backend;
+    let limits = adapter.limits();
+    let supports = |format: wgpu::TextureFormat, usage: TextureUsages| {
+        adapter
+            .get_texture_format_features(format)
+            .allowed_usages
+            .contains(usage)
+    };
+    Capabilities {
+        backend: format!("{:?}", backend),
+        compute_shaders: adapter
+            .get_downlevel_capabilities()
+            .flags
+            .contains(wgpu::DownlevelFlags::COMPUTE_SHADERS),
+        storage_buffers: limits.max_storage_buffers_per_shader_stage > 0,
+        storage_textures: limits.max_storage_textures_per_shader_stage > 0,
+        float_storage_textures: supports(
+            wgpu::TextureFormat::Rgba32Float,
+            TextureUsages::STORAGE_BINDING,
+        ) && supports(
+            wgpu::TextureFormat::Rg32Float,
+            TextureUsages::STORAGE_BINDING,
+        ),
+        float_render_targets: supports(
+            wgpu::TextureFormat::Rgba16Float,
+            TextureUsages::RENDER_ATTACHMENT,
+        ),
+        float32_filterable: adapter.features().contains(Features::FLOAT32_FILTERABLE),
+        hdr_surface: surface_caps
+            .formats
+            .contains(&wgpu::TextureFormat::Rgba16Float),
+        max_texture_size: limits.max_texture_dimension_2d,
+        frame_latency: backend != wgpu::Backend::BrowserWebGpu,
+    }
+}
+
+/// Per-pixel motion vectors: previous minus current pixel position in x/y,
+/// and 1 in z where the point could be reprojected.
+fn create_motion_texture(device: &Device, width: u32, height: u32) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Motion Texture"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba32Float,
+        usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    })
+}
+
+fn create_compute_bind_group(
+    device: &Device,
+    layout: &wgpu::BindGroupLayout,
+    output_texture: &wgpu::Texture,
+    camera_buffer: &wgpu::Buffer,
+    disk_buffer: &wgpu::Buffer,
+    planet_buffer: &wgpu::Buffer,
+    background_texture: &wgpu::Texture,
+    params_buffer: &wgpu::Buffer,
+    pulse_buffer: &wgpu::Buffer,
+    motion_texture: &wgpu::Texture,
+    motion_buffer: &wgpu::Buffer,
+    disk_lut_buffer: &wgpu::Buffer,
+    stats_buffer: &wgpu::Buffer,
+    lens_texture: &wgpu::Texture,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Compute Bind Group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(
+                    &output_texture.create_view(&wgpu::TextureViewDescriptor::default()),
+                ),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: camera_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: disk_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: planet_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 4,
+                resource: wgpu::BindingResource::TextureView(
+                    &background_texture.create_view(&wgpu::TextureViewDescriptor::default()),
+                ),
+            },
+            wgpu::BindGroupEntry {
+                binding: 5,
+                resource: params_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 6,
+                resource: pulse_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 7,
+                resource: wgpu::BindingResource::TextureView(
+                    &motion_texture.create_view(&wgpu::TextureViewDescriptor::default()),
+                ),
+            },
+            wgpu::BindGroupEntry {
+                binding: 8,
+                resource: motion_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 9,
+                resource: disk_lut_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 10,
+                resource: stats_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 11,
+                resource: wgpu::BindingResource::TextureView(
+                    &lens_texture.create_view(&wgpu::TextureViewDescriptor::default()),
+                ),
+            },
+        ],
+    })
+}
+
+fn create_render_bind_group(
+    device: &Device,
+    layout: &wgpu::BindGroupLayout,
+    output_texture: &wgpu::Texture,
+    sampler: &wgpu::Sampler,
+    display_buffer: &wgpu::Buffer,
+    overlay_texture: &wgpu::Texture,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Render Bind Group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(
+                    &output_texture.create_view(&wgpu::TextureViewDescriptor::default()),
+                ),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: display_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::TextureView(
+                    &overlay_texture.create_view(&wgpu::TextureViewDescriptor::default()),
+                ),
+            },
+        ],
+    })
+}
+
+/// An RGBA8 image stretched over the canvas by the display pass. Stored as
+/// sRGB so canvas pixels are decoded to linear before blending.
+fn create_overlay_texture(
+    device: &Device,
+    queue: &Queue,
+    rgba: &[u8],
+    width: u32,
+    height: u32,
+) -> wgpu::Texture {
+    let size = wgpu::Extent3d {
+        width,
+        height,
+        depth_or_array_layers: 1,
+    };
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Overlay Texture"),
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba8UnormSrgb,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    });
+    queue.write_texture(
+        wgpu::ImageCopyTexture {
+            texture: &texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        rgba,
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(4 * width),
+            rows_per_image: Some(height),
+        },
+        size,
+    );
+    texture
 }
 
 const SHADER_SOURCE: &str = r#"
@@ -681,11 +4627,119 @@ fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> VertexOutput {
     return output;
 }
 
+struct Display {
+    axis_x: vec4<f32>, // screen x, screen y, depth
+    axis_y: vec4<f32>,
+    axis_z: vec4<f32>,
+    resolution: vec2<f32>,
+    axes_enabled: f32,
+    grid_enabled: f32,
+    dither_enabled: f32,
+    overlay_enabled: f32,
+    debug_point_count: f32,
+    _pad2: f32,
+    // NDC x, y (y up) and fraction along the path of the debug ray's steps
+    debug_points: array<vec4<f32>, MAX_DEBUG_POINTS>,
+}
+
 @group(0) @binding(0) var compute_texture: texture_2d<f32>;
 @group(0) @binding(1) var texture_sampler: sampler;
+@group(0) @binding(2) var<uniform> display: Display;
+@group(0) @binding(3) var overlay_texture: texture_2d<f32>;
+
+const GNOMON_SIZE: f32 = 40.0;
+const GRID_OPACITY: f32 = 0.15;
+const MAX_DEBUG_POINTS: u32 = 64u;
+const DEBUG_POINT_RADIUS: f32 = 2.5; // pixels
+
+// Interleaved gradient noise: a cheap, well-distributed per-pixel threshold.
+fn dither_noise(frag: vec2<f32>) -> f32 {
+    return fract(52.9829189 * fract(dot(frag, vec2<f32>(0.06711056, 0.00583715))));
+}
+
+fn segment_distance(p: vec2<f32>, a: vec2<f32>, b: vec2<f32>) -> f32 {
+    let ab = b - a;
+    let t = clamp(dot(p - a, ab) / max(dot(ab, ab), 1e-6), 0.0, 1.0);
+    return length(p - (a + ab * t));
+}
+
+fn draw_axis(color: vec3<f32>, frag: vec2<f32>, origin: vec2<f32>, axis: vec4<f32>, axis_color: vec3<f32>) -> vec3<f32> {
+    // Framebuffer y grows downward
+    let tip = origin + vec2<f32>(axis.x, -axis.y) * GNOMON_SIZE;
+    let coverage = 1.0 - smoothstep(1.0, 2.0, segment_distance(frag, origin, tip));
+    let shade = select(1.0, 0.4, axis.z > 0.0);
+    return mix(color, axis_color * shade, coverage);
+}
 
 @fragment
 fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
-    return textureSample(compute_texture, texture_sampler, input.uv);
+    var color = textureSample(compute_texture, texture_sampler, input.uv).rgb;
+
+    if (display.grid_enabled > 0.5) {
+        // Distance in display pixels to the nearest compute texel edge, from
+        // the texture's real size so it stays right after a resize
+        let texels = vec2<f32>(textureDimensions(compute_texture));
+        let pixels_per_texel = display.resolution / texels;
+        let cell = fract(input.uv * texels);
+        let edge = min(cell, 1.0 - cell) * pixels_per_texel;
+        // No grid when texels are about a pixel wide; it would cover everything
+        let on_edge = (edge.x < 0.5 && pixels_per_texel.x >= 2.0)
+            || (edge.y < 0.5 && pixels_per_texel.y >= 2.0);
+        if (on_edge) {
+            color = mix(color, vec3<f32>(1.0), GRID_OPACITY);
+        }
+    }
+
+    if (display.axes_enabled > 0.5) {
+        let frag = input.position.xy;
+        let origin = vec2<f32>(GNOMON_SIZE + 20.0, display.resolution.y - GNOMON_SIZE - 20.0);
+        color = draw_axis(color, frag, origin, display.axis_x, vec3<f32>(1.0, 0.2, 0.2));
+        color = draw_axis(color, frag, origin, display.axis_y, vec3<f32>(0.2, 1.0, 0.2));
+        color = draw_axis(color, frag, origin, display.axis_z, vec3<f32>(0.3, 0.5, 1.0));
+    }
+
+    let debug_count = min(u32(display.debug_point_count), MAX_DEBUG_POINTS);
+    for (var i = 0u; i < debug_count; i++) {
+        let point = display.debug_points[i];
+        let screen = vec2<f32>((point.x + 1.0) * 0.5, (1.0 - point.y) * 0.5) * display.resolution;
+        let d = length(input.position.xy - screen);
+        let coverage = 1.0 - smoothstep(DEBUG_POINT_RADIUS - 1.0, DEBUG_POINT_RADIUS, d);
+        let dot_color = mix(vec3<f32>(0.2, 0.9, 1.0), vec3<f32>(1.0, 0.5, 0.1), point.z);
+        color = mix(color, dot_color, coverage);
+    }
+
+    if (display.dither_enabled > 0.5) {
+        // Up to half a quantization step either way breaks up banding; fade
+        // it out near black so flat black regions stay clean
+        let noise = (dither_noise(input.position.xy) - 0.5) / 255.0;
+        let fade = smoothstep(0.0, 4.0 / 255.0, max(color.r, max(color.g, color.b)));
+        color = max(color + noise * fade, vec3<f32>(0.0));
+    }
+
+    // Sampled unconditionally: textureSample needs uniform control flow
+    let overlay = textureSample(overlay_texture, texture_sampler, input.uv);
+    if (display.overlay_enabled > 0.5) {
+        color = mix(color, overlay.rgb, overlay.a);
+    }
+
+    return vec4<f32>(color, 1.0);
 }
 "#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unsized_canvas_starts_at_one_pixel_until_resized() {
+        // Constructed before layout, then resized to 800x600 CSS px on a
+        // 2x display
+        assert_eq!(surface_size(0, 0, 1.0, u32::MAX), (1, 1));
+        assert_eq!(surface_size(0, 600, 1.0, u32::MAX), (1, 600));
+        assert_eq!(surface_size(800, 600, 2.0, 8192), (1600, 1200));
+        // A tiny ratio never rounds a side to 0, a large one stops at the
+        // device's texture limit
+        assert_eq!(surface_size(800, 600, 0.0001, 8192), (1, 1));
+        assert_eq!(surface_size(6000, 600, 2.0, 8192), (8192, 1200));
+    }
+}
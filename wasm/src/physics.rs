@@ -1,22 +1,120 @@
-use glam::{Vec3, Vec4};
+use crate::integrator::TimelikeOrbit;
+use glam::{DVec3, Mat3, Vec3, Vec4};
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
 
 pub const C: f64 = 299792458.0;
 pub const G: f64 = 6.67430e-11;
+pub const SOLAR_MASS: f64 = 1.989e30;
+pub const EARTH_MASS: f64 = 5.972e24;
+pub const STEFAN_BOLTZMANN: f64 = 5.670374419e-8;
+
+/// The speed of light and gravitational constant the physics is expressed
+/// in. Lengths are always meters; the constants choose the units of mass and
+/// time. Only `GM/c^2`, a length, shapes the picture, so switching units
+/// with `convert_mass` leaves every deflection unchanged.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PhysicsConstants {
+    pub c: f64,
+    pub g: f64,
+}
+
+impl PhysicsConstants {
+    pub const SI: Self = PhysicsConstants { c: C, g: G };
+    /// `G = c = 1`: masses are lengths in meters and times are meters of
+    /// light travel.
+    pub const GEOMETRIZED: Self = PhysicsConstants { c: 1.0, g: 1.0 };
+
+    pub fn schwarzschild_radius(&self, mass: f64) -> f64 {
+        2.0 * self.g * mass / (self.c * self.c)
+    }
+
+    /// `mass` in these units re-expressed in `to`'s, keeping `GM/c^2`.
+    pub fn convert_mass(&self, mass: f64, to: &PhysicsConstants) -> f64 {
+        mass * (self.g / (self.c * self.c)) / (to.g / (to.c * to.c))
+    }
+
+    /// A mass in kg in these units.
+    pub fn mass_from_si(&self, kg: f64) -> f64 {
+        Self::SI.convert_mass(kg, self)
+    }
+
+    /// The Sun's mass in these units.
+    pub fn solar_mass(&self) -> f64 {
+        self.mass_from_si(SOLAR_MASS)
+    }
+
+    /// A mass in these units in kg.
+    pub fn mass_to_si(&self, mass: f64) -> f64 {
+        self.convert_mass(mass, &Self::SI)
+    }
+}
+
+impl Default for PhysicsConstants {
+    fn default() -> Self {
+        Self::SI
+    }
+}
+
+/// Default radius of the background "sky sphere". Rays that reach it are
+/// treated as escaped, and it doubles as the camera's far clip plane so the
+/// traced scene and any rasterized overlays end at the same distance.
+pub const DEFAULT_ESCAPE_RADIUS: f64 = 1e13;
+
+/// Default disk accretion rate in kg/s, around current estimates for
+/// Sagittarius A*; it gives the disk a peak temperature near 4800 K.
+pub const DEFAULT_ACCRETION_RATE: f64 = 1e15;
+
+/// Tilt of the planet's orbital plane from the hole's equatorial plane, in
+/// degrees, about the x axis.
+const ORBIT_INCLINATION: f32 = 30.0;
+
+/// RGB of a blackbody at `kelvin`, from Tanner Helland's fit to the CIE
+/// data, with the brightest channel at 1. Valid from about 1000 K to 40000 K;
+/// cooler bodies come out pure red.
+pub fn blackbody_rgb(kelvin: f32) -> [f32; 3] {
+    let t = kelvin / 100.0;
+    let red = if t <= 66.0 {
+        255.0
+    } else {
+        329.69873 * (t - 60.0).powf(-0.13320476)
+    };
+    let green = if t <= 66.0 {
+        99.4708 * t.ln() - 161.11957
+    } else {
+        288.12216 * (t - 60.0).powf(-0.075514846)
+    };
+    let blue = if t >= 66.0 {
+        255.0
+    } else if t <= 19.0 {
+        0.0
+    } else {
+        138.51773 * (t - 10.0).ln() - 305.0448
+    };
+    [red, green, blue].map(|c| c.clamp(0.0, 255.0) / 255.0)
+}
 
 #[derive(Debug, Clone, Copy)]
 pub struct BlackHole {
     pub position: Vec3,
+    /// In the units of `constants`; kg for SI.
     pub mass: f64,
     pub r_s: f64,
+    pub constants: PhysicsConstants,
 }
 
 impl BlackHole {
+    /// A hole of `mass` kg, in SI units.
     pub fn new(position: Vec3, mass: f64) -> Self {
-        let r_s = 2.0 * G * mass / (C * C);
+        Self::with_constants(position, mass, PhysicsConstants::SI)
+    }
+
+    pub fn with_constants(position: Vec3, mass: f64, constants: PhysicsConstants) -> Self {
         BlackHole {
             position,
             mass,
-            r_s,
+            r_s: constants.schwarzschild_radius(mass),
+            constants,
         }
     }
 
@@ -24,6 +122,12 @@ impl BlackHole {
         Self::new(Vec3::ZERO, 8.54e36)
     }
 
+    /// A world-space point relative to the hole, the frame the integrator
+    /// works in.
+    pub fn to_local(&self, point: Vec3) -> Vec3 {
+        point - self.position
+    }
+
     pub fn intercept(&self, px: f32, py: f32, pz: f32) -> bool {
         let dx = px as f64 - self.position.x as f64;
         let dy = py as f64 - self.position.y as f64;
@@ -35,6 +139,99 @@ impl BlackHole {
     pub fn schwarzschild_f(&self, r: f64) -> f64 {
         1.0 - self.r_s / r
     }
+
+    /// Speed of a circular orbit at radius `r` in meters per unit of time
+    /// (m/s for SI), as `r * dphi/dt` in Schwarzschild coordinate time.
+    /// `dphi/dt = sqrt(GM/r^3)` holds exactly in Schwarzschild, so a
+    /// circular orbit's period is `2 pi r / v`.
+    pub fn circular_orbit_speed(&self, r: f64) -> f64 {
+        (self.constants.g * self.mass / r).sqrt()
+    }
+
+    /// Impact parameter `b_c = (3 sqrt(3) / 2) r_s` separating photons that
+    /// are captured from those that escape. Seen from far away, this is the
+    /// radius of the shadow's edge (the critical curve).
+    pub fn critical_impact_parameter(&self) -> f64 {
+        1.5 * 3.0f64.sqrt() * self.r_s
+    }
+
+    /// Speed in meters per unit of time, measured by a static observer at
+    /// radius `r`, needed to escape to infinity: `c * sqrt(r_s / r)`.
+    /// Reaches c at the horizon.
+    pub fn local_escape_velocity(&self, r: f64) -> f64 {
+        self.constants.c * (self.r_s / r).sqrt()
+    }
+
+    /// Speed of a circular orbit at radius `r` as a fraction of c, measured
+    /// by a static observer there: `sqrt(r_s / (2 (r - r_s)))`. This is 0.5
+    /// at the ISCO and reaches 1 at the photon sphere (1.5 r_s); inside that
+    /// no circular orbit exists and infinity is returned.
+    pub fn local_orbit_speed_fraction(&self, r: f64) -> f64 {
+        if r <= 1.5 * self.r_s {
+            return f64::INFINITY;
+        }
+        (self.r_s / (2.0 * (r - self.r_s))).sqrt()
+    }
+
+    /// Radius of the innermost stable circular orbit, where an accretion
+    /// disk's inner edge sits. For this non-spinning hole it is 3 r_s; spin
+    /// would pull it inward for prograde orbits and push it out for
+    /// retrograde ones.
+    pub fn isco_radius(&self) -> f64 {
+        3.0 * self.r_s
+    }
+
+    /// Effective potential for a photon with angular momentum `L` per unit
+    /// energy, `V(r) = L^2 (1 - r_s/r) / r^2`. It peaks at the photon sphere,
+    /// `r = 1.5 r_s`: photons whose squared energy exceeds the peak are
+    /// captured, lower ones turn around and escape.
+    pub fn effective_potential(&self, r: f64, angular_momentum: f64) -> f64 {
+        angular_momentum * angular_momentum * self.schwarzschild_f(r) / (r * r)
+    }
+
+    /// `effective_potential` sampled at `samples` evenly spaced radii from
+    /// `r_min` to `r_max` inclusive, as `(r, V)` pairs for plotting.
+    pub fn effective_potential_curve(
+        &self,
+        r_min: f64,
+        r_max: f64,
+        samples: usize,
+        angular_momentum: f64,
+    ) -> Vec<(f64, f64)> {
+        sample_radii(r_min, r_max, samples, |r| {
+            self.effective_potential(r, angular_momentum)
+        })
+    }
+
+    /// `schwarzschild_f` sampled at `count` evenly spaced radii from `r_min`
+    /// to `r_max` inclusive, as `(r, f)` pairs for plotting. The range may
+    /// start inside the horizon, where `f` turns negative; it crosses zero
+    /// at `r_s` and is 0.5 at `2 r_s`.
+    pub fn metric_samples(&self, r_min: f64, r_max: f64, count: usize) -> Vec<(f64, f64)> {
+        sample_radii(r_min, r_max, count, |r| self.schwarzschild_f(r))
+    }
+
+    /// Rate of a static clock at radius `r` relative to one at infinity,
+    /// `sqrt(1 - r_s/r)`. Clamped to 0 at and inside the horizon.
+    pub fn time_dilation(&self, r: f64) -> f64 {
+        self.schwarzschild_f(r).max(0.0).sqrt()
+    }
+}
+
+/// `f` at `n` evenly spaced radii from `r_min` to `r_max` inclusive, as
+/// `(r, f(r))` pairs. A single sample is taken at `r_min`.
+fn sample_radii(r_min: f64, r_max: f64, n: usize, f: impl Fn(f64) -> f64) -> Vec<(f64, f64)> {
+    let step = if n > 1 {
+        (r_max - r_min) / (n - 1) as f64
+    } else {
+        0.0
+    };
+    (0..n)
+        .map(|i| {
+            let r = r_min + step * i as f64;
+            (r, f(r))
+        })
+        .collect()
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -56,7 +253,7 @@ impl ObjectData {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize)]
 pub struct Ray {
     pub r: f64,
     pub theta: f64,
@@ -84,19 +281,127 @@ impl Ray {
         }
     }
 
+    /// The same ray travelling the other way: the spatial velocity
+    /// components and the angular momentum are negated, while `dt` and the
+    /// energy stay positive. Null geodesics are time-symmetric, so
+    /// integrating the result retraces the path back toward where it came
+    /// from, e.g. from the disk toward the observer.
+    pub fn reverse(&self) -> Ray {
+        Ray {
+            dr: -self.dr,
+            dtheta: -self.dtheta,
+            dphi: -self.dphi,
+            angular_momentum: -self.angular_momentum,
+            ..*self
+        }
+    }
+
+    /// Position in Cartesian coordinates, meters, with `theta` measured
+    /// from +z as in `integrator::init_ray`.
+    pub fn position(&self) -> DVec3 {
+        DVec3::new(
+            self.r * self.theta.sin() * self.phi.cos(),
+            self.r * self.theta.sin() * self.phi.sin(),
+            self.r * self.theta.cos(),
+        )
+    }
+
+    /// `position` in single precision: the inverse of
+    /// `integrator::init_ray`'s mapping, with theta measured from +Z and phi
+    /// from +X toward +Y.
     pub fn to_cartesian(&self) -> Vec3 {
         let x = (self.r * self.theta.sin() * self.phi.cos()) as f32;
-        let y = (self.r * self.theta.cos()) as f32;
-        let z = (self.r * self.theta.sin() * self.phi.sin()) as f32;
+        let y = (self.r * self.theta.sin() * self.phi.sin()) as f32;
+        let z = (self.r * self.theta.cos()) as f32;
         Vec3::new(x, y, z)
     }
 }
 
+/// Procedural pattern applied to the disk's emission.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiskStyle {
+    /// Plain radial gradient (the original look).
+    Smooth,
+    /// Domain-warped fbm noise.
+    Turbulent,
+    /// Concentric rings of varying brightness.
+    Banded,
+}
+
+/// Where the disk's base emission color comes from.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiskColorMode {
+    /// Orange at the inner edge warming to yellow outward (the original look).
+    Gradient,
+    /// `Disk::flat_color` everywhere.
+    Flat,
+    /// Blackbody color and brightness of the Novikov-Thorne thin-disk
+    /// temperature profile (see `Disk::novikov_thorne_temperature`): dark at
+    /// the ISCO, brightest a little outside it, fading outward.
+    NovikovThorne,
+}
+
+/// The inputs of `Disk::color_at`, from `Disk::color_key`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DiskColorKey {
+    inner_radius: f32,
+    outer_radius: f32,
+    color_mode: DiskColorMode,
+    flat_color: [f32; 3],
+    accretion_rate: f64,
+    central_mass: f64,
+    constants: PhysicsConstants,
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct Disk {
     pub inner_radius: f32,
     pub outer_radius: f32,
     pub thickness: f32,
+    pub style: DiskStyle,
+    /// Seed for the procedural style, so a look can be reproduced.
+    pub seed: u32,
+    /// Shade the face toward the camera brighter than the far face.
+    pub two_sided: bool,
+    /// Number of orbiting hotspots; 0 disables them.
+    pub hotspot_count: u32,
+    pub hotspot_brightness: f32,
+    /// How long each hotspot lives before respawning, in simulated seconds.
+    pub hotspot_lifetime: f32,
+    pub color_mode: DiskColorMode,
+    /// Linear RGB used by `DiskColorMode::Flat`.
+    pub flat_color: [f32; 3],
+    /// Rest mass accreted per second in kg/s, setting the temperature for
+    /// `DiskColorMode::NovikovThorne`.
+    pub accretion_rate: f64,
+    /// Mass of the hole the disk orbits, in the units of `constants`, for
+    /// the temperature profile.
+    pub central_mass: f64,
+    pub constants: PhysicsConstants,
+    /// Angle in radians between the disk's normal and +Y.
+    pub tilt: f32,
+    /// How fast the tilted normal swings around +Y, in radians per
+    /// simulated second.
+    pub precession_rate: f32,
+    /// Current swing of the normal around +Y, in radians.
+    pub precession_angle: f32,
+    /// Stylistic grade of the disk's emission alone: saturation scales the
+    /// distance from gray and contrast is a power curve about mid-gray.
+    /// 1.0 leaves either unchanged.
+    pub saturation: f32,
+    pub contrast: f32,
+    /// Width, in approximate compute pixels, of the band where the disk's
+    /// emission fades out at its inner and outer radii. 0 keeps hard edges.
+    pub edge_softness: f32,
+    /// How strongly the glowing slab of `thickness` around the disk thins
+    /// away from its midplane: density goes as `(1 - |y| / half_thickness)`
+    /// to this power. 0 is uniform and 1 a linear falloff.
+    pub vertical_sharpness: f32,
+    /// Midplane crossings a ray composites. All but the last are
+    /// translucent, so the disk behind shows through; 1 keeps it opaque.
+    pub max_crossings: u32,
 }
 
 impl Disk {
@@ -105,54 +410,442 @@ impl Disk {
             inner_radius,
             outer_radius,
             thickness,
+            style: DiskStyle::Smooth,
+            seed: 0,
+            two_sided: false,
+            hotspot_count: 0,
+            hotspot_brightness: 1.0,
+            hotspot_lifetime: 5000.0,
+            color_mode: DiskColorMode::Gradient,
+            flat_color: [1.0, 0.6, 0.2],
+            accretion_rate: DEFAULT_ACCRETION_RATE,
+            central_mass: BlackHole::sagittarius_a().mass,
+            constants: PhysicsConstants::SI,
+            tilt: 0.0,
+            precession_rate: 0.0,
+            precession_angle: 0.0,
+            saturation: 1.0,
+            contrast: 1.0,
+            edge_softness: 1.0,
+            vertical_sharpness: 2.0,
+            max_crossings: 1,
+        }
+    }
+
+    /// Rotation from the disk's own frame, where it lies in the y = 0 plane,
+    /// to the world: tilted about +X by `tilt`, then swung about +Y by
+    /// `precession_angle`. The identity for an untilted disk.
+    pub fn orientation(&self) -> Mat3 {
+        Mat3::from_rotation_y(self.precession_angle) * Mat3::from_rotation_x(self.tilt)
+    }
+
+    /// A world-space point or direction in the disk's frame.
+    pub fn to_local(&self, v: Vec3) -> Vec3 {
+        self.orientation().transpose() * v
+    }
+
+    /// Base emission color at radius `r` meters in color `mode`, before the
+    /// style pattern and face shading modulate its brightness. The shader
+    /// samples a table built from this in the disk's own `color_mode` (see
+    /// `color_table`), so legends always match; derive any change to the
+    /// shader's disk color from here rather than editing the shader alone.
+    pub fn color_at(&self, r: f32, mode: DiskColorMode) -> [f32; 3] {
+        match mode {
+            DiskColorMode::Gradient => [1.0, r / self.outer_radius, 0.2],
+            DiskColorMode::Flat => self.flat_color,
+            DiskColorMode::NovikovThorne => {
+                let black_hole =
+                    BlackHole::with_constants(Vec3::ZERO, self.central_mass, self.constants);
+                let peak = self.novikov_thorne_peak_temperature(&black_hole);
+                if peak <= 0.0 {
+                    return [0.0; 3];
+                }
+                let temperature = self.novikov_thorne_temperature(r as f64, &black_hole);
+                // Emitted flux goes as T^4
+                let brightness = (temperature / peak).powi(4) as f32;
+                blackbody_rgb(temperature as f32).map(|c| c * brightness)
+            }
         }
     }
 
+    /// Effective temperature in kelvin of a Novikov-Thorne thin disk around
+    /// a non-spinning hole at radius `r` meters, accreting
+    /// `accretion_rate`. Zero at and inside the ISCO, where the disk's
+    /// inner edge exerts no torque, peaking at about 4.8 r_s and falling as
+    /// `r^(-3/4)` far out. Uses Page and Thorne's closed form of the
+    /// relativistic flux for spin zero.
+    pub fn novikov_thorne_temperature(&self, r: f64, black_hole: &BlackHole) -> f64 {
+        let m = black_hole.r_s / 2.0;
+        let x = (r / m).sqrt();
+        let x0 = (black_hole.isco_radius() / m).sqrt();
+        if x <= x0 {
+            return 0.0;
+        }
+
+        let root3 = 3.0f64.sqrt();
+        let torque = x - x0 - 0.5 * root3 * ((x - root3) / (x0 - root3)).ln()
+            + 0.5 * root3 * ((x + root3) / (x0 + root3)).ln();
+        let relativistic = torque / (x * (1.0 - 3.0 / (x * x)));
+        // The flux is in SI, so the accretion rate and Stefan-Boltzmann
+        // constant need the mass in kg
+        let mass_kg = black_hole.constants.mass_to_si(black_hole.mass);
+        let newtonian =
+            3.0 * G * mass_kg * self.accretion_rate / (8.0 * std::f64::consts::PI * r.powi(3));
+        (newtonian * relativistic / STEFAN_BOLTZMANN)
+            .max(0.0)
+            .powf(0.25)
+    }
+
+    /// Highest `novikov_thorne_temperature`, found by sampling out to four
+    /// times the ISCO.
+    fn novikov_thorne_peak_temperature(&self, black_hole: &BlackHole) -> f64 {
+        let isco = black_hole.isco_radius();
+        (1..=256)
+            .map(|i| {
+                self.novikov_thorne_temperature(isco * (1.0 + 3.0 * i as f64 / 256.0), black_hole)
+            })
+            .fold(0.0, f64::max)
+    }
+
+    /// `color_at` sampled at `size` evenly spaced radii from the inner to
+    /// the outer edge, as RGBA rows (alpha 1) for the shader's lookup table.
+    /// Only the base color is tabulated: it is what costs the most per
+    /// pixel in `DiskColorMode::NovikovThorne` (the temperature profile and
+    /// blackbody fit), while Doppler beaming, which only hotspots get,
+    /// depends on the ray's direction and is computed in the shader.
+    pub fn color_table(&self, size: usize) -> Vec<f32> {
+        let step = (self.outer_radius - self.inner_radius) / (size.max(2) - 1) as f32;
+        (0..size)
+            .flat_map(|i| {
+                let [r, g, b] = self.color_at(self.inner_radius + step * i as f32, self.color_mode);
+                [r, g, b, 1.0]
+            })
+            .collect()
+    }
+
+    /// Everything `color_at` reads, so a table from `color_table` can be
+    /// rebuilt exactly when it goes stale.
+    pub fn color_key(&self) -> DiskColorKey {
+        DiskColorKey {
+            inner_radius: self.inner_radius,
+            outer_radius: self.outer_radius,
+            color_mode: self.color_mode,
+            flat_color: self.flat_color,
+            accretion_rate: self.accretion_rate,
+            central_mass: self.central_mass,
+            constants: self.constants,
+        }
+    }
+
+    /// A disk from the hole's ISCO out to 5.2 r_s.
+    pub fn from_black_hole(black_hole: &BlackHole) -> Self {
+        let r_s = black_hole.r_s as f32;
+        let mut disk = Self::new(black_hole.isco_radius() as f32, r_s * 5.2, r_s * 0.079);
+        disk.central_mass = black_hole.mass;
+        disk.constants = black_hole.constants;
+        disk
+    }
+
     pub fn default_accretion_disk() -> Self {
-        let r_s = 1.269e10;
-        Self::new(r_s * 2.2, r_s * 5.2, 1.0e9)
+        Self::from_black_hole(&BlackHole::sagittarius_a())
     }
 }
 
+/// How the planet's orbit is computed.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlanetModel {
+    /// Closed Newtonian ellipse.
+    Kepler,
+    /// Schwarzschild timelike geodesic; the periapsis precesses.
+    GeodesicGR,
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct Planet {
     pub position: Vec3,
     pub velocity: Vec3,
     pub radius: f32,
+    /// The planet's own mass in the units of `constants` (kg for SI), used
+    /// only for its weak lensing.
+    pub mass: f64,
     pub semi_major_axis: f32,
     pub eccentricity: f32,
     pub mean_motion: f32,
+    pub model: PlanetModel,
+    /// Geodesic state for `PlanetModel::GeodesicGR`, restarted at periapsis
+    /// whenever the clock goes backwards.
+    geodesic: Option<TimelikeOrbit>,
+    /// `GM/c^2` of the hole in meters, the geodesic's length unit.
+    black_hole_m: f64,
+    /// Added to the mean anomaly, in radians, so `rescale` can change the
+    /// mean motion without the planet jumping along its orbit.
+    phase_offset: f32,
+    /// Added to the geodesic's clock, in units of `M/c`, for the same reason.
+    geodesic_clock_offset: f64,
+    constants: PhysicsConstants,
 }
 
 impl Planet {
+    /// Orbit with semi-major axis and planet radius in units of `r_s / 2`
+    /// (`GM/c^2`). Rejects eccentricities outside `[0, 1)` and orbits whose
+    /// periapsis reaches the photon sphere. Periapses between the photon
+    /// sphere and the ISCO are allowed for the Kepler model, though
+    /// `set_model` refuses them for the geodesic one. Positions are in
+    /// `black_hole`'s frame and masses in its units.
     pub fn new_elliptical_orbit(
         semi_major_axis_scu: f32,
         eccentricity: f32,
         radius: f32,
-        black_hole_mass: f64,
-    ) -> Self {
-        let r_s = 2.0 * G * black_hole_mass / (C * C);
-        let unit_scale = r_s as f32 / 2.0;
+        black_hole: &BlackHole,
+    ) -> Result<Self, String> {
+        if !(semi_major_axis_scu.is_finite() && semi_major_axis_scu > 0.0) {
+            return Err(format!(
+                "Semi-major axis must be positive, got {}",
+                semi_major_axis_scu
+            ));
+        }
+        if !(0.0..1.0).contains(&eccentricity) {
+            return Err(format!(
+                "Eccentricity must be in [0, 1) for an elliptical orbit, got {}",
+                eccentricity
+            ));
+        }
+
+        let unit_scale = black_hole.r_s as f32 / 2.0;
 
         let semi_major_axis = semi_major_axis_scu * unit_scale;
 
-        let mean_motion =
-            ((G * black_hole_mass / (semi_major_axis as f64).powi(3)).sqrt()) as f32 * 1000.0;
+        // The planet is fastest at periapsis; at or inside the photon sphere
+        // even a circular orbit there would have to move at c or faster.
+        let periapsis = semi_major_axis as f64 * (1.0 - eccentricity as f64);
+        let periapsis_speed = black_hole.local_orbit_speed_fraction(periapsis);
+        if periapsis_speed >= 1.0 {
+            return Err(format!(
+                "Orbit with semi-major axis {} and eccentricity {} reaches periapsis at {:.3} r_s, \
+                 inside the photon sphere; it would need a super-luminal speed",
+                semi_major_axis_scu,
+                eccentricity,
+                periapsis / black_hole.r_s
+            ));
+        }
+
+        // Mean motion in rad per unit of coordinate time. For e = 0 this is exactly
+        // the angular speed of `circular_orbit_speed`; any speed-up for
+        // display is applied to the clock, not here.
+        let a = semi_major_axis as f64;
+        let mean_motion = (black_hole.circular_orbit_speed(a) / a) as f32;
 
         let position = Vec3::new(semi_major_axis * (1.0 - eccentricity), 0.0, 0.0);
 
-        Planet {
+        Ok(Planet {
             position,
             velocity: Vec3::ZERO,
             radius: radius * unit_scale,
+            mass: black_hole.constants.mass_from_si(EARTH_MASS),
             semi_major_axis,
             eccentricity,
             mean_motion,
+            model: PlanetModel::Kepler,
+            geodesic: None,
+            black_hole_m: black_hole.r_s / 2.0,
+            phase_offset: 0.0,
+            geodesic_clock_offset: 0.0,
+            constants: black_hole.constants,
+        })
+    }
+
+    /// This planet, keeping its size, mass and orbit model, moved onto an
+    /// orbit with semi-major axis `semi_major_axis_rs` Schwarzschild radii
+    /// of `black_hole`. Unlike `new_elliptical_orbit`, rejects any periapsis
+    /// inside the ISCO, where no circular orbit is stable, whichever model
+    /// is in use.
+    pub fn with_orbit(
+        &self,
+        semi_major_axis_rs: f32,
+        eccentricity: f32,
+        black_hole: &BlackHole,
+    ) -> Result<Self, String> {
+        let unit_scale = black_hole.r_s as f32 / 2.0;
+        let mut planet = Planet::new_elliptical_orbit(
+            2.0 * semi_major_axis_rs,
+            eccentricity,
+            self.radius / unit_scale,
+            black_hole,
+        )?;
+        let periapsis = planet.semi_major_axis as f64 * (1.0 - eccentricity as f64);
+        if periapsis < black_hole.isco_radius() {
+            return Err(format!(
+                "Orbit with semi-major axis {} r_s and eccentricity {} reaches periapsis at \
+                 {:.3} r_s, inside the ISCO at {} r_s",
+                semi_major_axis_rs,
+                eccentricity,
+                periapsis / black_hole.r_s,
+                black_hole.isco_radius() / black_hole.r_s
+            ));
+        }
+        planet.mass = self.mass;
+        planet.set_model(self.model)?;
+        Ok(planet)
+    }
+
+    /// Switches the orbit model. The geodesic model needs a stable bound
+    /// orbit, periapsis-to-apoapsis, with semi-latus rectum above `6 + 2e`
+    /// in units of `GM/c^2`; tighter orbits are rejected.
+    pub fn set_model(&mut self, model: PlanetModel) -> Result<(), String> {
+        if model == PlanetModel::GeodesicGR {
+            let e = self.eccentricity as f64;
+            let p = self.semi_major_axis as f64 / self.black_hole_m * (1.0 - e * e);
+            if TimelikeOrbit::from_periapsis(p, e).is_none() {
+                return Err(format!(
+                    "Orbit with semi-latus rectum {:.2} GM/c^2 and eccentricity {} is not a \
+                     stable geodesic (needs more than {:.2}); widen the orbit first",
+                    p,
+                    e,
+                    6.0 + 2.0 * e
+                ));
+            }
         }
+        self.model = model;
+        self.geodesic = None;
+        Ok(())
+    }
+
+    pub fn schwarzschild_radius(&self) -> f64 {
+        self.constants.schwarzschild_radius(self.mass)
+    }
+
+    /// Scales the orbit and the planet by `factor` to follow the hole's
+    /// Schwarzschild radius as it changes by the same factor. The orbit keeps
+    /// its shape in units of `r_s`, so its period scales by `factor` too; the
+    /// planet stays at the point of the orbit it had reached at `time`.
+    pub fn rescale(&mut self, factor: f64, time: f32) {
+        let mean_anomaly = self.mean_motion * time + self.phase_offset;
+        let geodesic_time = self.geodesic_time(time);
+
+        self.semi_major_axis *= factor as f32;
+        self.radius *= factor as f32;
+        self.mean_motion /= factor as f32;
+        self.black_hole_m *= factor;
+        self.position *= factor as f32;
+
+        self.phase_offset = mean_anomaly - self.mean_motion * time;
+        self.geodesic_clock_offset =
+            geodesic_time - time as f64 * self.constants.c / self.black_hole_m;
+    }
+
+    /// Switches the units of mass and time to `constants`, re-expressing the
+    /// planet's mass and its orbital rate. Lengths are unchanged, and the
+    /// planet stays at the point of the orbit it had reached at `time`.
+    pub fn set_constants(&mut self, constants: PhysicsConstants, time: f32) {
+        let mean_anomaly = self.mean_motion * time + self.phase_offset;
+        let geodesic_time = self.geodesic_time(time);
+
+        // With GM/c^2 held, dphi/dt = c sqrt((GM/c^2) / a^3) goes as c
+        self.mass = self.constants.convert_mass(self.mass, &constants);
+        self.mean_motion *= (constants.c / self.constants.c) as f32;
+        self.constants = constants;
+
+        self.phase_offset = mean_anomaly - self.mean_motion * time;
+        self.geodesic_clock_offset =
+            geodesic_time - time as f64 * self.constants.c / self.black_hole_m;
+    }
+
+    /// Geodesic coordinate time at `time` on the planet's clock, in units of
+    /// `M/c`.
+    fn geodesic_time(&self, time: f32) -> f64 {
+        time as f64 * self.constants.c / self.black_hole_m + self.geodesic_clock_offset
     }
 
     pub fn update(&mut self, time: f32) {
-        let mean_anomaly = self.mean_motion * time;
+        match self.model {
+            PlanetModel::Kepler => self.update_kepler(time),
+            PlanetModel::GeodesicGR => self.update_geodesic(time),
+        }
+    }
+
+    fn update_geodesic(&mut self, time: f32) {
+        let m = self.black_hole_m;
+        let t = self.geodesic_time(time);
+        let e = self.eccentricity as f64;
+        let p = self.semi_major_axis as f64 / m * (1.0 - e * e);
+
+        let mut orbit = match self.geodesic {
+            Some(orbit) if orbit.t <= t => orbit,
+            _ => match TimelikeOrbit::from_periapsis(p, e) {
+                Some(orbit) => orbit,
+                None => return self.update_kepler(time),
+            },
+        };
+        orbit.advance_to(t);
+        self.geodesic = Some(orbit);
+
+        let r = m / orbit.u;
+        let (sin_phi, cos_phi) = orbit.phi.sin_cos();
+        let x_orbit = (r * cos_phi) as f32;
+        let z_orbit = (r * sin_phi) as f32;
+
+        // dr/dt and r dphi/dt in meters per unit of time
+        let phi_rate = orbit.angular_rate() * self.constants.c / m;
+        let dr = -m * orbit.du / (orbit.u * orbit.u) * phi_rate;
+        let r_dphi = r * phi_rate;
+        let vx_orbit = (dr * cos_phi - r_dphi * sin_phi) as f32;
+        let vz_orbit = (dr * sin_phi + r_dphi * cos_phi) as f32;
+
+        self.set_orbit_plane(x_orbit, z_orbit, vx_orbit, vz_orbit);
+    }
+
+    /// Tilts in-plane position and velocity by the orbit's inclination.
+    fn set_orbit_plane(&mut self, x_orbit: f32, z_orbit: f32, vx_orbit: f32, vz_orbit: f32) {
+        self.position = Self::from_orbit_plane(x_orbit, z_orbit);
+        self.velocity = Self::from_orbit_plane(vx_orbit, vz_orbit);
+    }
+
+    /// A vector in the orbital plane, periapsis of the unprecessed orbit
+    /// along x, in the hole's frame.
+    fn from_orbit_plane(x_orbit: f32, z_orbit: f32) -> Vec3 {
+        let (sin_i, cos_i) = ORBIT_INCLINATION.to_radians().sin_cos();
+        Vec3::new(x_orbit, z_orbit * sin_i, z_orbit * cos_i)
+    }
+
+    /// Unit normal of the orbital plane, which passes through the hole.
+    pub fn orbit_normal(&self) -> Vec3 {
+        let (sin_i, cos_i) = ORBIT_INCLINATION.to_radians().sin_cos();
+        Vec3::new(0.0, cos_i, -sin_i)
+    }
+
+    /// Angle in the orbital plane from the x axis to the current periapsis,
+    /// in radians. Always 0 for the Kepler model; the geodesic one turns it
+    /// forward at each periapsis passage by that orbit's precession.
+    pub fn periapsis_angle(&self) -> f32 {
+        match (self.model, &self.geodesic) {
+            (PlanetModel::GeodesicGR, Some(orbit)) => {
+                orbit.periapsis_phi.rem_euclid(std::f64::consts::TAU) as f32
+            }
+            _ => 0.0,
+        }
+    }
+
+    /// `count` points evenly spaced in eccentric anomaly around the orbital
+    /// ellipse, turned to the current periapsis, in the hole's frame. The
+    /// path closes from the last point back to the first.
+    pub fn orbit_points(&self, count: usize) -> Vec<Vec3> {
+        let a = self.semi_major_axis;
+        let e = self.eccentricity;
+        let b = a * (1.0 - e * e).sqrt();
+        let (sin_w, cos_w) = self.periapsis_angle().sin_cos();
+        (0..count)
+            .map(|i| {
+                let eccentric_anomaly = i as f32 / count as f32 * std::f32::consts::TAU;
+                let x = a * (eccentric_anomaly.cos() - e);
+                let z = b * eccentric_anomaly.sin();
+                Self::from_orbit_plane(x * cos_w - z * sin_w, x * sin_w + z * cos_w)
+            })
+            .collect()
+    }
+
+    fn update_kepler(&mut self, time: f32) {
+        let mean_anomaly = self.mean_motion * time + self.phase_offset;
 
         let mut eccentric_anomaly = mean_anomaly;
         for _ in 0..4 {
@@ -168,12 +861,6 @@ impl Planet {
         let z_orbit =
             self.semi_major_axis * (1.0 - self.eccentricity * self.eccentricity).sqrt() * sin_e;
 
-        let inclination = 30.0f32.to_radians();
-
-        self.position.x = x_orbit;
-        self.position.y = z_orbit * inclination.sin();
-        self.position.z = z_orbit * inclination.cos();
-
         let vx_orbit =
             -self.semi_major_axis * self.mean_motion * sin_e / (1.0 - self.eccentricity * cos_e);
         let vz_orbit = self.semi_major_axis
@@ -182,8 +869,342 @@ impl Planet {
             * cos_e
             / (1.0 - self.eccentricity * cos_e);
 
-        self.velocity.x = vx_orbit;
-        self.velocity.y = vz_orbit * inclination.sin();
-        self.velocity.z = vz_orbit * inclination.cos();
+        self.set_orbit_plane(x_orbit, z_orbit, vx_orbit, vz_orbit);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::integrator::init_ray;
+
+    #[test]
+    fn to_cartesian_inverts_init_ray() {
+        let pos = Vec3::new(3e10, -2e10, 5e10);
+        let ray = init_ray(pos, Vec3::X, 1e10);
+        assert!(ray.to_cartesian().distance(pos) < pos.length() * 1e-6);
+        assert!(ray.position().as_vec3().distance(ray.to_cartesian()) < 1.0);
+    }
+
+    #[test]
+    fn blackbody_matches_known_temperatures() {
+        let close = |a: [f32; 3], b: [f32; 3]| a.iter().zip(b).all(|(a, b)| (a - b).abs() < 0.01);
+        // Helland's table: 1000 K is (255, 68, 0), 6600 K white and
+        // 10000 K (201, 218, 255)
+        assert!(close(blackbody_rgb(1000.0), [1.0, 68.0 / 255.0, 0.0]));
+        assert!(close(blackbody_rgb(6600.0), [1.0; 3]));
+        assert!(close(
+            blackbody_rgb(10000.0),
+            [201.0 / 255.0, 218.0 / 255.0, 1.0]
+        ));
+    }
+
+    #[test]
+    fn color_at_the_disk_edges_in_each_mode() {
+        let black_hole = BlackHole::sagittarius_a();
+        let disk = Disk {
+            flat_color: [0.2, 0.4, 1.0],
+            ..Disk::from_black_hole(&black_hole)
+        };
+        let (inner, outer) = (disk.inner_radius, disk.outer_radius);
+
+        let gradient = |r| disk.color_at(r, DiskColorMode::Gradient);
+        assert_eq!(gradient(inner), [1.0, inner / outer, 0.2]);
+        assert_eq!(gradient(outer), [1.0, 1.0, 0.2]);
+        assert_eq!(disk.color_at(inner, DiskColorMode::Flat), disk.flat_color);
+        assert_eq!(disk.color_at(outer, DiskColorMode::Flat), disk.flat_color);
+
+        // The default accretion rate peaks near 4800 K around Sagittarius A*
+        let peak = disk.novikov_thorne_peak_temperature(&black_hole);
+        assert!((4600.0..5000.0).contains(&peak), "peak {} K", peak);
+        // No torque at the ISCO, so no light; 5.2 r_s is just past the peak
+        let thermal = |r| disk.color_at(r, DiskColorMode::NovikovThorne);
+        assert!(thermal(inner).iter().all(|&c| c < 1e-6));
+        let temperature = disk.novikov_thorne_temperature(outer as f64, &black_hole);
+        assert!((4600.0..peak).contains(&temperature));
+        let brightness = (temperature / peak).powi(4) as f32;
+        let expected = blackbody_rgb(temperature as f32).map(|c| c * brightness);
+        assert!(
+            thermal(outer)
+                .iter()
+                .zip(expected)
+                .all(|(a, b)| (a - b).abs() < 1e-5)
+        );
+        let [red, green, blue] = thermal(outer);
+        assert!(red > green && green > blue && blue > 0.0);
+    }
+
+    #[test]
+    fn color_key_changes_with_every_color_input() {
+        let disk = Disk::from_black_hole(&BlackHole::sagittarius_a());
+        let key = disk.color_key();
+        let changed = [
+            Disk {
+                color_mode: DiskColorMode::NovikovThorne,
+                ..disk
+            },
+            Disk {
+                flat_color: [0.2, 0.4, 1.0],
+                ..disk
+            },
+            Disk {
+                accretion_rate: 2.0 * disk.accretion_rate,
+                ..disk
+            },
+            Disk {
+                central_mass: 2.0 * disk.central_mass,
+                ..disk
+            },
+            Disk {
+                constants: PhysicsConstants::GEOMETRIZED,
+                ..disk
+            },
+            Disk {
+                outer_radius: 2.0 * disk.outer_radius,
+                ..disk
+            },
+        ];
+        for other in changed {
+            assert_ne!(other.color_key(), key, "{:?}", other);
+        }
+        // Shading that isn't tabulated leaves the table as is
+        let restyled = Disk {
+            style: DiskStyle::Banded,
+            hotspot_count: 4,
+            ..disk
+        };
+        assert_eq!(restyled.color_key(), key);
+    }
+
+    #[test]
+    fn time_dilation_is_the_root_of_schwarzschild_f() {
+        let black_hole = BlackHole::sagittarius_a();
+        let r_s = black_hole.r_s;
+        for r in [1.5 * r_s, 3.0 * r_s, 100.0 * r_s] {
+            let expected = black_hole.schwarzschild_f(r).sqrt();
+            assert!((black_hole.time_dilation(r) - expected).abs() < 1e-12);
+        }
+        // Half the far-away rate at 4/3 r_s, and stopped at the horizon
+        assert!((black_hole.time_dilation(4.0 / 3.0 * r_s) - 0.5).abs() < 1e-12);
+        assert_eq!(black_hole.time_dilation(r_s), 0.0);
+        assert_eq!(black_hole.time_dilation(0.5 * r_s), 0.0);
+    }
+
+    #[test]
+    fn circular_orbit_period_is_circumference_over_speed() {
+        let black_hole = BlackHole::sagittarius_a();
+        let mut planet = Planet::new_elliptical_orbit(12.0, 0.0, 0.4, &black_hole).unwrap();
+        let a = planet.semi_major_axis as f64;
+        let period = std::f64::consts::TAU * a / black_hole.circular_orbit_speed(a);
+        let from_mean_motion = std::f64::consts::TAU / planet.mean_motion as f64;
+        assert!((from_mean_motion / period - 1.0).abs() < 1e-5);
+
+        planet.update(0.0);
+        let start = planet.position;
+        planet.update(period as f32);
+        assert!(planet.position.distance(start) < 1e-4 * planet.semi_major_axis);
+        planet.update(0.5 * period as f32);
+        assert!((planet.position + start).length() < 1e-4 * planet.semi_major_axis);
+    }
+
+    #[test]
+    fn orbits_reaching_the_photon_sphere_are_rejected() {
+        let black_hole = BlackHole::sagittarius_a();
+        let r_s = black_hole.r_s;
+        assert!(
+            (black_hole.local_escape_velocity(r_s) / black_hole.constants.c - 1.0).abs() < 1e-12
+        );
+        assert!((black_hole.local_orbit_speed_fraction(3.0 * r_s) - 0.5).abs() < 1e-12);
+        assert_eq!(
+            black_hole.local_orbit_speed_fraction(1.5 * r_s),
+            f64::INFINITY
+        );
+
+        // Semi-major axes are in units of r_s / 2: periapsis at 1.8 r_s is
+        // allowed, at 1.2 r_s it would need a speed above c
+        assert!(Planet::new_elliptical_orbit(12.0, 0.7, 0.4, &black_hole).is_ok());
+        let error = Planet::new_elliptical_orbit(12.0, 0.8, 0.4, &black_hole).unwrap_err();
+        assert!(error.contains("photon sphere"), "{}", error);
+        assert!(Planet::new_elliptical_orbit(2.0, 0.0, 0.4, &black_hole).is_err());
+        assert!(Planet::new_elliptical_orbit(-12.0, 0.0, 0.4, &black_hole).is_err());
+        assert!(Planet::new_elliptical_orbit(12.0, 1.0, 0.4, &black_hole).is_err());
+    }
+
+    #[test]
+    fn geodesic_orbit_advances_its_periapsis_and_kepler_does_not() {
+        let black_hole = BlackHole::sagittarius_a();
+        // Semi-latus rectum 100 GM/c^2; a circular orbit there would
+        // precess by 2 pi (1 / sqrt(1 - 6/p) - 1) per turn
+        let (p, e) = (100.0, 0.3);
+        let mut planet =
+            Planet::new_elliptical_orbit(p / (1.0 - e * e), e, 0.4, &black_hole).unwrap();
+        let expected = std::f64::consts::TAU * (1.0 / (1.0 - 6.0 / p as f64).sqrt() - 1.0);
+        // One radial period is about 7450 M/c, a little over Kepler's 7240
+        let m_over_c = black_hole.r_s / 2.0 / black_hole.constants.c;
+        let time = (7600.0 * m_over_c) as f32;
+
+        // Kepler's ellipse closes: one period brings the planet back to
+        // periapsis on the x axis
+        let start = Vec3::new(planet.semi_major_axis * (1.0 - e), 0.0, 0.0);
+        planet.update(std::f32::consts::TAU / planet.mean_motion);
+        assert!(planet.position.distance(start) < 1e-3 * planet.semi_major_axis);
+        assert_eq!(planet.periapsis_angle(), 0.0);
+
+        planet.set_model(PlanetModel::GeodesicGR).unwrap();
+        planet.update(0.0);
+        assert_eq!(planet.periapsis_angle(), 0.0);
+        planet.update(time);
+        let advance = planet.periapsis_angle() as f64;
+        assert!(
+            (advance / expected - 1.0).abs() < 0.01,
+            "advance {} rad, expected {}",
+            advance,
+            expected
+        );
+        // About 5% more than the weak-field 6 pi / p
+        assert!((advance / (6.0 * std::f64::consts::PI / p as f64) - 1.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn effective_potential_peaks_at_the_photon_sphere() {
+        let black_hole = BlackHole::sagittarius_a();
+        let r_s = black_hole.r_s;
+        // With L at the critical impact parameter the peak is exactly 1
+        let b_c = black_hole.critical_impact_parameter();
+        let curve = black_hole.effective_potential_curve(r_s, 6.0 * r_s, 501, b_c);
+        assert_eq!(curve.len(), 501);
+        assert_eq!(curve[0], (r_s, 0.0));
+        assert!((curve[500].0 / r_s - 6.0).abs() < 1e-12);
+
+        let (r_peak, v_peak) = curve
+            .iter()
+            .copied()
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .unwrap();
+        assert!(
+            (r_peak / r_s - 1.5).abs() < 1e-9,
+            "peak at {} r_s",
+            r_peak / r_s
+        );
+        assert!((v_peak - 1.0).abs() < 1e-12);
+        assert!(black_hole.effective_potential(1.4 * r_s, b_c) < v_peak);
+        assert!(black_hole.effective_potential(1.6 * r_s, b_c) < v_peak);
+    }
+
+    #[test]
+    fn default_disk_starts_at_the_isco_for_any_mass() {
+        let black_hole = BlackHole::sagittarius_a();
+        let disk = Disk::from_black_hole(&black_hole);
+        assert_eq!(disk.inner_radius, black_hole.isco_radius() as f32);
+        assert!((black_hole.isco_radius() / black_hole.r_s - 3.0).abs() < 1e-12);
+
+        let heavier = BlackHole::with_constants(
+            black_hole.position,
+            10.0 * black_hole.mass,
+            black_hole.constants,
+        );
+        let heavier_disk = Disk::from_black_hole(&heavier);
+        assert_eq!(heavier_disk.inner_radius, heavier.isco_radius() as f32);
+        assert!((heavier_disk.inner_radius / disk.inner_radius - 10.0).abs() < 1e-5);
+        assert!((heavier_disk.outer_radius / disk.outer_radius - 10.0).abs() < 1e-5);
+
+        // 3 r_s is 6 GM/c^2, where circular geodesics stop being stable
+        assert!(crate::integrator::TimelikeOrbit::from_periapsis(6.0, 0.0).is_none());
+        assert!(crate::integrator::TimelikeOrbit::from_periapsis(6.01, 0.0).is_some());
+    }
+
+    #[test]
+    fn novikov_thorne_temperature_vanishes_at_the_isco_and_peaks_outside() {
+        let black_hole = BlackHole::sagittarius_a();
+        let disk = Disk::from_black_hole(&black_hole);
+        let r_s = black_hole.r_s;
+        let temperature = |r_rs: f64| disk.novikov_thorne_temperature(r_rs * r_s, &black_hole);
+
+        assert_eq!(temperature(3.0), 0.0);
+        assert_eq!(temperature(2.0), 0.0);
+        assert!(temperature(3.001) > 0.0);
+
+        let (r_peak, peak) = (1..=3000)
+            .map(|i| 3.0 + i as f64 * 0.001)
+            .map(|r| (r, temperature(r)))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .unwrap();
+        assert!((4.7..4.85).contains(&r_peak), "peaks at {} r_s", r_peak);
+        assert!(temperature(3.05) < 0.5 * peak);
+        // Far out the torque term saturates, leaving T ~ r^(-3/4)
+        let falloff = temperature(100.0) / temperature(200.0);
+        assert!(
+            (falloff / 2f64.powf(0.75) - 1.0).abs() < 0.03,
+            "{}",
+            falloff
+        );
+    }
+
+    #[test]
+    fn metric_samples_cross_zero_at_the_horizon() {
+        let black_hole = BlackHole::sagittarius_a();
+        let r_s = black_hole.r_s;
+        let samples = black_hole.metric_samples(0.5 * r_s, 2.0 * r_s, 4);
+        assert_eq!(samples.len(), 4);
+        let radii: Vec<f64> = samples.iter().map(|&(r, _)| r / r_s).collect();
+        for (r, expected) in radii.iter().zip([0.5, 1.0, 1.5, 2.0]) {
+            assert!((r - expected).abs() < 1e-12);
+        }
+        // Negative inside the horizon, zero on it and 0.5 at 2 r_s
+        assert!((samples[0].1 + 1.0).abs() < 1e-12);
+        assert!(samples[1].1.abs() < 1e-12);
+        assert!((samples[3].1 - 0.5).abs() < 1e-12);
+        assert_eq!(
+            black_hole.metric_samples(r_s, 2.0 * r_s, 1),
+            vec![(r_s, 0.0)]
+        );
+        assert!(black_hole.metric_samples(r_s, 2.0 * r_s, 0).is_empty());
+    }
+
+    #[test]
+    fn rescaling_keeps_the_planet_at_the_same_point_of_its_orbit() {
+        let black_hole = BlackHole::sagittarius_a();
+        for model in [PlanetModel::Kepler, PlanetModel::GeodesicGR] {
+            let mut planet = Planet::new_elliptical_orbit(30.0, 0.5, 0.4, &black_hole).unwrap();
+            planet.set_model(model).unwrap();
+            let period = std::f32::consts::TAU / planet.mean_motion;
+            let time = 0.3 * period;
+            planet.update(time);
+            let before = planet.position;
+
+            // The hole doubles in mass: same eccentric anomaly on an orbit
+            // twice the size, now taking twice as long to go round
+            planet.rescale(2.0, time);
+            planet.update(time);
+            assert!(
+                planet.position.distance(2.0 * before) < 1e-4 * planet.semi_major_axis,
+                "{:?}",
+                model
+            );
+            assert!((planet.mean_motion * period / std::f32::consts::TAU - 0.5).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn set_orbit_rejects_periapses_inside_the_isco() {
+        let black_hole = BlackHole::sagittarius_a();
+        let mut planet = Planet::new_elliptical_orbit(30.0, 0.2, 0.4, &black_hole).unwrap();
+        planet.mass = 2.0 * planet.mass;
+        planet.set_model(PlanetModel::GeodesicGR).unwrap();
+
+        // Periapsis at 4 r_s
+        let moved = planet.with_orbit(8.0, 0.5, &black_hole).unwrap();
+        assert!((moved.semi_major_axis as f64 / black_hole.r_s - 8.0).abs() < 1e-6);
+        assert_eq!(moved.eccentricity, 0.5);
+        assert!((moved.radius / planet.radius - 1.0).abs() < 1e-6);
+        assert_eq!(moved.mass, planet.mass);
+        assert_eq!(moved.model, PlanetModel::GeodesicGR);
+
+        // Periapsis at 2 r_s: outside the photon sphere but not stable
+        let error = planet.with_orbit(4.0, 0.5, &black_hole).unwrap_err();
+        assert!(error.contains("ISCO"), "{}", error);
+        planet.set_model(PlanetModel::Kepler).unwrap();
+        assert!(planet.with_orbit(4.0, 0.5, &black_hole).is_err());
+        assert!(planet.with_orbit(8.0, 1.5, &black_hole).is_err());
     }
 }
@@ -0,0 +1,152 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+/// Row pitch of a texture copy, padded to wgpu's copy alignment.
+pub fn padded_bytes_per_row(width: u32, bytes_per_pixel: u32) -> u32 {
+    let unpadded = width * bytes_per_pixel;
+    let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    (unpadded + align - 1) / align * align
+}
+
+/// Bytes per texel of an uncompressed color texture.
+pub fn bytes_per_pixel(texture: &wgpu::Texture) -> u32 {
+    texture.format().block_copy_size(None).unwrap_or(4)
+}
+
+/// Records a copy of a color texture into a fresh mappable buffer.
+pub fn copy_texture_to_buffer(
+    device: &wgpu::Device,
+    encoder: &mut wgpu::CommandEncoder,
+    texture: &wgpu::Texture,
+    width: u32,
+    height: u32,
+) -> wgpu::Buffer {
+    let padded_row = padded_bytes_per_row(width, bytes_per_pixel(texture));
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Readback Buffer"),
+        size: (padded_row * height) as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    encoder.copy_texture_to_buffer(
+        wgpu::ImageCopyTexture {
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_row),
+                rows_per_image: Some(height),
+            },
+        },
+        wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+    );
+
+    buffer
+}
+
+/// Records a copy of a whole buffer into a fresh mappable buffer. Read it
+/// back as a single row: `PendingReadback::new(buffer, size / 4, 1, 4)`.
+pub fn copy_buffer_to_buffer(
+    device: &wgpu::Device,
+    encoder: &mut wgpu::CommandEncoder,
+    source: &wgpu::Buffer,
+) -> wgpu::Buffer {
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Readback Buffer"),
+        size: source.size(),
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    encoder.copy_buffer_to_buffer(source, 0, &buffer, 0, source.size());
+    buffer
+}
+
+#[derive(Default)]
+struct MapState {
+    result: Option<Result<(), wgpu::BufferAsyncError>>,
+    waker: Option<Waker>,
+}
+
+/// A readback buffer waiting on `map_async`. Resolves to tightly packed rows
+/// with the copy padding stripped.
+///
+/// On the web the map completes on a later tick of the event loop, so this
+/// must be awaited; natively `Device::poll(Maintain::Wait)` completes it
+/// immediately.
+pub struct PendingReadback {
+    buffer: wgpu::Buffer,
+    width: u32,
+    height: u32,
+    bytes_per_pixel: u32,
+    state: Arc<Mutex<MapState>>,
+}
+
+impl PendingReadback {
+    /// Starts mapping `buffer`. The copy into it must already be submitted.
+    pub fn new(buffer: wgpu::Buffer, width: u32, height: u32, bytes_per_pixel: u32) -> Self {
+        let state = Arc::new(Mutex::new(MapState::default()));
+        let callback_state = state.clone();
+        buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                let mut state = callback_state.lock().unwrap();
+                state.result = Some(result);
+                if let Some(waker) = state.waker.take() {
+                    waker.wake();
+                }
+            });
+
+        PendingReadback {
+            buffer,
+            width,
+            height,
+            bytes_per_pixel,
+            state,
+        }
+    }
+
+    fn unpad(&self) -> Vec<u8> {
+        let padded_row = padded_bytes_per_row(self.width, self.bytes_per_pixel) as usize;
+        let row_bytes = (self.width * self.bytes_per_pixel) as usize;
+        let mut pixels = Vec::with_capacity(row_bytes * self.height as usize);
+        {
+            let data = self.buffer.slice(..).get_mapped_range();
+            for row in data.chunks(padded_row).take(self.height as usize) {
+                pixels.extend_from_slice(&row[..row_bytes]);
+            }
+        }
+        self.buffer.unmap();
+        pixels
+    }
+}
+
+impl Future for PendingReadback {
+    type Output = Result<Vec<u8>, String>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.state.lock().unwrap();
+        match state.result.take() {
+            Some(Ok(())) => {
+                drop(state);
+                Poll::Ready(Ok(self.unpad()))
+            }
+            Some(Err(e)) => Poll::Ready(Err(format!("Failed to map readback buffer: {:?}", e))),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
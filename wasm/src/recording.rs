@@ -0,0 +1,105 @@
+use wasm_bindgen::prelude::*;
+
+/// Frames from `BlackHoleRenderer::record_session`, evenly spaced in time
+/// for a video encoder. Frames are handed out one at a time by `next_frame`,
+/// which gives up the recording's copy, so the whole session never has to
+/// cross into JS at once.
+#[wasm_bindgen]
+pub struct RecordedFrames {
+    width: u32,
+    height: u32,
+    fps: f64,
+    /// RGBA8 rows from the top left, oldest first. Entries are taken as
+    /// `next_frame` reaches them.
+    frames: Vec<Option<Vec<u8>>>,
+    next: usize,
+}
+
+/// One recorded frame and when it appears in the video.
+#[wasm_bindgen]
+pub struct RecordedFrame {
+    pixels: Vec<u8>,
+    index: u32,
+    timestamp_us: f64,
+}
+
+impl RecordedFrames {
+    pub fn new(width: u32, height: u32, fps: f64, frames: Vec<Vec<u8>>) -> Self {
+        RecordedFrames {
+            width,
+            height,
+            fps,
+            frames: frames.into_iter().map(Some).collect(),
+            next: 0,
+        }
+    }
+
+    fn timestamp_us(&self, index: usize) -> f64 {
+        (index as f64 * 1e6 / self.fps).round()
+    }
+}
+
+#[wasm_bindgen]
+impl RecordedFrames {
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn fps(&self) -> f64 {
+        self.fps
+    }
+
+    /// Total frames in the recording, including ones already taken.
+    pub fn len(&self) -> u32 {
+        self.frames.len() as u32
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Frames not yet returned by `next_frame`.
+    pub fn remaining(&self) -> u32 {
+        (self.frames.len() - self.next) as u32
+    }
+
+    /// How long each frame is shown, in microseconds, as WebCodecs'
+    /// `VideoFrame` expects for `duration`.
+    pub fn frame_duration_us(&self) -> f64 {
+        (1e6 / self.fps).round()
+    }
+
+    /// The next frame in order, or `None` once all have been taken.
+    pub fn next_frame(&mut self) -> Option<RecordedFrame> {
+        let index = self.next;
+        let pixels = self.frames.get_mut(index)?.take()?;
+        self.next += 1;
+        Some(RecordedFrame {
+            pixels,
+            index: index as u32,
+            timestamp_us: self.timestamp_us(index),
+        })
+    }
+}
+
+#[wasm_bindgen]
+impl RecordedFrame {
+    /// RGBA8 rows from the top left.
+    pub fn pixels(&self) -> js_sys::Uint8Array {
+        js_sys::Uint8Array::from(self.pixels.as_slice())
+    }
+
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Presentation time from the start of the recording, in microseconds,
+    /// as WebCodecs' `VideoFrame` expects for `timestamp`.
+    pub fn timestamp_us(&self) -> f64 {
+        self.timestamp_us
+    }
+}
@@ -0,0 +1,531 @@
+//! A CPU reference for the image `shader.wgsl` writes to its output texture,
+//! for catching drift between the shader and the Rust side (ray generation
+//! and coordinate conventions especially). It is slow and meant for small
+//! images; `BlackHoleRenderer::render_reference` runs it on the live view.
+//!
+//! It covers the default scene: perspective or orthographic camera, the hole,
+//! the disk in any style with its face shading, edge feather, glowing slab
+//! and translucent crossings, and the star map at infinity without rotation.
+//! Jets, the planet, hotspots, pulses, overlays, dispersion and the photon
+//! ring boost are left out. The final disk color is taken at the plane
+//! crossing rather than at the end of the crossing step, base colors come
+//! from `Disk::color_at` rather than its table, and the noise styles use CPU
+//! `sin`, so expect small differences from the GPU near disk edges and in
+//! turbulent disks.
+
+use crate::camera::{Camera, CameraUniform, MAX_FOV, MIN_FOV, Projection};
+use crate::integrator::{DEFAULT_MAX_STEPS, TraceResult, adaptive_steps, trace_like_shader_with};
+use crate::physics::{BlackHole, DEFAULT_ESCAPE_RADIUS, Disk, DiskStyle};
+use crate::share::ViewParams;
+use glam::{Vec2, Vec3, Vec4};
+use std::f32::consts::PI;
+
+/// Largest side accepted by `render_thumbnail`.
+pub const MAX_THUMBNAIL_SIZE: u32 = 256;
+/// Step budget for thumbnails, a quarter of the shader's default.
+const THUMBNAIL_STEPS: u32 = DEFAULT_MAX_STEPS / 4;
+// The shader's constants of the same names
+const DISK_FAR_FACE: f32 = 0.45;
+const DISK_SLAB_DEPTH: f32 = 2.0;
+const DISK_CROSSING_OPACITY: f32 = 0.75;
+
+/// The parts of the shader's `camera` uniform the disk's shading reads.
+struct View {
+    /// Camera position relative to the hole, in meters.
+    position: Vec3,
+    /// Size of a pixel in meters is `footprint_base + footprint_slope *
+    /// distance` from the camera.
+    footprint_base: f32,
+    footprint_slope: f32,
+}
+
+impl View {
+    /// `pixel_footprint` in the shader.
+    fn pixel_footprint(&self, distance: f32) -> f32 {
+        self.footprint_base + self.footprint_slope * distance
+    }
+}
+
+/// A `size` x `size` preview of a saved view (see `ViewParams`), for
+/// galleries of saved states. No GPU is needed. The mass, camera, field of
+/// view and disk radii come from `view`; everything else about the disk
+/// (style, colors, tilt, thickness and the rest) from `disk`, which is
+/// placed around the hole of `view`'s mass in `disk.constants`' units.
+///
+/// It runs the reference tracer with a quarter of the usual step budget,
+/// so on top of `reference_render`'s differences from the shader, rays
+/// that skim the photon sphere run out of steps and render as background:
+/// higher-order rings fade and the shadow's edge is slightly soft. `size`
+/// is capped at `MAX_THUMBNAIL_SIZE`.
+pub fn render_thumbnail(
+    view: &ViewParams,
+    disk: &Disk,
+    size: u32,
+    background: &image::RgbaImage,
+) -> image::RgbaImage {
+    let size = size.clamp(1, MAX_THUMBNAIL_SIZE);
+    let black_hole = BlackHole::with_constants(
+        Vec3::ZERO,
+        view.solar_masses * disk.constants.solar_mass(),
+        disk.constants,
+    );
+    let mut camera = Camera::new();
+    camera.azimuth = view.azimuth;
+    camera.elevation = view.elevation;
+    camera.constrain_elevation();
+    camera.radius = view.radius.clamp(camera.min_radius, camera.max_radius);
+    camera.fov = view.fov.clamp(MIN_FOV, MAX_FOV);
+    let disk = Disk {
+        inner_radius: view.disk_inner_radius,
+        outer_radius: view.disk_outer_radius,
+        central_mass: black_hole.mass,
+        ..*disk
+    };
+
+    let pixels = reference_render(
+        size,
+        size,
+        &camera,
+        &black_hole,
+        &disk,
+        background,
+        THUMBNAIL_STEPS,
+        DEFAULT_ESCAPE_RADIUS,
+    );
+    image::RgbaImage::from_fn(size, size, |x, y| {
+        image::Rgba(pixels[(y * size + x) as usize])
+    })
+}
+
+/// Renders `width` x `height` RGBA8 pixels, row by row from the top left,
+/// with the compute shader's algorithm. Rays come from the same
+/// `CameraUniform` the live view uploads; as in the shader, each gets
+/// `adaptive_steps` of `max_steps` and escapes at `escape_radius` meters.
+pub fn reference_render(
+    width: u32,
+    height: u32,
+    camera: &Camera,
+    black_hole: &BlackHole,
+    disk: &Disk,
+    background: &image::RgbaImage,
+    max_steps: u32,
+    escape_radius: f64,
+) -> Vec<[u8; 4]> {
+    let (pos, right, up, forward) = camera.basis();
+    let aspect = width as f32 / height as f32;
+    let uniform = CameraUniform {
+        pos: black_hole.to_local(pos),
+        right,
+        up,
+        forward,
+        tan_half_fov: camera.tan_half_fov(aspect),
+        aspect,
+        moving: camera.moving,
+        projection: camera.projection.shader_code(),
+        ortho_scale: camera.ortho_scale,
+        black_hole_rs: black_hole.r_s as f32,
+        c_light: black_hole.constants.c as f32,
+    };
+
+    let view = match camera.projection {
+        Projection::Orthographic => View {
+            position: uniform.pos,
+            footprint_base: 2.0 * uniform.ortho_scale / height as f32,
+            footprint_slope: 0.0,
+        },
+        Projection::Perspective => View {
+            position: uniform.pos,
+            footprint_base: 0.0,
+            footprint_slope: 2.0 * uniform.tan_half_fov / height as f32,
+        },
+    };
+    let slab_shown = disk.thickness > 0.0;
+
+    let mut pixels = Vec::with_capacity((width * height) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let ndc_x = 2.0 * (x as f32 + 0.5) / width as f32 - 1.0;
+            let ndc_y = 1.0 - 2.0 * (y as f32 + 0.5) / height as f32;
+            let (origin, dir) = uniform.primary_ray(ndc_x, ndc_y);
+
+            let steps = adaptive_steps(origin, black_hole.r_s, max_steps);
+
+            // Light in front of wherever the ray ends and its transmittance,
+            // kept as in the shader's `trace`
+            let mut disk_light = Vec4::new(0.0, 0.0, 0.0, 1.0);
+            let mut slab_entry = disk_light;
+            let mut slab_skip = false;
+            let trace = trace_like_shader_with(
+                origin,
+                dir,
+                black_hole.r_s,
+                escape_radius,
+                steps,
+                disk,
+                Vec3::ZERO,
+                0.0,
+                |step| {
+                    let (a, b) = (step.start.as_vec3(), step.end.as_vec3());
+                    if slab_shown {
+                        if !in_disk_slab(disk, a) {
+                            slab_entry = disk_light;
+                            slab_skip = false;
+                        }
+                        if step.disk_crossing.is_some() {
+                            disk_light = slab_entry;
+                            slab_skip = true;
+                        } else if !slab_skip {
+                            let stop = step.stop.map_or(1.0, |(t, _)| t.min(1.0));
+                            let end = a.lerp(b, stop as f32);
+                            disk_light = disk_slab_along(disk, &view, disk_light, a, end);
+                        }
+                    }
+                    let opaque = matches!(step.stop, Some((_, TraceResult::HitDisk)));
+                    if let (Some(t), false) = (step.disk_crossing, opaque) {
+                        let glow = disk_surface_color(disk, &view, a.lerp(b, t as f32), b - a);
+                        disk_light = (disk_light.truncate()
+                            + disk_light.w * DISK_CROSSING_OPACITY * glow)
+                            .extend(disk_light.w * (1.0 - DISK_CROSSING_OPACITY));
+                    }
+                },
+            );
+            let surface = match trace.result {
+                TraceResult::HitBlackHole => Vec3::ZERO,
+                TraceResult::HitDisk => {
+                    disk_surface_color(disk, &view, trace.position, trace.direction)
+                }
+                _ => sample_background(background, trace.position),
+            };
+            let color = disk_light.truncate() + disk_light.w * surface;
+
+            let [r, g, b] = color.clamp(Vec3::ZERO, Vec3::ONE).to_array();
+            let to_byte = |c: f32| (c * 255.0).round() as u8;
+            pixels.push([to_byte(r), to_byte(g), to_byte(b), 255]);
+        }
+    }
+    pixels
+}
+
+/// `disk_surface_color` in the shader, less the hotspots: light leaving the
+/// disk at `hit`, in meters from the hole, along a ray travelling in `dir`.
+fn disk_surface_color(disk: &Disk, view: &View, hit: Vec3, dir: Vec3) -> Vec3 {
+    let local = disk.to_local(hit);
+    let r = local.length().clamp(disk.inner_radius, disk.outer_radius);
+    let [red, green, blue] = disk.color_at(r, disk.color_mode);
+    let mut color = Vec3::new(red, green, blue) * disk_pattern(disk, local);
+    if disk.two_sided {
+        color *= disk_face_shading(disk, view, disk.to_local(dir.normalize()));
+    }
+    let radial = Vec2::new(local.x, local.z).length();
+    color *= disk_edge_feather(disk, view, radial, hit.distance(view.position));
+    grade_disk(disk, color).min(Vec3::ONE)
+}
+
+/// `disk_face_shading` in the shader, for `dir` in the disk's frame.
+fn disk_face_shading(disk: &Disk, view: &View, dir: Vec3) -> f32 {
+    let camera_side = if disk.to_local(view.position).y >= 0.0 {
+        1.0
+    } else {
+        -1.0
+    };
+    let facing = -dir.y * camera_side;
+    let face = DISK_FAR_FACE + (1.0 - DISK_FAR_FACE) * smoothstep(-0.1, 0.1, facing);
+    face * (0.75 + 0.25 * dir.y.abs())
+}
+
+/// `disk_edge_feather` in the shader.
+fn disk_edge_feather(disk: &Disk, view: &View, r: f32, distance: f32) -> f32 {
+    if disk.edge_softness <= 0.0 {
+        return 1.0;
+    }
+    let band = (disk.edge_softness * view.pixel_footprint(distance))
+        .min(0.5 * (disk.outer_radius - disk.inner_radius));
+    if band <= 0.0 {
+        return 1.0;
+    }
+    smoothstep(disk.inner_radius, disk.inner_radius + band, r)
+        * (1.0 - smoothstep(disk.outer_radius - band, disk.outer_radius, r))
+}
+
+/// `disk_density` in the shader.
+fn disk_density(disk: &Disk, y: f32, h: f32) -> f32 {
+    let rest = 1.0 - y.abs() / h;
+    if rest <= 0.0 {
+        return 0.0;
+    }
+    rest.powf(disk.vertical_sharpness)
+}
+
+/// `disk_column` in the shader.
+fn disk_column(disk: &Disk, y: f32, h: f32) -> f32 {
+    let p = disk.vertical_sharpness + 1.0;
+    let rest = 1.0 - (y.abs() / h).min(1.0);
+    let remaining = if rest <= 0.0 { 0.0 } else { rest.powf(p) };
+    // WGSL's `sign` is 0 at 0, unlike `f32::signum`
+    let sign = if y == 0.0 { 0.0 } else { y.signum() };
+    sign * h / p * (1.0 - remaining)
+}
+
+/// `in_disk_slab` in the shader, for `p` in meters from the hole.
+fn in_disk_slab(disk: &Disk, p: Vec3) -> bool {
+    let local = disk.to_local(p);
+    let r = Vec2::new(local.x, local.z).length();
+    local.y.abs() < 0.5 * disk.thickness && r >= disk.inner_radius && r <= disk.outer_radius
+}
+
+/// `disk_slab_along` in the shader: adds the slab's glow between `a` and
+/// `b` to `slab`, whose alpha is the transmittance so far.
+fn disk_slab_along(disk: &Disk, view: &View, slab: Vec4, a_world: Vec3, b_world: Vec3) -> Vec4 {
+    let h = 0.5 * disk.thickness;
+    let (a, b) = (disk.to_local(a_world), disk.to_local(b_world));
+    if a.y * b.y > 0.0 && a.y.abs().min(b.y.abs()) >= h {
+        return slab;
+    }
+    let mid = 0.5 * (a + b);
+    let r = Vec2::new(mid.x, mid.z).length();
+    if r < disk.inner_radius || r > disk.outer_radius {
+        return slab;
+    }
+
+    let seg_len = b.distance(a);
+    let rise = b.y - a.y;
+    let column = if rise.abs() > 1e-3 * h {
+        (disk_column(disk, b.y, h) - disk_column(disk, a.y, h)).abs() * seg_len / rise.abs()
+    } else {
+        disk_density(disk, mid.y, h) * seg_len
+    };
+    let feather = disk_edge_feather(
+        disk,
+        view,
+        r,
+        (0.5 * (a_world + b_world)).distance(view.position),
+    );
+    let tau = DISK_SLAB_DEPTH * column / disk_column(disk, h, h) * feather;
+    let alpha = 1.0 - (-tau).exp();
+    let [red, green, blue] = disk.color_at(r, disk.color_mode);
+    let glow = grade_disk(disk, Vec3::new(red, green, blue) * disk_pattern(disk, mid));
+    (slab.truncate() + slab.w * alpha * glow).extend(slab.w * (1.0 - alpha))
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// `grade_disk` in the shader: the disk's own saturation, then contrast as
+/// a power curve about linear mid-gray.
+fn grade_disk(disk: &Disk, color: Vec3) -> Vec3 {
+    const PIVOT: f32 = 0.18;
+    let luma = color.dot(Vec3::new(0.2126, 0.7152, 0.0722));
+    let saturated = Vec3::splat(luma)
+        .lerp(color, disk.saturation)
+        .max(Vec3::ZERO);
+    PIVOT * (saturated / PIVOT).powf(disk.contrast)
+}
+
+/// `sample_background` in the shader: nearest texel of the equirectangular
+/// map along `dir`.
+fn sample_background(background: &image::RgbaImage, dir: Vec3) -> Vec3 {
+    let dir = dir.normalize();
+    let u = 0.5 + dir.z.atan2(dir.x) / (2.0 * PI);
+    let v = 0.5 - dir.y.asin() / PI;
+    let (w, h) = background.dimensions();
+    let x = (u * w as f32) as u32 % w;
+    let y = (v * h as f32) as u32 % h;
+    let [r, g, b, _] = background.get_pixel(x, y).0;
+    Vec3::new(r as f32, g as f32, b as f32) / 255.0
+}
+
+/// `disk_pattern` in the shader.
+fn disk_pattern(disk: &Disk, hit: Vec3) -> f32 {
+    let seed = (disk.seed % 65536) as f32;
+    let seed_offset = Vec2::new(seed * 0.137, seed * 0.071);
+    let plane = Vec2::new(hit.x, hit.z);
+    let radial = plane.length() / disk.outer_radius;
+    match disk.style {
+        DiskStyle::Banded => {
+            let band = (radial * 24.0).floor();
+            let level = hash2(Vec2::new(band, 0.0) + seed_offset);
+            let edge = 0.5 + 0.5 * ((radial * 24.0).fract() * 2.0 * PI).cos();
+            0.55 + 0.45 * level * (0.6 + 0.4 * edge)
+        }
+        DiskStyle::Turbulent => {
+            let p = plane / disk.outer_radius * 6.0 + seed_offset;
+            let warp = Vec2::new(fbm(p), fbm(p + Vec2::new(5.2, 1.3)));
+            0.35 + 1.1 * fbm(p + 4.0 * warp)
+        }
+        DiskStyle::Smooth => 1.0,
+    }
+}
+
+fn hash2(p: Vec2) -> f32 {
+    let h = p.dot(Vec2::new(127.1, 311.7)).sin() * 43758.5453;
+    h - h.floor()
+}
+
+fn value_noise(p: Vec2) -> f32 {
+    let i = p.floor();
+    let f = p - i;
+    let w = f * f * (3.0 - 2.0 * f);
+    let a = hash2(i);
+    let b = hash2(i + Vec2::new(1.0, 0.0));
+    let c = hash2(i + Vec2::new(0.0, 1.0));
+    let d = hash2(i + Vec2::new(1.0, 1.0));
+    let lerp = |x: f32, y: f32, t: f32| x + (y - x) * t;
+    lerp(lerp(a, b, w.x), lerp(c, d, w.x), w.y)
+}
+
+fn fbm(p: Vec2) -> f32 {
+    let mut sum = 0.0;
+    let mut amplitude = 0.5;
+    let mut q = p;
+    for _ in 0..5 {
+        sum += amplitude * value_noise(q);
+        q = q * 2.03 + Vec2::new(17.0, 9.0);
+        amplitude *= 0.5;
+    }
+    sum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn white_sky() -> image::RgbaImage {
+        image::RgbaImage::from_pixel(8, 4, image::Rgba([255; 4]))
+    }
+
+    #[test]
+    fn shadow_is_round_and_centered() {
+        let size = 64;
+        let pixels = reference_render(
+            size,
+            size,
+            &Camera::new(),
+            &BlackHole::sagittarius_a(),
+            &Disk::new(0.0, 0.0, 0.0),
+            &white_sky(),
+            DEFAULT_MAX_STEPS,
+            DEFAULT_ESCAPE_RADIUS,
+        );
+        let dark = |x: u32, y: u32| pixels[(y * size + x) as usize] == [0, 0, 0, 255];
+        let row: Vec<u32> = (0..size).filter(|&x| dark(x, size / 2)).collect();
+        let column: Vec<u32> = (0..size).filter(|&y| dark(size / 2, y)).collect();
+
+        assert!(row.len() >= 4, "shadow too small: {:?}", row);
+        assert!(row.len().abs_diff(column.len()) <= 1);
+        for run in [&row, &column] {
+            let (first, last) = (run[0], run[run.len() - 1]);
+            assert_eq!(last - first + 1, run.len() as u32, "shadow has a gap");
+            assert!(
+                (first + last).abs_diff(size - 1) <= 1,
+                "off center: {:?}",
+                run
+            );
+        }
+        // Rays that escape show the sky untouched
+        assert_eq!(pixels[0], [255; 4]);
+    }
+
+    #[test]
+    fn translated_hole_and_camera_render_the_same_shadow() {
+        let size = 32;
+        let camera = Camera::new();
+        let render = |camera: &Camera, black_hole: &BlackHole| {
+            reference_render(
+                size,
+                size,
+                camera,
+                black_hole,
+                &Disk::new(0.0, 0.0, 0.0),
+                &white_sky(),
+                DEFAULT_MAX_STEPS,
+                DEFAULT_ESCAPE_RADIUS,
+            )
+        };
+        let centered = render(&camera, &BlackHole::sagittarius_a());
+
+        let offset = Vec3::new(3e10, -1e10, 2e10);
+        let mut black_hole = BlackHole::sagittarius_a();
+        black_hole.position = offset;
+        let mut moved = camera.clone();
+        assert!(moved.look_from(camera.position() + offset, offset));
+        let translated = render(&moved, &black_hole);
+
+        let dark = |pixels: &[[u8; 4]]| pixels.iter().filter(|p| **p == [0, 0, 0, 255]).count();
+        assert!(dark(&centered) >= 4);
+        let differing = centered
+            .iter()
+            .zip(&translated)
+            .filter(|(a, b)| a != b)
+            .count();
+        // Rounding the moved positions may flip a pixel on the shadow's rim
+        assert!(differing <= 2, "{} pixels differ", differing);
+    }
+
+    #[test]
+    fn thumbnail_follows_the_views_mass_and_fov() {
+        let size = 32;
+        let view = ViewParams {
+            solar_masses: BlackHole::sagittarius_a().mass / crate::physics::SOLAR_MASS,
+            azimuth: 0.0,
+            elevation: 1.66,
+            radius: 1.67e11,
+            fov: 60.0,
+            // Inside the horizon, so the disk never shows
+            disk_inner_radius: 1.0,
+            disk_outer_radius: 2.0,
+        };
+        let shadow_width = |view: &ViewParams| {
+            let image = render_thumbnail(view, &Disk::new(0.0, 0.0, 0.0), size, &white_sky());
+            (0..size)
+                .filter(|&x| image.get_pixel(x, size / 2).0 == [0, 0, 0, 255])
+                .count()
+        };
+
+        let base = shadow_width(&view);
+        assert!(base >= 2, "no shadow in the thumbnail");
+        let heavier = ViewParams {
+            solar_masses: 2.0 * view.solar_masses,
+            ..view
+        };
+        assert!(shadow_width(&heavier) > base + 2);
+        let zoomed = ViewParams { fov: 30.0, ..view };
+        assert!(shadow_width(&zoomed) > base + 2);
+    }
+
+    #[test]
+    fn slab_column_through_full_thickness_has_slab_depth_each_way() {
+        let mut disk = Disk::new(1.0, 3.0, 0.2);
+        disk.edge_softness = 0.0;
+        let view = View {
+            position: Vec3::new(0.0, 10.0, 0.0),
+            footprint_base: 0.0,
+            footprint_slope: 0.0,
+        };
+        let clear = Vec4::new(0.0, 0.0, 0.0, 1.0);
+        let top = Vec3::new(2.0, 0.1, 0.0);
+        let bottom = Vec3::new(2.0, -0.1, 0.0);
+
+        let slab = disk_slab_along(&disk, &view, clear, top, bottom);
+        assert!((slab.w - (-2.0 * DISK_SLAB_DEPTH).exp()).abs() < 1e-5);
+        let half = disk_slab_along(&disk, &view, clear, top, Vec3::new(2.0, 0.0, 0.0));
+        assert!((half.w - (-DISK_SLAB_DEPTH).exp()).abs() < 1e-5);
+        // Above the slab there is nothing to cross
+        let above = disk_slab_along(&disk, &view, clear, top + Vec3::Y, top + 0.5 * Vec3::Y);
+        assert_eq!(above, clear);
+    }
+
+    #[test]
+    fn edge_feather_fades_to_nothing_at_the_radii() {
+        let disk = Disk::new(1.0, 3.0, 0.0);
+        let view = View {
+            position: Vec3::ZERO,
+            footprint_base: 0.1,
+            footprint_slope: 0.0,
+        };
+        assert_eq!(disk_edge_feather(&disk, &view, 1.0, 5.0), 0.0);
+        assert_eq!(disk_edge_feather(&disk, &view, 2.0, 5.0), 1.0);
+        assert_eq!(disk_edge_feather(&disk, &view, 3.0, 5.0), 0.0);
+    }
+}
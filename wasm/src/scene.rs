@@ -0,0 +1,142 @@
+use crate::physics::Ray;
+use serde::Serialize;
+
+/// Physically scaled snapshot of the scene for HUDs. Serialized to JSON by
+/// `BlackHoleRenderer::scene_info`; lengths are in meters unless the field
+/// name says `_rs` (multiples of the Schwarzschild radius).
+#[derive(Debug, Clone, Serialize)]
+pub struct SceneInfo {
+    /// Camera position and distance relative to the hole, wherever
+    /// `set_black_hole_position` put it.
+    pub camera_position_m: [f32; 3],
+    pub camera_position_rs: [f32; 3],
+    pub camera_radius_m: f32,
+    pub camera_radius_rs: f32,
+    pub camera_azimuth: f32,
+    pub camera_elevation: f32,
+    pub black_hole_mass_kg: f64,
+    pub black_hole_mass_solar: f64,
+    pub schwarzschild_radius_m: f64,
+    pub disk_inner_radius_m: f32,
+    pub disk_outer_radius_m: f32,
+    pub planet_position_m: [f32; 3],
+    pub planet_semi_major_axis_m: f32,
+    pub planet_eccentricity: f32,
+    pub planet_mean_motion: f32,
+    pub fps: f64,
+}
+
+/// Optional features the device supports, read from the adapter when the
+/// renderer is created. Serialized to JSON by
+/// `BlackHoleRenderer::capabilities`, so the frontend can gray out options
+/// the device can't run instead of hitting errors.
+#[derive(Debug, Clone, Serialize)]
+pub struct Capabilities {
+    /// Graphics API behind the adapter, e.g. `"BrowserWebGpu"` or `"Gl"` for
+    /// the WebGL2 fallback.
+    pub backend: String,
+    /// Compute shaders, which tracing needs.
+    pub compute_shaders: bool,
+    /// Storage buffers in shaders, for light pulses and frame statistics.
+    pub storage_buffers: bool,
+    /// Writable storage textures, for the traced image.
+    pub storage_textures: bool,
+    /// `rgba32float` and `rg32float` storage textures, for motion vectors
+    /// and the lens map.
+    pub float_storage_textures: bool,
+    /// `rgba16float` as a render target.
+    pub float_render_targets: bool,
+    /// Filtering of 32-bit float textures.
+    pub float32_filterable: bool,
+    /// The canvas can present `rgba16float`, as HDR output needs.
+    pub hdr_surface: bool,
+    /// Largest width or height of a 2D texture, which bounds the compute
+    /// resolution and captures.
+    pub max_texture_size: u32,
+    /// `set_frame_latency` takes effect.
+    pub frame_latency: bool,
+}
+
+/// Ray outcomes over one traced frame, from the shader's frame statistics
+/// counters. Serialized to JSON by `BlackHoleRenderer::frame_stats`.
+/// Fractions are of all traced pixels; a high `max_steps_fraction` means the
+/// step budget (`set_integration_quality`) is too small for the view.
+#[derive(Debug, Clone, Serialize)]
+pub struct FrameStats {
+    pub pixels: u32,
+    pub captured_fraction: f64,
+    pub disk_fraction: f64,
+    pub planet_fraction: f64,
+    pub escaped_fraction: f64,
+    pub max_steps_fraction: f64,
+    /// Mean over all rays of the closest approach to the hole, in r_s. Each
+    /// ray's value is capped at `FrameStats::MAX_RADIUS_RS`.
+    pub mean_min_radius_rs: f64,
+}
+
+impl FrameStats {
+    /// Cap on a ray's recorded closest approach, matching `STATS_MAX_RADIUS_RS`
+    /// in `shader.wgsl`. It keeps the sum within a u32 at large resolutions.
+    pub const MAX_RADIUS_RS: f64 = 800.0;
+    /// Closest approach is summed in steps of this many r_s.
+    const RADIUS_QUANTUM_RS: f64 = 0.1;
+
+    /// Builds the stats from the counters, laid out as the pixel counts per
+    /// `TraceResult` discriminant followed by the closest-approach sum.
+    pub fn from_counters(counters: &[u32]) -> Self {
+        let count = |i: usize| counters.get(i).copied().unwrap_or(0);
+        let pixels: u32 = (0..5).map(count).sum();
+        let fraction = |i: usize| {
+            if pixels == 0 {
+                0.0
+            } else {
+                count(i) as f64 / pixels as f64
+            }
+        };
+
+        FrameStats {
+            pixels,
+            captured_fraction: fraction(0),
+            disk_fraction: fraction(1),
+            planet_fraction: fraction(2),
+            escaped_fraction: fraction(3),
+            max_steps_fraction: fraction(4),
+            mean_min_radius_rs: if pixels == 0 {
+                0.0
+            } else {
+                count(5) as f64 * Self::RADIUS_QUANTUM_RS / pixels as f64
+            },
+        }
+    }
+}
+
+/// Everything known about one primary ray, for debugging a single pixel.
+/// Serialized to JSON by `BlackHoleRenderer::inspect_pixel`. Fields that
+/// don't apply to how the ray ended hold sentinels: `final_ray` is null
+/// for a captured ray, `background_uv` is `[-1, -1]` unless the ray escaped,
+/// and `disk_hit_radius_rs` is -1 unless it hit the disk.
+#[derive(Debug, Clone, Serialize)]
+pub struct PixelInspection {
+    pub ndc: [f32; 2],
+    /// `TraceResult` variant name.
+    pub result: String,
+    pub steps: u32,
+    /// The ray's adaptive step budget, as the shader scales it for the
+    /// camera's distance.
+    pub max_steps: u32,
+    /// Closest approach to the hole, in r_s.
+    pub min_radius_rs: f64,
+    /// Angle swept around the hole in the ray's orbital plane, in turns.
+    pub winding: f64,
+    pub path_length_rs: f64,
+    /// Geodesic state at the camera.
+    pub initial_ray: Ray,
+    /// Geodesic state where the trace stopped, restarted from the final
+    /// position and direction.
+    pub final_ray: Option<Ray>,
+    /// Where the trace stopped, relative to the hole, in meters.
+    pub final_position: [f32; 3],
+    pub background_uv: [f32; 2],
+    /// Radius of the disk hit in the disk's plane, in r_s.
+    pub disk_hit_radius_rs: f64,
+}
@@ -0,0 +1,262 @@
+use crate::physics::{DEFAULT_ESCAPE_RADIUS, blackbody_rgb};
+use glam::{EulerRot, Mat3, Vec3};
+use wasm_bindgen::prelude::*;
+
+/// What escaped rays show.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Background {
+    /// The embedded star map.
+    Texture,
+    Solid([f32; 3]),
+    /// Blend from `bottom` looking down -Y to `top` looking up +Y, in the
+    /// background's rotated frame.
+    Gradient {
+        top: [f32; 3],
+        bottom: [f32; 3],
+    },
+}
+
+/// What the compute pass draws.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViewMode {
+    /// The traced, lensed scene.
+    Lensing,
+    /// The Flamm paraboloid, the shape of the hole's spatial geometry, as a
+    /// gridded surface seen along straight rays.
+    EmbeddingDiagram,
+}
+
+/// How the star map is sampled where escaped rays land on it.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackgroundFiltering {
+    /// The nearest texel, for a crisp, pixelated sky.
+    Nearest,
+    /// Blend of the four nearest texels.
+    Linear,
+}
+
+/// Color temperature in kelvin that leaves the background untinted.
+pub const NEUTRAL_WHITE_BALANCE: f32 = 6500.0;
+
+/// `blackbody_rgb` at `kelvin`, scaled so `NEUTRAL_WHITE_BALANCE` maps to
+/// white.
+pub fn white_balance_tint(kelvin: f32) -> [f32; 3] {
+    let tint = blackbody_rgb(kelvin);
+    let neutral = blackbody_rgb(NEUTRAL_WHITE_BALANCE);
+    [0, 1, 2].map(|i| tint[i] / neutral[i])
+}
+
+/// Render options that are not part of the physical scene. Compute-side
+/// options are packed into the `Params` uniform in `shader.wgsl`, whose field
+/// order must match `uniform_data`; display-only options are written to the
+/// display shader's uniform by the renderer.
+#[derive(Debug, Clone, Copy)]
+pub struct RenderSettings {
+    /// Radius in meters beyond which a ray counts as escaped, shared by the
+    /// shader and the CPU integrators.
+    pub escape_radius: f64,
+    pub jets_enabled: bool,
+    /// Length of each jet in meters, measured from the disk plane.
+    pub jet_length: f32,
+    /// Full opening angle of each jet cone in degrees.
+    pub jet_opening_angle_deg: f32,
+    pub jet_color: [f32; 3],
+    pub jet_intensity: f32,
+    /// Draw the world-axes gnomon in the display pass.
+    pub axes_overlay: bool,
+    /// Outline compute texels in the display pass to show the upscale factor.
+    pub compute_grid_overlay: bool,
+    /// Dither the display pass output against 8-bit banding.
+    pub dithering: bool,
+    /// Bend rays around the planet as well as the hole.
+    pub planet_lensing: bool,
+    /// Yaw, pitch and roll in degrees applied to escaped ray directions before
+    /// the background lookup.
+    pub background_rotation: [f32; 3],
+    pub background: Background,
+    /// Color temperature in kelvin the background is tinted toward;
+    /// `NEUTRAL_WHITE_BALANCE` leaves it as is.
+    pub background_white_balance: f32,
+    /// Radius in meters of the sphere the background is painted on; infinite
+    /// for a sky at infinity.
+    pub background_distance: f64,
+    /// Strength of the stylized per-channel lensing split. Physically,
+    /// gravitational lensing is achromatic; this is purely for looks.
+    pub dispersion: f32,
+    /// Direction toward a distant light lighting the planet, normalized.
+    pub planet_light_direction: [f32; 3],
+    pub planet_light_color: [f32; 3],
+    pub planet_light_intensity: f32,
+    /// Draw the planet's orbital ellipse, lensed, as a faint line.
+    pub orbit_overlay: bool,
+    pub orbit_overlay_color: [f32; 3],
+    /// Overlay a line along the traced edge between captured and escaping
+    /// rays.
+    pub critical_curve: bool,
+    pub critical_curve_color: [f32; 3],
+    /// Width of the ring in compute pixels.
+    pub critical_curve_thickness: f32,
+    /// Count ray outcomes in the compute pass for `frame_stats`.
+    pub frame_stats: bool,
+    /// Write each pixel's background UV for `export_lens_map`.
+    pub lens_map: bool,
+    /// Trace extra sub-samples for pixels on the shadow's edge.
+    pub edge_refinement: bool,
+    /// Hide the disk, planet and jets so only the lensed background and the
+    /// black shadow remain.
+    pub background_only: bool,
+    /// Brightness multiplier for light from rays that wound at least
+    /// `PHOTON_RING_WINDING` times around the hole. 1 leaves it unchanged.
+    pub photon_ring_boost: f32,
+    /// Aberrate the background for the camera's `observer_velocity`.
+    pub aberration: bool,
+    /// Camera velocity relative to the hole's static frame, as a fraction of
+    /// the speed of light, in world axes. Its length is below 1.
+    pub observer_velocity: [f32; 3],
+    /// Integrate rays in emulated double precision in the shader.
+    pub high_precision: bool,
+    pub view_mode: ViewMode,
+    pub background_filtering: BackgroundFiltering,
+}
+
+impl RenderSettings {
+    /// `background_rotation` as the matrix taking world directions to the
+    /// background's frame.
+    pub fn background_rotation_matrix(&self) -> Mat3 {
+        let [yaw, pitch, roll] = self.background_rotation.map(f32::to_radians);
+        Mat3::from_euler(EulerRot::YXZ, yaw, pitch, roll)
+    }
+
+    /// `aberrate` in the shader: the static-frame direction of light seen
+    /// along `dir` by a camera moving at `observer_velocity`.
+    pub fn aberrate(&self, dir: Vec3) -> Vec3 {
+        let beta = Vec3::from(self.observer_velocity);
+        let b = beta.length();
+        if !self.aberration || b <= 0.0 {
+            return dir;
+        }
+        let axis = beta / b;
+        let cos_seen = dir.dot(axis);
+        let across = dir - cos_seen * axis;
+        let across_len = across.length();
+        let cos_static = (cos_seen - b) / (1.0 - b * cos_seen);
+        if across_len < 1e-6 {
+            return axis * cos_static.signum();
+        }
+        let sin_static = (1.0 - cos_static * cos_static).max(0.0).sqrt();
+        cos_static * axis + sin_static * across / across_len
+    }
+
+    /// Equirectangular texture coordinates of the background along the world
+    /// direction `dir`, as the shader's lens map records them.
+    pub fn background_uv(&self, dir: Vec3) -> [f32; 2] {
+        let sky = (self.background_rotation_matrix() * self.aberrate(dir.normalize())).normalize();
+        [
+            0.5 + sky.z.atan2(sky.x) / std::f32::consts::TAU,
+            0.5 - sky.y.asin() / std::f32::consts::PI,
+        ]
+    }
+
+    pub fn uniform_data(&self) -> Vec<f32> {
+        let mut data = vec![
+            self.escape_radius as f32,
+            if self.jets_enabled { 1.0 } else { 0.0 },
+            self.jet_length,
+            (self.jet_opening_angle_deg.to_radians() / 2.0).tan(),
+            self.jet_color[0],
+            self.jet_color[1],
+            self.jet_color[2],
+            self.jet_intensity,
+        ];
+
+        for column in self.background_rotation_matrix().to_cols_array_2d() {
+            data.extend_from_slice(&column);
+            data.push(0.0);
+        }
+
+        data.extend_from_slice(&[
+            self.dispersion,
+            self.critical_curve_thickness,
+            if self.frame_stats { 1.0 } else { 0.0 },
+            // 0 stands for infinity
+            if self.background_distance.is_finite() {
+                self.background_distance as f32
+            } else {
+                0.0
+            },
+        ]);
+        data.extend_from_slice(&self.critical_curve_color);
+        data.push(if self.critical_curve { 1.0 } else { 0.0 });
+
+        let (computed, top, bottom) = match self.background {
+            Background::Texture => (0.0, [0.0; 3], [0.0; 3]),
+            Background::Solid(color) => (1.0, color, color),
+            Background::Gradient { top, bottom } => (1.0, top, bottom),
+        };
+        data.extend_from_slice(&top);
+        data.push(computed);
+        data.extend_from_slice(&bottom);
+        data.push(0.0);
+
+        data.extend_from_slice(&[
+            if self.lens_map { 1.0 } else { 0.0 },
+            if self.edge_refinement { 1.0 } else { 0.0 },
+            if self.background_only { 1.0 } else { 0.0 },
+            self.photon_ring_boost,
+        ]);
+        data.extend_from_slice(&white_balance_tint(self.background_white_balance));
+        data.push(0.0);
+        data.extend_from_slice(&self.observer_velocity);
+        data.push(if self.aberration { 1.0 } else { 0.0 });
+        data.extend_from_slice(&[
+            if self.high_precision { 1.0 } else { 0.0 },
+            self.view_mode as u32 as f32,
+            self.background_filtering as u32 as f32,
+            0.0,
+        ]);
+
+        data
+    }
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        RenderSettings {
+            escape_radius: DEFAULT_ESCAPE_RADIUS,
+            jets_enabled: false,
+            jet_length: 2.5e11,
+            jet_opening_angle_deg: 10.0,
+            jet_color: [0.5, 0.7, 1.0],
+            jet_intensity: 1.0,
+            axes_overlay: false,
+            compute_grid_overlay: false,
+            dithering: true,
+            planet_lensing: false,
+            background_rotation: [0.0; 3],
+            background: Background::Texture,
+            background_white_balance: NEUTRAL_WHITE_BALANCE,
+            background_distance: f64::INFINITY,
+            dispersion: 0.0,
+            planet_light_direction: [0.0, 0.8, 0.6],
+            planet_light_color: [1.0, 0.95, 0.9],
+            planet_light_intensity: 0.35,
+            orbit_overlay: false,
+            orbit_overlay_color: [0.6, 0.8, 1.0],
+            critical_curve: false,
+            critical_curve_color: [1.0, 0.85, 0.2],
+            critical_curve_thickness: 1.5,
+            frame_stats: false,
+            lens_map: false,
+            edge_refinement: false,
+            background_only: false,
+            photon_ring_boost: 1.0,
+            aberration: false,
+            observer_velocity: [0.0; 3],
+            high_precision: false,
+            view_mode: ViewMode::Lensing,
+            background_filtering: BackgroundFiltering::Nearest,
+        }
+    }
+}
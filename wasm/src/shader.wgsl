@@ -13,18 +13,95 @@ struct Camera {
     aspect: f32,
     moving: u32,
     _pad4: u32,
+    projection: f32, // 0 = perspective, 1 = orthographic, 2 = equirectangular
+    ortho_scale: f32, // half-height of the orthographic view in meters
+    black_hole_rs: f32, // Schwarzschild radius of the hole in meters
+    c_light: f32, // speed of light in meters per unit of simulation time
 }
 
 struct Disk {
     inner_radius: f32,
     outer_radius: f32,
-    _pad: f32,
+    two_sided: f32,
     thickness: f32,
+    style: f32, // 0 = smooth, 1 = turbulent, 2 = banded
+    seed: f32,
+    saturation: f32,
+    contrast: f32,
+    hotspot_count: f32,
+    hotspot_brightness: f32,
+    hotspot_lifetime: f32, // simulated seconds
+    time: f32, // simulated seconds
+    to_disk: mat3x3<f32>, // world to the disk's frame, where it lies in y = 0
+    edge_softness: f32, // feather width at the inner and outer radii, in pixels
+    vertical_sharpness: f32, // power of the slab's falloff away from the midplane
+    max_crossings: f32, // midplane crossings composited; all but the last are translucent
+    _pad2: f32,
 }
 
 struct Planet {
     position: vec3<f32>,
     radius: f32,
+    schwarzschild_radius: f32, // of the planet itself, in meters
+    lensing_enabled: f32,
+    visible: f32,
+    _pad1: f32,
+    light_dir: vec4<f32>, // direction toward the light, intensity
+    light_color: vec4<f32>,
+    orbit_color: vec4<f32>, // rgb, overlay enabled
+    orbit_normal: vec4<f32>, // xyz normal of the orbital plane, line half-width in meters
+    orbit_points: array<vec4<f32>, ORBIT_POINTS>, // closed path around the orbit, in meters
+}
+
+struct Params {
+    escape_radius: f32,
+    jets_enabled: f32,
+    jet_length: f32,
+    jet_tan_half_angle: f32,
+    jet_color: vec4<f32>, // rgb, intensity
+    background_rotation: mat3x3<f32>,
+    dispersion: f32, // stylistic; 0 = achromatic
+    critical_curve_thickness: f32, // pixels
+    frame_stats: f32,
+    background_distance: f32, // meters; 0 = at infinity
+    critical_curve_color: vec4<f32>, // rgb, enabled
+    background_top: vec4<f32>, // rgb, 1 = computed instead of the texture
+    background_bottom: vec4<f32>,
+    lens_map: f32,
+    edge_refinement: f32,
+    background_only: f32, // hides the disk, planet and jets
+    photon_ring_boost: f32, // multiplier for rays past PHOTON_RING_WINDING; 1 = off
+    background_tint: vec4<f32>, // rgb white balance multiplier
+    observer_velocity: vec4<f32>, // xyz in units of c, aberration enabled
+    high_precision: f32, // integrate u and du/dphi in double-single
+    view_mode: f32, // 0 = lensing, 1 = embedding diagram
+    background_filtering: f32, // 0 = nearest, 1 = linear
+    _pad8: f32,
+}
+
+// Photons of active light pulses: xyz position in meters, w unused
+struct Pulses {
+    count: f32,
+    _pad0: f32,
+    _pad1: f32,
+    _pad2: f32,
+    photons: array<vec4<f32>>,
+}
+
+// Previous frame's camera basis and how far the planet moved since, for
+// reprojecting this frame's hit points
+struct Motion {
+    prev_pos: vec3<f32>,
+    _pad0: f32,
+    prev_right: vec3<f32>,
+    _pad1: f32,
+    prev_up: vec3<f32>,
+    _pad2: f32,
+    prev_forward: vec3<f32>,
+    _pad3: f32,
+    planet_delta: vec3<f32>,
+    enabled: f32,
+    _pad4: vec4<f32>,
 }
 
 @group(0) @binding(0) var output_texture: texture_storage_2d<rgba8unorm, write>;
@@ -32,20 +109,459 @@ struct Planet {
 @group(0) @binding(2) var<uniform> disk: Disk;
 @group(0) @binding(3) var<uniform> planet: Planet;
 @group(0) @binding(4) var background_texture: texture_2d<f32>;
+@group(0) @binding(5) var<uniform> params: Params;
+@group(0) @binding(6) var<storage, read> pulses: Pulses;
+@group(0) @binding(7) var motion_texture: texture_storage_2d<rgba32float, write>;
+@group(0) @binding(8) var<uniform> motion: Motion;
+// Base disk color from the inner to the outer edge, built from `Disk::color_at`
+@group(0) @binding(9) var<storage, read> disk_lut: array<vec4<f32>>;
+// Pixels per outcome, indexed like `TraceResult`, then the sum of each ray's
+// closest approach in tenths of r_s; cleared before every dispatch
+@group(0) @binding(10) var<storage, read_write> ray_stats: array<atomic<u32>, 8>;
+// Background UV each pixel sampled, or LENS_MAP_NONE where none was
+@group(0) @binding(11) var lens_texture: texture_storage_2d<rg32float, write>;
 
-const WIDTH: u32 = 800u;
-const HEIGHT: u32 = 600u;
-const NSTEPS: u32 = 2000u;
+// Set from Rust as a pipeline override; see `set_integration_quality`
+override NSTEPS: u32 = 2000u;
 const MAX_REVOLUTIONS: f32 = 2.0;
 const PI: f32 = 3.14159265359;
-const SAG_A_RS: f32 = 1.269e10;
+const JET_BETA: f32 = 0.9;
+const DISK_FAR_FACE: f32 = 0.45;
+const DISK_GRADE_PIVOT: f32 = 0.18; // linear mid-gray, the disk contrast pivot
+const DISK_SLAB_DEPTH: f32 = 2.0; // optical depth from the slab's top straight down to the midplane
+const DISK_CROSSING_OPACITY: f32 = 0.75; // of each translucent midplane crossing
+const MAX_HOTSPOTS: u32 = 16u;
+const ORBIT_POINTS: u32 = 64u;
+const PHOTON_RING_WINDING: f32 = 0.75; // turns about the hole
+const PHOTON_RING_FADE: f32 = 0.1; // turns over which the boost fades in
+const EMBEDDING_EXTENT_RS: f32 = 12.0; // rim radius of the embedding diagram
+const EMBEDDING_MARCH_STEPS: u32 = 192u;
+const EMBEDDING_SPOKE_ANGLE: f32 = 0.261799; // 15 degrees
+const ORBIT_LINE_OPACITY: f32 = 0.6;
+const HOTSPOT_SIZE: f32 = 0.04; // gaussian sigma, fraction of the outer radius
+const STATS_MAX_RADIUS_RS: f32 = 800.0;
+const LENS_MAP_NONE: f32 = -1.0;
+// Surfaces a ray can stop on within one step
+const HIT_NONE: u32 = 0u;
+const HIT_HORIZON: u32 = 1u;
+const HIT_DISK: u32 = 2u;
+const HIT_PLANET: u32 = 3u;
+
+// A world-space point or direction in the disk's frame, which can be tilted
+// and precessing.
+fn to_disk_frame(v: vec3<f32>) -> vec3<f32> {
+    return disk.to_disk * v;
+}
 
-fn crosses_equatorial_plane(old_pos: vec3<f32>, new_pos: vec3<f32>) -> bool {
+fn crosses_equatorial_plane(old_world: vec3<f32>, new_world: vec3<f32>) -> bool {
+    let old_pos = to_disk_frame(old_world);
+    let new_pos = to_disk_frame(new_world);
     let crossed = (old_pos.y * new_pos.y) < 0.0;
     let r = length(vec2<f32>(new_pos.x, new_pos.z));
     return crossed && (r >= disk.inner_radius && r <= disk.outer_radius);
 }
 
+// Relativistic Doppler factor for plasma moving along `flow` at JET_BETA,
+// seen by light leaving in direction `photon_dir`.
+fn doppler_factor(flow: vec3<f32>, photon_dir: vec3<f32>) -> f32 {
+    let gamma = 1.0 / sqrt(1.0 - JET_BETA * JET_BETA);
+    return 1.0 / (gamma * (1.0 - JET_BETA * dot(flow, photon_dir)));
+}
+
+// Blue-shift approaching emission and red-shift receding emission.
+fn doppler_tint(color: vec3<f32>, d: f32) -> vec3<f32> {
+    return color * vec3<f32>(pow(1.0 / d, 0.5), 1.0, pow(d, 0.5));
+}
+
+// Emission picked up by a ray segment passing through the bipolar jets along
+// the disk's axis. The jets are optically thin, so each segment adds light in
+// proportion to its length, sampled at its midpoint.
+fn jet_emission_along(a_world: vec3<f32>, b_world: vec3<f32>) -> vec3<f32> {
+    let a = to_disk_frame(a_world);
+    let b = to_disk_frame(b_world);
+    let mid = 0.5 * (a + b);
+    let height = abs(mid.y);
+    if (height > params.jet_length) {
+        return vec3<f32>(0.0);
+    }
+
+    let cone_radius = disk.thickness + height * params.jet_tan_half_angle;
+    let axis_dist = length(mid.xz);
+    if (axis_dist > cone_radius) {
+        return vec3<f32>(0.0);
+    }
+
+    let segment = b - a;
+    let seg_len = length(segment);
+    if (seg_len <= 0.0) {
+        return vec3<f32>(0.0);
+    }
+
+    let core = 1.0 - axis_dist / cone_radius;
+    let fade = 1.0 - height / params.jet_length;
+    let density = core * core * fade * seg_len / params.jet_length;
+
+    // The traced ray runs backward, so the light itself travels along -segment.
+    // Cap the beaming so looking straight down a jet saturates instead of blowing up.
+    let flow = vec3<f32>(0.0, sign(mid.y), 0.0);
+    let d = min(doppler_factor(flow, -segment / seg_len), 2.0);
+    let beamed = doppler_tint(params.jet_color.rgb, d) * d * d * d;
+    return beamed * params.jet_color.a * density;
+}
+
+// The orbit overlay's line where a ray segment crosses the planet's orbital
+// plane, fading out across the line's width.
+fn orbit_line_along(a: vec3<f32>, b: vec3<f32>) -> vec3<f32> {
+    let normal = planet.orbit_normal.xyz;
+    let height_a = dot(a, normal);
+    let height_b = dot(b, normal);
+    if (height_a * height_b > 0.0 || height_a == height_b) {
+        return vec3<f32>(0.0);
+    }
+    let crossing = mix(a, b, height_a / (height_a - height_b));
+
+    var dist = 1e30;
+    for (var i = 0u; i < ORBIT_POINTS; i++) {
+        let p = planet.orbit_points[i].xyz;
+        let q = planet.orbit_points[(i + 1u) % ORBIT_POINTS].xyz;
+        let seg = q - p;
+        let t = clamp(dot(crossing - p, seg) / max(dot(seg, seg), 1e-30), 0.0, 1.0);
+        dist = min(dist, length(crossing - (p + seg * t)));
+    }
+    let half_width = planet.orbit_normal.w;
+    if (dist >= half_width) {
+        return vec3<f32>(0.0);
+    }
+    return planet.orbit_color.rgb * ORBIT_LINE_OPACITY * (1.0 - dist / half_width);
+}
+
+// Glow from light-pulse photons drawn as points at their projected screen
+// position. The photons themselves move along geodesics on the CPU.
+fn pulse_glow(pix: vec2<f32>, dims: vec2<f32>) -> vec3<f32> {
+    var glow = 0.0;
+    let count = u32(pulses.count);
+    for (var i = 0u; i < count; i++) {
+        let d = pulses.photons[i].xyz - camera.pos;
+        let z = dot(d, camera.forward);
+        if (z <= 0.0) {
+            continue;
+        }
+        let extent = select(z * camera.tan_half_fov, camera.ortho_scale, camera.projection > 0.5);
+        let ndc_x = dot(d, camera.right) / (extent * camera.aspect);
+        let ndc_y = -dot(d, camera.up) / extent;
+        let screen = vec2<f32>((ndc_x + 1.0) * 0.5 * dims.x, (1.0 - ndc_y) * 0.5 * dims.y);
+        let dist2 = dot(pix - screen, pix - screen);
+        glow += exp(-dist2 / 4.0);
+    }
+    return vec3<f32>(1.0, 0.95, 0.8) * glow;
+}
+
+fn hash2(p: vec2<f32>) -> f32 {
+    return fract(sin(dot(p, vec2<f32>(127.1, 311.7))) * 43758.5453);
+}
+
+fn value_noise(p: vec2<f32>) -> f32 {
+    let i = floor(p);
+    let f = fract(p);
+    let w = f * f * (3.0 - 2.0 * f);
+    let a = hash2(i);
+    let b = hash2(i + vec2<f32>(1.0, 0.0));
+    let c = hash2(i + vec2<f32>(0.0, 1.0));
+    let d = hash2(i + vec2<f32>(1.0, 1.0));
+    return mix(mix(a, b, w.x), mix(c, d, w.x), w.y);
+}
+
+fn fbm(p: vec2<f32>) -> f32 {
+    var sum = 0.0;
+    var amplitude = 0.5;
+    var q = p;
+    for (var i = 0; i < 5; i++) {
+        sum += amplitude * value_noise(q);
+        q = q * 2.03 + vec2<f32>(17.0, 9.0);
+        amplitude *= 0.5;
+    }
+    return sum;
+}
+
+// Pixel position (not center-offset) that a world point (w = 1) or a
+// direction at infinity (w = 0) had in the previous frame. z is 0 when it
+// can't be reprojected: behind the camera, or a direction seen through the
+// orthographic projection.
+fn previous_pixel(p: vec4<f32>, dims: vec2<f32>) -> vec3<f32> {
+    let rel = p.xyz - motion.prev_pos * p.w;
+    var ndc = vec2<f32>(0.0);
+    if (camera.projection > 0.5) {
+        if (p.w < 0.5) {
+            return vec3<f32>(0.0);
+        }
+        ndc = vec2<f32>(
+            dot(rel, motion.prev_right) / (camera.aspect * camera.ortho_scale),
+            -dot(rel, motion.prev_up) / camera.ortho_scale
+        );
+    } else {
+        let depth = dot(rel, motion.prev_forward);
+        if (depth <= 0.0) {
+            return vec3<f32>(0.0);
+        }
+        ndc = vec2<f32>(
+            dot(rel, motion.prev_right) / (depth * camera.aspect * camera.tan_half_fov),
+            -dot(rel, motion.prev_up) / (depth * camera.tan_half_fov)
+        );
+    }
+    return vec3<f32>((ndc.x + 1.0) * 0.5 * dims.x, (1.0 - ndc.y) * 0.5 * dims.y, 1.0);
+}
+
+// Direction in the hole's static frame of light a camera moving at
+// `params.observer_velocity` sees arriving from `dir`. Sources crowd toward
+// the direction of motion: cos(theta) = (cos(theta') - b) / (1 - b cos(theta')).
+fn aberrate(dir: vec3<f32>) -> vec3<f32> {
+    let beta = params.observer_velocity.xyz;
+    let b = length(beta);
+    if (params.observer_velocity.w < 0.5 || b <= 0.0) {
+        return dir;
+    }
+    let axis = beta / b;
+    let cos_seen = dot(dir, axis);
+    let across = dir - cos_seen * axis;
+    let across_len = length(across);
+    let cos_static = (cos_seen - b) / (1.0 - b * cos_seen);
+    if (across_len < 1e-6) {
+        return axis * sign(cos_static);
+    }
+    let sin_static = sqrt(max(1.0 - cos_static * cos_static, 0.0));
+    return cos_static * axis + sin_static * across / across_len;
+}
+
+// Background color seen along a world-space escape direction.
+fn sample_background(dir: vec3<f32>) -> vec3<f32> {
+    let sky_dir = params.background_rotation * aberrate(dir);
+    if (params.background_top.w > 0.5) {
+        let t = 0.5 + 0.5 * sky_dir.y;
+        let sky = mix(params.background_bottom.rgb, params.background_top.rgb, t);
+        return min(sky * params.background_tint.rgb, vec3<f32>(1.0));
+    }
+    let uv = direction_to_uv(sky_dir);
+
+    var sky: vec3<f32>;
+    if (params.background_filtering > 0.5) {
+        sky = background_texel_linear(uv);
+    } else {
+        // Convert UV to texture coordinates (textureLoad requires integer coordinates in compute shaders)
+        let tex_dims = textureDimensions(background_texture);
+        let tex_x = u32(uv.x * f32(tex_dims.x)) % tex_dims.x;
+        let tex_y = u32(uv.y * f32(tex_dims.y)) % tex_dims.y;
+        sky = textureLoad(background_texture, vec2<u32>(tex_x, tex_y), 0).rgb;
+    }
+    return min(sky * params.background_tint.rgb, vec3<f32>(1.0));
+}
+
+// Bilinear blend of the star map's four texels around `uv`, by hand since
+// the compute pass binds the texture without a sampler. Longitude wraps
+// around the seam; latitude clamps at the poles.
+fn background_texel_linear(uv: vec2<f32>) -> vec3<f32> {
+    let dims = vec2<i32>(textureDimensions(background_texture));
+    let texel = uv * vec2<f32>(dims) - 0.5;
+    let base = vec2<i32>(floor(texel));
+    let f = texel - floor(texel);
+    let x0 = ((base.x % dims.x) + dims.x) % dims.x;
+    let x1 = (x0 + 1) % dims.x;
+    let y0 = clamp(base.y, 0, dims.y - 1);
+    let y1 = clamp(base.y + 1, 0, dims.y - 1);
+    let top = mix(
+        textureLoad(background_texture, vec2<i32>(x0, y0), 0).rgb,
+        textureLoad(background_texture, vec2<i32>(x1, y0), 0).rgb,
+        f.x,
+    );
+    let bottom = mix(
+        textureLoad(background_texture, vec2<i32>(x0, y1), 0).rgb,
+        textureLoad(background_texture, vec2<i32>(x1, y1), 0).rgb,
+        f.x,
+    );
+    return mix(top, bottom, f.y);
+}
+
+// Brightness of the disk face a ray lands on, for `ray_dir` in the disk's
+// frame. The face on the camera's side of the plane is lit fully and the far
+// face (seen through lensing) dimmer; the two blend smoothly near grazing
+// incidence so there is no seam.
+fn disk_face_shading(ray_dir: vec3<f32>) -> f32 {
+    let camera_side = select(-1.0, 1.0, to_disk_frame(camera.pos).y >= 0.0);
+    // Positive when the ray comes down onto the camera-side face
+    let facing = -ray_dir.y * camera_side;
+    let face = mix(DISK_FAR_FACE, 1.0, smoothstep(-0.1, 0.1, facing));
+    let incidence = 0.75 + 0.25 * abs(ray_dir.y);
+    return face * incidence;
+}
+
+// Light from the orbiting hotspots at a disk hit point, for a ray arriving
+// along `ray_dir`, both in the disk's frame. Each spot orbits at the local
+// Keplerian rate, fades in and out over its lifetime, then respawns at a new
+// radius and angle. Spots are Doppler beamed: brighter and bluer on the
+// approaching side.
+fn hotspot_emission(hit: vec3<f32>, ray_dir: vec3<f32>) -> vec3<f32> {
+    var total = vec3<f32>(0.0);
+    let count = min(u32(disk.hotspot_count), MAX_HOTSPOTS);
+    let lifetime = max(disk.hotspot_lifetime, 1e-3);
+    let gm = 0.5 * camera.black_hole_rs * camera.c_light * camera.c_light;
+    let sigma = HOTSPOT_SIZE * disk.outer_radius;
+
+    for (var i = 0u; i < count; i++) {
+        let fi = f32(i);
+        // Stagger the spots so they don't all respawn together
+        let cycle_time = disk.time / lifetime + hash2(vec2<f32>(fi, disk.seed));
+        let cycle = floor(cycle_time);
+        let age = cycle_time - cycle;
+        let key = vec2<f32>(fi * 7.31 + cycle, disk.seed + cycle * 1.7);
+
+        // Favor the hotter inner disk
+        let h = hash2(key);
+        let radius = mix(disk.inner_radius, disk.outer_radius, h * h);
+        let omega = sqrt(gm / (radius * radius * radius));
+        let angle = hash2(key + vec2<f32>(3.1, 1.9)) * 2.0 * PI + omega * age * lifetime;
+        let center = radius * vec2<f32>(cos(angle), sin(angle));
+
+        let offset = hit.xz - center;
+        let blob = exp(-dot(offset, offset) / (2.0 * sigma * sigma)) * sin(PI * age);
+        if (blob < 1e-3) {
+            continue;
+        }
+
+        let beta = omega * radius / camera.c_light;
+        let velocity = beta * vec3<f32>(-sin(angle), 0.0, cos(angle));
+        let gamma = 1.0 / sqrt(1.0 - beta * beta);
+        // The photon travels back along the ray, toward the camera
+        let d = 1.0 / (gamma * (1.0 + dot(velocity, ray_dir)));
+        total += doppler_tint(vec3<f32>(1.0, 0.9, 0.7), d) * blob * d * d * d;
+    }
+    return total * disk.hotspot_brightness;
+}
+
+// Applies the disk's own saturation and contrast to its emission.
+fn grade_disk(color: vec3<f32>) -> vec3<f32> {
+    let luma = dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+    let saturated = max(mix(vec3<f32>(luma), color, disk.saturation), vec3<f32>(0.0));
+    return DISK_GRADE_PIVOT * pow(saturated / DISK_GRADE_PIVOT, vec3<f32>(disk.contrast));
+}
+
+// Base disk color at radius `r` meters, linearly interpolated from the
+// lookup table.
+fn disk_base_color(r: f32) -> vec3<f32> {
+    let last = f32(arrayLength(&disk_lut) - 1u);
+    let t = clamp((r - disk.inner_radius) / (disk.outer_radius - disk.inner_radius), 0.0, 1.0) * last;
+    let i = u32(floor(t));
+    let j = min(i + 1u, u32(last));
+    return mix(disk_lut[i].rgb, disk_lut[j].rgb, fract(t));
+}
+
+// Size in meters of one output pixel at `distance` from the camera, ignoring
+// lensing.
+fn pixel_footprint(distance: f32) -> f32 {
+    let height = f32(textureDimensions(output_texture).y);
+    if (camera.projection > 1.5) {
+        return distance * PI / height;
+    } else if (camera.projection > 0.5) {
+        return 2.0 * camera.ortho_scale / height;
+    }
+    return distance * 2.0 * camera.tan_half_fov / height;
+}
+
+// Fades the disk's emission to nothing over `edge_softness` pixels at its
+// inner and outer radii, so its rim doesn't alias. `distance` is from the
+// camera to the hit point.
+fn disk_edge_feather(r: f32, distance: f32) -> f32 {
+    if (disk.edge_softness <= 0.0) {
+        return 1.0;
+    }
+    let band = min(
+        disk.edge_softness * pixel_footprint(distance),
+        0.5 * (disk.outer_radius - disk.inner_radius),
+    );
+    if (band <= 0.0) {
+        return 1.0;
+    }
+    return smoothstep(disk.inner_radius, disk.inner_radius + band, r)
+        * (1.0 - smoothstep(disk.outer_radius - band, disk.outer_radius, r));
+}
+
+// Density of the disk's slab at height `y` above its midplane, relative to
+// the midplane, for a slab of half-thickness `h`.
+fn disk_density(y: f32, h: f32) -> f32 {
+    let rest = 1.0 - abs(y) / h;
+    if (rest <= 0.0) {
+        return 0.0;
+    }
+    return pow(rest, disk.vertical_sharpness);
+}
+
+// Integral of `disk_density` from the midplane to height `y`: odd in `y` and
+// constant beyond the slab, so the column between two heights is a
+// difference of two calls.
+fn disk_column(y: f32, h: f32) -> f32 {
+    let p = disk.vertical_sharpness + 1.0;
+    let rest = 1.0 - min(abs(y) / h, 1.0);
+    let remaining = select(pow(rest, p), 0.0, rest <= 0.0);
+    return sign(y) * h / p * (1.0 - remaining);
+}
+
+// Whether a world-space point lies inside the disk's slab.
+fn in_disk_slab(p_world: vec3<f32>) -> bool {
+    let p = to_disk_frame(p_world);
+    let r = length(p.xz);
+    return abs(p.y) < 0.5 * disk.thickness && r >= disk.inner_radius && r <= disk.outer_radius;
+}
+
+// Adds the glow of the disk's slab along a ray segment to `slab` (rgb light
+// so far, alpha the transmittance so far), front to back. Height varies
+// linearly along a straight segment, so the material crossed is exact
+// however long the step; a segment nearly level with the disk would divide
+// by almost nothing, so it samples the density at its midpoint instead,
+// which keeps grazing rays from flickering between steps.
+fn disk_slab_along(slab: vec4<f32>, a_world: vec3<f32>, b_world: vec3<f32>) -> vec4<f32> {
+    let h = 0.5 * disk.thickness;
+    let a = to_disk_frame(a_world);
+    let b = to_disk_frame(b_world);
+    if (a.y * b.y > 0.0 && min(abs(a.y), abs(b.y)) >= h) {
+        return slab;
+    }
+    let mid = 0.5 * (a + b);
+    let r = length(mid.xz);
+    if (r < disk.inner_radius || r > disk.outer_radius) {
+        return slab;
+    }
+
+    let seg_len = length(b - a);
+    let rise = b.y - a.y;
+    var column = disk_density(mid.y, h) * seg_len;
+    if (abs(rise) > 1e-3 * h) {
+        column = abs(disk_column(b.y, h) - disk_column(a.y, h)) * seg_len / abs(rise);
+    }
+    let feather = disk_edge_feather(r, length(0.5 * (a_world + b_world) - camera.pos));
+    let tau = DISK_SLAB_DEPTH * column / disk_column(h, h) * feather;
+    let alpha = 1.0 - exp(-tau);
+    let glow = grade_disk(disk_base_color(r) * disk_pattern(mid));
+    return vec4<f32>(slab.rgb + slab.a * alpha * glow, slab.a * (1.0 - alpha));
+}
+
+// Brightness multiplier for the selected procedural disk style at a point
+// in the disk plane (physical units).
+fn disk_pattern(hit: vec3<f32>) -> f32 {
+    let seed_offset = vec2<f32>(disk.seed * 0.137, disk.seed * 0.071);
+    let radial = length(hit.xz) / disk.outer_radius;
+    if (disk.style > 1.5) {
+        // Banded: concentric rings, each with its own brightness
+        let band = floor(radial * 24.0);
+        let level = hash2(vec2<f32>(band, 0.0) + seed_offset);
+        let edge = 0.5 + 0.5 * cos(fract(radial * 24.0) * 2.0 * PI);
+        return 0.55 + 0.45 * level * (0.6 + 0.4 * edge);
+    } else if (disk.style > 0.5) {
+        // Turbulent: domain-warped fbm over the disk plane
+        let p = hit.xz / disk.outer_radius * 6.0 + seed_offset;
+        let warp = vec2<f32>(fbm(p), fbm(p + vec2<f32>(5.2, 1.3)));
+        return 0.35 + 1.1 * fbm(p + 4.0 * warp);
+    }
+    return 1.0;
+}
+
 fn direction_to_uv(dir: vec3<f32>) -> vec2<f32> {
     let normalized = normalize(dir);
     let u = 0.5 + atan2(normalized.z, normalized.x) / (2.0 * PI);
@@ -72,145 +588,669 @@ fn intersect_sphere(ray_origin: vec3<f32>, ray_dir: vec3<f32>, sphere_center: ve
     return t;
 }
 
-@compute @workgroup_size(16, 16)
-fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
-    let pix = vec2<u32>(global_id.xy);
-    if (pix.x >= WIDTH || pix.y >= HEIGHT) {
-        return;
+// Double-single arithmetic: a value held as an unevaluated sum hi + lo of
+// two f32s, for about 48 bits of mantissa. Relies on the compiler keeping
+// the order of operations in two_sum and on fma being fused.
+
+// a + b exactly, as (rounded sum, error)
+fn two_sum(a: f32, b: f32) -> vec2<f32> {
+    let s = a + b;
+    let bb = s - a;
+    let err = (a - (s - bb)) + (b - bb);
+    return vec2<f32>(s, err);
+}
+
+// a * b exactly, as (rounded product, error)
+fn two_prod(a: f32, b: f32) -> vec2<f32> {
+    let p = a * b;
+    return vec2<f32>(p, fma(a, b, -p));
+}
+
+fn ds_add(a: vec2<f32>, b: vec2<f32>) -> vec2<f32> {
+    let s = two_sum(a.x, b.x);
+    return two_sum(s.x, s.y + a.y + b.y);
+}
+
+fn ds_mul(a: vec2<f32>, b: vec2<f32>) -> vec2<f32> {
+    let p = two_prod(a.x, b.x);
+    return two_sum(p.x, p.y + a.x * b.y + a.y * b.x);
+}
+
+fn ds_scale(a: vec2<f32>, b: f32) -> vec2<f32> {
+    let p = two_prod(a.x, b);
+    return two_sum(p.x, p.y + a.y * b);
+}
+
+// A ray's orbital plane around the hole: it moves as
+// pos = (cos(phi) * normal + sin(phi) * tangent) / u, starting at phi = 0.
+struct Orbit {
+    normal: vec3<f32>,
+    tangent: vec3<f32>,
+    du: f32,
+}
+
+// Sets up the orbital plane for a ray at `pos` (geometric units) heading in `dir`.
+fn init_orbit(pos: vec3<f32>, dir: vec3<f32>) -> Orbit {
+    var orbit: Orbit;
+    let u = 1.0 / length(pos);
+    orbit.normal = normalize(pos);
+    let tangent_vec_unnorm = cross(cross(orbit.normal, dir), orbit.normal);
+    let tangent_len = length(tangent_vec_unnorm);
+
+    // Safeguard against degenerate cases
+    orbit.du = 0.0;
+    if (tangent_len > 1e-6) {
+        orbit.tangent = tangent_vec_unnorm / tangent_len;
+        let denominator = dot(dir, orbit.tangent);
+        if (abs(denominator) > 1e-6) {
+            orbit.du = -dot(dir, orbit.normal) / denominator * u;
+        }
+    } else {
+        // Fallback: use a perpendicular vector
+        orbit.tangent = normalize(cross(orbit.normal, vec3<f32>(0.0, 1.0, 0.0)));
+        if (length(cross(orbit.normal, vec3<f32>(0.0, 1.0, 0.0))) < 1e-6) {
+            orbit.tangent = normalize(cross(orbit.normal, vec3<f32>(1.0, 0.0, 0.0)));
+        }
     }
+    return orbit;
+}
 
-    // Initialize ray from camera
-    let screen_u = (2.0 * (f32(pix.x) + 0.5) / f32(WIDTH) - 1.0) * camera.aspect * camera.tan_half_fov;
-    let screen_v = (1.0 - 2.0 * (f32(pix.y) + 0.5) / f32(HEIGHT)) * camera.tan_half_fov;
-    let ray_dir = normalize(screen_u * camera.right - screen_v * camera.up + camera.forward);
+// Weak-field deflection angle 2 r_s / b toward the planet for a segment
+// whose closest approach lies within it, or 0 if it doesn't pass nearby.
+fn planet_deflection(a: vec3<f32>, b: vec3<f32>) -> vec4<f32> {
+    let seg = b - a;
+    let len2 = dot(seg, seg);
+    if (len2 <= 0.0) {
+        return vec4<f32>(0.0);
+    }
+    let t = dot(planet.position - a, seg) / len2;
+    if (t < 0.0 || t > 1.0) {
+        return vec4<f32>(0.0);
+    }
+    let to_planet = planet.position - (a + seg * t);
+    let impact = length(to_planet);
+    if (impact <= planet.radius || impact > planet.radius * 50.0) {
+        return vec4<f32>(0.0);
+    }
+    let angle = 2.0 * planet.schwarzschild_radius / impact;
+    return vec4<f32>(to_planet / impact, angle);
+}
 
-    var color = vec4<f32>(0.0, 0.0, 0.0, 1.0);
+struct PrimaryRay {
+    origin: vec3<f32>,
+    dir: vec3<f32>,
+}
 
+// Camera ray through a point in pixel coordinates (y down).
+fn primary_ray(pixel: vec2<f32>, dims: vec2<f32>) -> PrimaryRay {
+    let ndc_x = 2.0 * pixel.x / dims.x - 1.0;
+    let ndc_y = 1.0 - 2.0 * pixel.y / dims.y;
+    var ray: PrimaryRay;
+    ray.origin = camera.pos;
+    ray.dir = camera.forward;
+    if (camera.projection > 1.5) {
+        // Equirectangular panorama around the camera. Screen-up is -up, as in
+        // the perspective ray generation below.
+        let lon = ndc_x * PI;
+        let lat = ndc_y * 0.5 * PI;
+        ray.dir = normalize(cos(lat) * (sin(lon) * camera.right + cos(lon) * camera.forward)
+            - sin(lat) * camera.up);
+    } else if (camera.projection > 0.5) {
+        // Orthographic: parallel rays leaving a plane through the camera
+        ray.origin += ndc_x * camera.aspect * camera.ortho_scale * camera.right
+            - ndc_y * camera.ortho_scale * camera.up;
+    } else {
+        let screen_u = ndc_x * camera.aspect * camera.tan_half_fov;
+        let screen_v = ndc_y * camera.tan_half_fov;
+        ray.dir = normalize(screen_u * camera.right - screen_v * camera.up + camera.forward);
+    }
+    return ray;
+}
+
+// Impact parameter of a straight ray about the hole, |origin x dir|.
+fn impact_parameter(ray: PrimaryRay) -> f32 {
+    return length(cross(ray.origin, ray.dir));
+}
+
+// Coverage of the critical curve at a pixel, drawn along the edge of the
+// shadow as traced rather than at the analytic b_c = (3 sqrt(3) / 2) r_s,
+// which this integrator's shadow falls a little inside. Rays half the line's
+// width to either side along each axis are traced, and the pixel is on the
+// line where any of them disagrees with its own ray on capture. Where the
+// disk or planet is in front of the edge nothing is captured on either side,
+// so the line is hidden along with the edge.
+fn critical_curve_coverage(pixel: vec2<f32>, dims: vec2<f32>, captured: bool) -> f32 {
+    let offset = max(0.5 * params.critical_curve_thickness, 0.5);
+    var offsets = array<vec2<f32>, 4>(
+        vec2<f32>(-offset, 0.0),
+        vec2<f32>(offset, 0.0),
+        vec2<f32>(0.0, -offset),
+        vec2<f32>(0.0, offset),
+    );
+    for (var i = 0u; i < 4u; i++) {
+        let ray = primary_ray(pixel + offsets[i], dims);
+        if (trace(ray.origin, ray.dir).hit_black_hole != captured) {
+            return 1.0;
+        }
+    }
+    return 0.0;
+}
+
+// Where a ray ended up: its last two positions in geometric units
+// (r_s = 2), what stopped it, and what it picked up on the way.
+struct Trace {
+    pos: vec3<f32>,
+    old_pos: vec3<f32>,
+    min_r: f32,
+    hit_black_hole: bool,
+    escaped: bool,
+    hit_disk: bool,
+    hit_planet: bool,
+    planet_normal: vec3<f32>,
+    jet_emission: vec3<f32>,
+    orbit_emission: vec3<f32>,
+    disk_light: vec4<f32>, // disk light in front (slab glow, translucent crossings), transmittance
+    winding: f32, // turns swept about the hole
+}
+
+fn trace(ray_origin: vec3<f32>, ray_dir: vec3<f32>) -> Trace {
     // Normalize to geometric units where r_s = 2.0
-    let unit_scale = SAG_A_RS / 2.0;
+    let unit_scale = camera.black_hole_rs / 2.0;
 
     // Leapfrog integration using u = 1/r (in geometric units)
-    var pos = camera.pos / unit_scale;
+    var pos = ray_origin / unit_scale;
     var u = 1.0 / length(pos);
     let u0 = u;
+    let high_precision = params.high_precision > 0.5;
     let r0 = 1.0 / u0;
 
     // Adaptive step count: fewer steps when far away, since gravity is weaker
     // At r=10 (close), use full 2000 steps. At r=1000 (far), use ~200 steps
     let distance_factor = clamp(100.0 / r0, 0.1, 1.0);
     let adaptive_nsteps = u32(f32(NSTEPS) * distance_factor);
-    let escape_distance = max(1000.0, r0 * 1.5);
+    let escape_distance = max(params.escape_radius / unit_scale, r0 * 1.5);
+    // A finite background only encloses the scene if the camera is inside it
+    let background_distance = params.background_distance / unit_scale;
+    let finite_background = params.background_distance > 0.0 && r0 < background_distance;
 
-    let normal_vec = normalize(pos);
-    let tangent_vec_unnorm = cross(cross(normal_vec, ray_dir), normal_vec);
-    let tangent_len = length(tangent_vec_unnorm);
+    let show_scene = params.background_only < 0.5;
+    let planet_shown = show_scene && planet.visible > 0.5;
 
-    // Safeguard against degenerate cases
-    var tangent_vec = vec3<f32>(0.0);
-    var du = 0.0;
-    if (tangent_len > 1e-6) {
-        tangent_vec = tangent_vec_unnorm / tangent_len;
-        let denominator = dot(ray_dir, tangent_vec);
-        if (abs(denominator) > 1e-6) {
-            du = -dot(ray_dir, normal_vec) / denominator * u;
-        }
-    } else {
-        // Fallback: use a perpendicular vector
-        tangent_vec = normalize(cross(normal_vec, vec3<f32>(0.0, 1.0, 0.0)));
-        if (length(cross(normal_vec, vec3<f32>(0.0, 1.0, 0.0))) < 1e-6) {
-            tangent_vec = normalize(cross(normal_vec, vec3<f32>(1.0, 0.0, 0.0)));
-        }
-    }
-    let du0 = du;
+    let orbit = init_orbit(pos, ray_dir);
+    var normal_vec = orbit.normal;
+    var tangent_vec = orbit.tangent;
+    var du = orbit.du;
+    var planet_deflected = false;
+    // u and du in double-single, when high_precision; u and du then hold
+    // their rounded values
+    var u_ds = vec2<f32>(u, 0.0);
+    var du_ds = vec2<f32>(du, 0.0);
 
     var phi = 0.0;
+    var swept = 0.0;
     var old_pos = pos;
+    var min_r = r0;
 
     var hit_black_hole = false;
+    var escaped = false;
     var hit_disk = false;
     var hit_planet = false;
     var planet_normal = vec3<f32>(0.0);
+    var jet_emission = vec3<f32>(0.0);
+    var orbit_emission = vec3<f32>(0.0);
+    let slab_shown = show_scene && disk.thickness > 0.0;
+    var disk_light = vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    // `disk_light` as of the ray's last entry into the slab. A ray that goes
+    // on to reach the midplane shows the disk's surface, which already
+    // stands for the slab it came through, so it reverts to this, and after
+    // a translucent crossing skips the rest of that slab.
+    var slab_entry = disk_light;
+    var slab_skip = false;
+    var crossings = 0u;
 
     for (var j = 0u; j < adaptive_nsteps; j++) {
         let step = MAX_REVOLUTIONS * 2.0 * PI / f32(adaptive_nsteps);
 
         // Leapfrog integration (in geometric units where r_s = 2.0)
-        u += du * step;
-        let ddu = -u * (1.0 - 1.5 * u * u);
-        du += ddu * step;
+        let old_u = u;
+        if (high_precision) {
+            // Over thousands of steps, f32 sums lose the low bits of u that
+            // decide whether a ray near the shadow's edge escapes
+            u_ds = ds_add(u_ds, ds_scale(du_ds, step));
+            let ddu_ds = ds_add(ds_scale(ds_mul(ds_mul(u_ds, u_ds), u_ds), 1.5), -u_ds);
+            du_ds = ds_add(du_ds, ds_scale(ddu_ds, step));
+            u = u_ds.x;
+            du = du_ds.x;
+        } else {
+            u += du * step;
+            let ddu = -u * (1.0 - 1.5 * u * u);
+            du += ddu * step;
+        }
 
         if (u < 0.0) {
+            escaped = true;
             break;
         }
 
         phi += step;
+        swept += step;
 
         old_pos = pos;
         pos = (cos(phi) * normal_vec + sin(phi) * tangent_vec) / u;
 
         let r = 1.0 / u;
+        min_r = min(min_r, r);
 
-        // Check for event horizon (u > 0.5 means r < 2.0 in geometric units)
-        if (u > 0.5) {
-            hit_black_hole = true;
-            break;
-        }
-
-        // Check for disk crossing (need to convert back to physical units)
+        // A step can reach more than one surface; only the nearest along it
+        // counts, so the opaque planet hides disk behind it and vice versa.
+        // Each candidate is a fraction of the way along the step.
         let pos_physical = pos * unit_scale;
         let old_pos_physical = old_pos * unit_scale;
-        if (crosses_equatorial_plane(old_pos_physical, pos_physical)) {
-            hit_disk = true;
-            break;
+        var nearest = 2.0;
+        var nearest_hit = HIT_NONE;
+
+        // Event horizon: u > 0.5 means r < 2.0 in geometric units
+        if (u > 0.5) {
+            nearest = clamp((0.5 - old_u) / (u - old_u), 0.0, 1.0);
+            nearest_hit = HIT_HORIZON;
         }
 
-        // Check for planet intersection
         let ray_segment = pos_physical - old_pos_physical;
         let ray_length = length(ray_segment);
-        if (ray_length > 0.0) {
+        if (planet_shown && ray_length > 0.0) {
             let ray_dir_norm = ray_segment / ray_length;
             let t = intersect_sphere(old_pos_physical, ray_dir_norm, planet.position, planet.radius);
-            if (t >= 0.0 && t <= ray_length) {
+            if (t >= 0.0 && t <= ray_length && t / ray_length < nearest) {
                 let hit_point = old_pos_physical + ray_dir_norm * t;
                 planet_normal = normalize(hit_point - planet.position);
-                hit_planet = true;
-                break;
+                nearest = t / ray_length;
+                nearest_hit = HIT_PLANET;
             }
         }
 
+        // The disk is weighed against the solid surfaces separately: all but
+        // the last crossing allowed are translucent, and the ray goes on
+        // through them to whatever lies behind, even within the same step
+        var disk_t = 2.0;
+        if (show_scene && crosses_equatorial_plane(old_pos_physical, pos_physical)) {
+            let old_height = to_disk_frame(old_pos_physical).y;
+            disk_t = old_height / (old_height - to_disk_frame(pos_physical).y);
+        }
+        let disk_first = disk_t < nearest;
+        let disk_opaque = disk_first && crossings + 1u >= u32(disk.max_crossings);
+        if (disk_opaque) {
+            nearest = disk_t;
+            nearest_hit = HIT_DISK;
+        }
+
+        if (slab_shown) {
+            if (!in_disk_slab(old_pos_physical)) {
+                slab_entry = disk_light;
+                slab_skip = false;
+            }
+            if (disk_first) {
+                disk_light = slab_entry;
+                slab_skip = true;
+            } else if (!slab_skip) {
+                let end = mix(old_pos_physical, pos_physical, min(nearest, 1.0));
+                disk_light = disk_slab_along(disk_light, old_pos_physical, end);
+            }
+        }
+
+        // Crossings are met in order along the ray, so compositing each
+        // behind the light gathered so far keeps them front to back
+        if (disk_first && !disk_opaque) {
+            let hit = mix(old_pos_physical, pos_physical, disk_t);
+            let glow = disk_surface_color(hit, ray_segment);
+            disk_light = vec4<f32>(
+                disk_light.rgb + disk_light.a * DISK_CROSSING_OPACITY * glow,
+                disk_light.a * (1.0 - DISK_CROSSING_OPACITY),
+            );
+            crossings += 1u;
+        }
+
+        if (nearest_hit == HIT_HORIZON) {
+            hit_black_hole = true;
+            break;
+        } else if (nearest_hit == HIT_DISK) {
+            hit_disk = true;
+            break;
+        } else if (nearest_hit == HIT_PLANET) {
+            hit_planet = true;
+            break;
+        }
+
+        if (show_scene && params.jets_enabled > 0.5) {
+            jet_emission += jet_emission_along(old_pos_physical, pos_physical);
+        }
+
+        if (planet_shown && planet.orbit_color.a > 0.5) {
+            orbit_emission += orbit_line_along(old_pos_physical, pos_physical);
+        }
+
+        // The planet's own weak lensing: bend once at closest approach and
+        // continue in the new orbital plane around the hole
+        if (planet_shown && planet.lensing_enabled > 0.5 && !planet_deflected) {
+            let deflection = planet_deflection(old_pos_physical, pos_physical);
+            if (deflection.w > 0.0) {
+                let dir = normalize(pos - old_pos);
+                let new_dir = normalize(dir + deflection.xyz * deflection.w);
+                let bent = init_orbit(pos, new_dir);
+                normal_vec = bent.normal;
+                tangent_vec = bent.tangent;
+                du = bent.du;
+                u = 1.0 / length(pos);
+                u_ds = vec2<f32>(u, 0.0);
+                du_ds = vec2<f32>(du, 0.0);
+                phi = 0.0;
+                planet_deflected = true;
+            }
+        }
+
+        // A ray leaving a finite background sphere samples it where it
+        // crosses: the far root of |old_pos + t * segment| = distance
+        if (finite_background && r > background_distance) {
+            let segment = pos - old_pos;
+            let a = dot(segment, segment);
+            let b = dot(old_pos, segment);
+            let c = dot(old_pos, old_pos) - background_distance * background_distance;
+            let t = (-b + sqrt(max(b * b - a * c, 0.0))) / a;
+            pos = old_pos + segment * clamp(t, 0.0, 1.0);
+            escaped = true;
+            break;
+        }
+
         // Escape condition: ray has traveled far enough away
         if (r > escape_distance) {
+            escaped = true;
             break;
         }
     }
 
-    if (hit_black_hole) {
+    var result: Trace;
+    result.pos = pos;
+    result.old_pos = old_pos;
+    result.min_r = min_r;
+    result.hit_black_hole = hit_black_hole;
+    result.escaped = escaped;
+    result.hit_disk = hit_disk;
+    result.hit_planet = hit_planet;
+    result.planet_normal = planet_normal;
+    result.jet_emission = jet_emission;
+    result.orbit_emission = orbit_emission;
+    result.disk_light = disk_light;
+    result.winding = swept / (2.0 * PI);
+    return result;
+}
+
+// Light leaving the disk's surface at `hit_world`, in meters, along a ray
+// travelling in direction `ray_dir_world`.
+fn disk_surface_color(hit_world: vec3<f32>, ray_dir_world: vec3<f32>) -> vec3<f32> {
+    let hit = to_disk_frame(hit_world);
+    let dir = to_disk_frame(normalize(ray_dir_world));
+    var disk_color = disk_base_color(length(hit)) * disk_pattern(hit);
+    if (disk.two_sided > 0.5) {
+        disk_color *= disk_face_shading(dir);
+    }
+    if (disk.hotspot_count > 0.5) {
+        disk_color += hotspot_emission(hit, dir);
+    }
+    disk_color *= disk_edge_feather(length(hit.xz), length(hit_world - camera.pos));
+    return min(grade_disk(disk_color), vec3<f32>(1.0));
+}
+
+// Color of a traced ray, including jet emission in front of whatever it
+// ended on.
+fn shade(t: Trace, ray_dir: vec3<f32>) -> vec3<f32> {
+    var color = vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    if (t.hit_black_hole) {
         color = vec4<f32>(0.0, 0.0, 0.0, 1.0);
-    } else if (hit_planet) {
+    } else if (t.hit_planet) {
         // Simple Lambertian shading
         let light_dir = normalize(-planet.position);
-        let diffuse = max(0.0, dot(planet_normal, light_dir));
+        let diffuse = max(0.0, dot(t.planet_normal, light_dir));
         let ambient = 0.2;
         let brightness = ambient + (1.0 - ambient) * diffuse;
-        let planet_color = vec3<f32>(0.4, 0.6, 0.9) * brightness;
-        color = vec4<f32>(planet_color, 1.0);
-    } else if (hit_disk) {
-        let pos_physical = pos * unit_scale;
-        let r = length(pos_physical) / disk.outer_radius;
-        let disk_color = vec3<f32>(1.0, r, 0.2);
-        color = vec4<f32>(disk_color, 1.0);
+        // Distant directional light; its night side gets only the above
+        let star = max(0.0, dot(t.planet_normal, planet.light_dir.xyz)) * planet.light_dir.w;
+        let base = vec3<f32>(0.4, 0.6, 0.9);
+        let planet_color = base * brightness + base * planet.light_color.rgb * star;
+        color = vec4<f32>(min(planet_color, vec3<f32>(1.0)), 1.0);
+    } else if (t.hit_disk) {
+        let hit = t.pos * camera.black_hole_rs / 2.0;
+        color = vec4<f32>(disk_surface_color(hit, t.pos - t.old_pos), 1.0);
     } else {
         // Ray escaped - sample background using final ray direction (gravitationally bent!)
-        let final_ray_dir = normalize(pos);
-        let uv = direction_to_uv(final_ray_dir);
+        let final_ray_dir = normalize(t.pos);
+        if (params.dispersion > 0.0) {
+            // Stylized, not physical: exaggerate the bend for blue and relax
+            // it for red, splitting rings into rainbows
+            let bend = (final_ray_dir - ray_dir) * params.dispersion;
+            let red = sample_background(normalize(final_ray_dir - bend)).r;
+            let green = sample_background(final_ray_dir).g;
+            let blue = sample_background(normalize(final_ray_dir + bend)).b;
+            color = vec4<f32>(red, green, blue, 1.0);
+        } else {
+            color = vec4<f32>(sample_background(final_ray_dir), 1.0);
+        }
+    }
 
-        // Convert UV to texture coordinates (textureLoad requires integer coordinates in compute shaders)
-        let tex_dims = textureDimensions(background_texture);
-        let tex_x = u32(uv.x * f32(tex_dims.x)) % tex_dims.x;
-        let tex_y = u32(uv.y * f32(tex_dims.y)) % tex_dims.y;
-        let bg_color = textureLoad(background_texture, vec2<u32>(tex_x, tex_y), 0);
-        color = vec4<f32>(bg_color.rgb, 1.0);
+    // The disk's slab and translucent crossings are in front of whatever
+    // the ray ended on
+    color = vec4<f32>(t.disk_light.rgb + t.disk_light.a * color.rgb, 1.0);
+
+    // Emphasis only: the ring is already as bright as the physics makes it
+    if (params.photon_ring_boost != 1.0) {
+        let ring = smoothstep(PHOTON_RING_WINDING - PHOTON_RING_FADE, PHOTON_RING_WINDING, t.winding);
+        color = vec4<f32>(color.rgb * mix(1.0, params.photon_ring_boost, ring), 1.0);
+    }
+
+    // Jets and the orbit overlay are additive, in front of whatever the ray
+    // ended on
+    return min(color.rgb + t.jet_emission + t.orbit_emission, vec3<f32>(1.0));
+}
+
+// Whether a pixel straddles the shadow's edge: its four neighbors disagree
+// on capture. Uses the analytic capture test on the straight camera rays
+// (impact parameter below critical, and heading inward from outside the
+// photon sphere) so no neighbor has to be traced.
+fn on_shadow_edge(pixel: vec2<f32>, dims: vec2<f32>) -> bool {
+    let critical = 2.598076 * camera.black_hole_rs;
+    var offsets = array<vec2<f32>, 4>(
+        vec2<f32>(-1.0, 0.0),
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(0.0, -1.0),
+        vec2<f32>(0.0, 1.0),
+    );
+    var captured = 0u;
+    for (var i = 0u; i < 4u; i++) {
+        let ray = primary_ray(pixel + offsets[i], dims);
+        let inside_photon_sphere = length(ray.origin) < 1.5 * camera.black_hole_rs;
+        let inward = dot(ray.origin, ray.dir) < 0.0;
+        if (impact_parameter(ray) < critical && (inward || inside_photon_sphere)) {
+            captured += 1u;
+        }
+    }
+    return captured > 0u && captured < 4u;
+}
+
+// Height of a point above the Flamm paraboloid z(r) = 2 sqrt(r_s (r - r_s)),
+// placed so its throat sits `depth` below the hole's center and its rim
+// level with it. Negative below the surface.
+fn above_embedding(p: vec3<f32>, depth: f32) -> f32 {
+    let rs = camera.black_hole_rs;
+    return p.y + depth - 2.0 * sqrt(rs * max(length(p.xz) - rs, 0.0));
+}
+
+// Coverage of a grid line `dist` meters away, about a pixel wide.
+fn grid_line(dist: f32, footprint: f32) -> f32 {
+    return 1.0 - smoothstep(0.5, 1.5, dist / footprint);
+}
+
+// The embedding diagram seen along a straight camera ray: marched for a sign
+// change of `above_embedding`, then refined by bisection.
+fn embedding_diagram(ray: PrimaryRay) -> vec3<f32> {
+    let background = vec3<f32>(0.02, 0.02, 0.04);
+    let rs = camera.black_hole_rs;
+    let rim = EMBEDDING_EXTENT_RS * rs;
+    let depth = 2.0 * sqrt(rs * (rim - rs));
+
+    // Bounding sphere around the funnel
+    let center = vec3<f32>(0.0, -0.5 * depth, 0.0);
+    let bound = length(vec2<f32>(rim, 0.5 * depth));
+    let to_center = ray.origin - center;
+    let b = dot(to_center, ray.dir);
+    let disc = b * b - (dot(to_center, to_center) - bound * bound);
+    if (disc < 0.0) {
+        return background;
+    }
+    let t_far = -b + sqrt(disc);
+    var t_prev = max(-b - sqrt(disc), 0.0);
+    if (t_far <= t_prev) {
+        return background;
+    }
+
+    let dt = (t_far - t_prev) / f32(EMBEDDING_MARCH_STEPS);
+    var side_prev = above_embedding(ray.origin + ray.dir * t_prev, depth);
+    var hit_t = -1.0;
+    for (var i = 0u; i < EMBEDDING_MARCH_STEPS; i++) {
+        let t = t_prev + dt;
+        let side = above_embedding(ray.origin + ray.dir * t, depth);
+        if (side_prev * side <= 0.0) {
+            var lo = t_prev;
+            var hi = t;
+            for (var j = 0u; j < 16u; j++) {
+                let mid = 0.5 * (lo + hi);
+                if (above_embedding(ray.origin + ray.dir * mid, depth) * side_prev > 0.0) {
+                    lo = mid;
+                } else {
+                    hi = mid;
+                }
+            }
+            // Inside the throat and past the rim there is no surface
+            let rho = length((ray.origin + ray.dir * hi).xz);
+            if (rho >= rs && rho <= rim) {
+                hit_t = hi;
+                break;
+            }
+        }
+        t_prev = t;
+        side_prev = side;
+    }
+    if (hit_t < 0.0) {
+        return background;
+    }
+
+    let hit = ray.origin + ray.dir * hit_t;
+    let rho = length(hit.xz);
+    let slope = sqrt(rs / max(rho - rs, 1e-4 * rs));
+    let normal = normalize(vec3<f32>(-slope * hit.x / rho, 1.0, -slope * hit.z / rho));
+    let light = normalize(vec3<f32>(0.3, 1.0, 0.2));
+    let diffuse = abs(dot(normal, light));
+    let base = mix(vec3<f32>(0.05, 0.1, 0.35), vec3<f32>(0.2, 0.55, 0.6), (hit.y + depth) / depth);
+    let shaded = base * (0.3 + 0.7 * diffuse);
+
+    // Grid: circles one r_s apart, measured along the slope, and spokes
+    let footprint = pixel_footprint(hit_t);
+    let stretch = sqrt(1.0 + slope * slope);
+    let ring = abs(rho - round(rho / rs) * rs) * stretch;
+    let angle = atan2(hit.z, hit.x);
+    let spoke = rho * abs(angle - round(angle / EMBEDDING_SPOKE_ANGLE) * EMBEDDING_SPOKE_ANGLE);
+    let grid = max(grid_line(ring, footprint), grid_line(spoke, footprint));
+    let color = mix(shaded, vec3<f32>(0.85, 0.9, 1.0), 0.7 * grid);
+    let throat = grid_line((rho - rs) * stretch, 2.0 * footprint);
+    return mix(color, vec3<f32>(1.0, 0.25, 0.2), throat);
+}
+
+@compute @workgroup_size(16, 16)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let pix = vec2<u32>(global_id.xy);
+    let dims = textureDimensions(output_texture);
+    if (pix.x >= dims.x || pix.y >= dims.y) {
+        return;
+    }
+
+    let center = vec2<f32>(pix) + 0.5;
+    let primary = primary_ray(center, vec2<f32>(dims));
+    if (params.view_mode > 0.5) {
+        textureStore(output_texture, vec2<i32>(pix), vec4<f32>(embedding_diagram(primary), 1.0));
+        return;
+    }
+    let traced = trace(primary.origin, primary.dir);
+    let pos = traced.pos;
+    let hit_black_hole = traced.hit_black_hole;
+    let hit_disk = traced.hit_disk;
+    let hit_planet = traced.hit_planet;
+    let unit_scale = camera.black_hole_rs / 2.0;
+
+    var rgb = shade(traced, primary.dir);
+    if (params.edge_refinement > 0.5 && on_shadow_edge(center, vec2<f32>(dims))) {
+        // Rotated-grid sub-samples, averaged with the center
+        var offsets = array<vec2<f32>, 4>(
+            vec2<f32>(-0.125, -0.375),
+            vec2<f32>(0.375, -0.125),
+            vec2<f32>(0.125, 0.375),
+            vec2<f32>(-0.375, 0.125),
+        );
+        for (var i = 0u; i < 4u; i++) {
+            let sub = primary_ray(center + offsets[i], vec2<f32>(dims));
+            rgb += shade(trace(sub.origin, sub.dir), sub.dir);
+        }
+        rgb /= 5.0;
+    }
+    var color = vec4<f32>(rgb, 1.0);
+
+    if (params.frame_stats > 0.5) {
+        var outcome = 4u;
+        if (hit_black_hole) {
+            outcome = 0u;
+        } else if (hit_disk) {
+            outcome = 1u;
+        } else if (hit_planet) {
+            outcome = 2u;
+        } else if (traced.escaped) {
+            outcome = 3u;
+        }
+        atomicAdd(&ray_stats[outcome], 1u);
+        // r is in units of r_s / 2 here
+        let closest_rs = min(0.5 * traced.min_r, STATS_MAX_RADIUS_RS);
+        atomicAdd(&ray_stats[5], u32(closest_rs * 10.0));
+    }
+
+    if (params.critical_curve_color.w > 0.5 && camera.projection < 1.5) {
+        let coverage = critical_curve_coverage(center, vec2<f32>(dims), hit_black_hole);
+        color = vec4<f32>(mix(color.rgb, params.critical_curve_color.rgb, coverage), 1.0);
+    }
+
+    if (pulses.count > 0.0 && camera.projection < 1.5) {
+        let glow = pulse_glow(center, vec2<f32>(dims));
+        color = vec4<f32>(min(color.rgb + glow, vec3<f32>(1.0)), 1.0);
     }
 
     textureStore(output_texture, vec2<i32>(pix), color);
+
+    if (params.lens_map > 0.5 && camera.projection < 1.5) {
+        var lens_uv = vec2<f32>(LENS_MAP_NONE);
+        if (!hit_black_hole && !hit_disk && !hit_planet) {
+            lens_uv = direction_to_uv(params.background_rotation * aberrate(normalize(pos)));
+        }
+        textureStore(lens_texture, vec2<i32>(pix), vec4<f32>(lens_uv, 0.0, 0.0));
+    }
+
+    if (motion.enabled > 0.5 && camera.projection < 1.5) {
+        // Where the thing this pixel landed on was last frame. The hole's
+        // shadow moves with its center; the planet with its orbit.
+        var world = vec4<f32>(normalize(pos), 0.0);
+        if (hit_black_hole) {
+            world = vec4<f32>(0.0, 0.0, 0.0, 1.0);
+        } else if (hit_planet) {
+            let surface = planet.position + traced.planet_normal * planet.radius;
+            world = vec4<f32>(surface - motion.planet_delta, 1.0);
+        } else if (hit_disk) {
+            world = vec4<f32>(pos * unit_scale, 1.0);
+        }
+        let previous = previous_pixel(world, vec2<f32>(dims));
+        let offset = select(vec2<f32>(0.0), previous.xy - center, previous.z > 0.5);
+        textureStore(motion_texture, vec2<i32>(pix), vec4<f32>(offset, previous.z, 0.0));
+    }
 }
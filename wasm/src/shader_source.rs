@@ -0,0 +1,119 @@
+//! Checks for user-supplied compute shaders, so a replacement for
+//! `shader.wgsl` can be rejected with a readable message before it reaches
+//! the GPU instead of producing an invalid pipeline.
+
+use naga::valid::{Capabilities, ValidationFlags, Validator};
+use naga::{AddressSpace, ImageClass, ShaderStage, StorageAccess, TypeInner};
+
+/// The resources a compute shader can bind: everything in group 0 of the
+/// compute bind group layout, by binding index.
+const COMPUTE_BINDINGS: [(u32, BindingKind); 12] = [
+    (0, BindingKind::StorageTexture),
+    (1, BindingKind::Uniform),
+    (2, BindingKind::Uniform),
+    (3, BindingKind::Uniform),
+    (4, BindingKind::Texture),
+    (5, BindingKind::Uniform),
+    (6, BindingKind::ReadOnlyStorage),
+    (7, BindingKind::StorageTexture),
+    (8, BindingKind::Uniform),
+    (9, BindingKind::ReadOnlyStorage),
+    (10, BindingKind::Storage),
+    (11, BindingKind::StorageTexture),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BindingKind {
+    Uniform,
+    ReadOnlyStorage,
+    Storage,
+    Texture,
+    StorageTexture,
+    Other,
+}
+
+impl BindingKind {
+    fn of(module: &naga::Module, global: &naga::GlobalVariable) -> Self {
+        match global.space {
+            AddressSpace::Uniform => BindingKind::Uniform,
+            AddressSpace::Storage { access } if access.contains(StorageAccess::STORE) => {
+                BindingKind::Storage
+            }
+            AddressSpace::Storage { .. } => BindingKind::ReadOnlyStorage,
+            AddressSpace::Handle => match module.types[global.ty].inner {
+                TypeInner::Image {
+                    class: ImageClass::Storage { .. },
+                    ..
+                } => BindingKind::StorageTexture,
+                TypeInner::Image { .. } => BindingKind::Texture,
+                _ => BindingKind::Other,
+            },
+            _ => BindingKind::Other,
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            BindingKind::Uniform => "a uniform buffer",
+            BindingKind::ReadOnlyStorage => "a read-only storage buffer",
+            BindingKind::Storage => "a read-write storage buffer",
+            BindingKind::Texture => "a sampled texture",
+            BindingKind::StorageTexture => "a storage texture",
+            BindingKind::Other => "an unsupported resource",
+        }
+    }
+}
+
+/// Parses and validates `source` as a replacement compute shader. It must
+/// have a compute entry point `main`, an `NSTEPS` override for the step
+/// budget, and bind only resources from the compute bind group layout, each
+/// as the same kind of resource. Errors are formatted for display.
+pub fn validate_compute_shader(source: &str) -> Result<(), String> {
+    let module = naga::front::wgsl::parse_str(source).map_err(|e| e.emit_to_string(source))?;
+    Validator::new(ValidationFlags::all(), Capabilities::default())
+        .validate(&module)
+        .map_err(|e| e.emit_to_string(source))?;
+
+    if !module
+        .entry_points
+        .iter()
+        .any(|entry| entry.name == "main" && entry.stage == ShaderStage::Compute)
+    {
+        return Err("Shader has no compute entry point named `main`".to_string());
+    }
+    if !module
+        .overrides
+        .iter()
+        .any(|(_, o)| o.name.as_deref() == Some("NSTEPS"))
+    {
+        return Err("Shader must declare `override NSTEPS: u32`".to_string());
+    }
+
+    for (_, global) in module.global_variables.iter() {
+        let Some(binding) = &global.binding else {
+            continue;
+        };
+        let name = global.name.as_deref().unwrap_or("<unnamed>");
+        let expected = COMPUTE_BINDINGS
+            .iter()
+            .find(|(index, _)| binding.group == 0 && *index == binding.binding)
+            .map(|(_, kind)| *kind)
+            .ok_or_else(|| {
+                format!(
+                    "`{}` is bound at @group({}) @binding({}), which the compute layout does not have",
+                    name, binding.group, binding.binding
+                )
+            })?;
+        let found = BindingKind::of(&module, global);
+        if found != expected {
+            return Err(format!(
+                "`{}` at @binding({}) is {}, but the compute layout expects {}",
+                name,
+                binding.binding,
+                found.describe(),
+                expected.describe()
+            ));
+        }
+    }
+    Ok(())
+}
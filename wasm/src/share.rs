@@ -0,0 +1,137 @@
+use crate::camera::{MAX_FOV, MIN_FOV};
+
+/// The scene parameters carried by a shareable link, encoded as a compact
+/// query string such as
+/// `m=4.29e6&az=0.5&el=1.66&r=1.67e11&fov=60&di=2.79e10&do=6.6e10`. Mass is
+/// in solar masses, the camera angles in radians, the field of view in
+/// degrees and lengths in meters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ViewParams {
+    pub solar_masses: f64,
+    pub azimuth: f32,
+    pub elevation: f32,
+    pub radius: f32,
+    pub fov: f32,
+    pub disk_inner_radius: f32,
+    pub disk_outer_radius: f32,
+}
+
+impl ViewParams {
+    pub fn to_query(&self) -> String {
+        format!(
+            "m={:e}&az={}&el={}&r={:e}&fov={}&di={:e}&do={:e}",
+            self.solar_masses,
+            self.azimuth,
+            self.elevation,
+            self.radius,
+            self.fov,
+            self.disk_inner_radius,
+            self.disk_outer_radius
+        )
+    }
+
+    /// Parses a query string, with or without a leading `?` or `#`. Keys that
+    /// are missing keep their value from `base`; unknown keys are ignored so
+    /// links stay readable by older builds.
+    pub fn from_query(query: &str, base: ViewParams) -> Result<Self, String> {
+        let mut params = base;
+        let query = query.trim_start_matches(['?', '#']);
+
+        for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or_else(|| format!("Expected key=value, got '{}'", pair))?;
+            let parsed = || {
+                value
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|v| v.is_finite())
+                    .ok_or_else(|| format!("Invalid number for '{}': '{}'", key, value))
+            };
+            match key {
+                "m" => params.solar_masses = parsed()?,
+                "az" => params.azimuth = parsed()? as f32,
+                "el" => params.elevation = parsed()? as f32,
+                "r" => params.radius = parsed()? as f32,
+                "fov" => params.fov = parsed()? as f32,
+                "di" => params.disk_inner_radius = parsed()? as f32,
+                "do" => params.disk_outer_radius = parsed()? as f32,
+                _ => {}
+            }
+        }
+
+        if params.solar_masses <= 0.0 {
+            return Err(format!(
+                "Black hole mass must be positive, got {} solar masses",
+                params.solar_masses
+            ));
+        }
+        if params.radius <= 0.0 {
+            return Err(format!(
+                "Camera radius must be positive, got {}",
+                params.radius
+            ));
+        }
+        if !(MIN_FOV..=MAX_FOV).contains(&params.fov) {
+            return Err(format!(
+                "Field of view must be between {} and {} degrees, got {}",
+                MIN_FOV, MAX_FOV, params.fov
+            ));
+        }
+        if params.disk_inner_radius <= 0.0 || params.disk_outer_radius <= params.disk_inner_radius {
+            return Err(format!(
+                "Disk radii must satisfy 0 < inner < outer, got {} and {}",
+                params.disk_inner_radius, params.disk_outer_radius
+            ));
+        }
+        Ok(params)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn base() -> ViewParams {
+        ViewParams {
+            solar_masses: 4.29e6,
+            azimuth: 0.5,
+            elevation: 1.66,
+            radius: 1.67e11,
+            fov: 60.0,
+            disk_inner_radius: 2.79e10,
+            disk_outer_radius: 6.6e10,
+        }
+    }
+
+    #[test]
+    fn query_round_trips_mass_and_fov() {
+        let view = ViewParams {
+            solar_masses: 6.5e9,
+            fov: 35.0,
+            ..base()
+        };
+        let parsed = ViewParams::from_query(&view.to_query(), base()).unwrap();
+        assert_eq!(parsed.solar_masses, view.solar_masses);
+        assert_eq!(parsed.fov, view.fov);
+        assert_eq!(parsed, view);
+    }
+
+    #[test]
+    fn missing_keys_keep_the_base_values() {
+        let parsed = ViewParams::from_query("#fov=90", base()).unwrap();
+        assert_eq!(
+            parsed,
+            ViewParams {
+                fov: 90.0,
+                ..base()
+            }
+        );
+    }
+
+    #[test]
+    fn out_of_range_mass_and_fov_are_rejected() {
+        assert!(ViewParams::from_query("m=0", base()).is_err());
+        assert!(ViewParams::from_query("fov=180", base()).is_err());
+    }
+}
@@ -0,0 +1,76 @@
+use crate::integrator::TraceResult;
+use std::cell::Cell;
+
+/// Default fraction of CPU-traced rays allowed to exhaust the step budget
+/// before a warning is logged.
+pub const DEFAULT_MAX_STEPS_WARNING_FRACTION: f64 = 0.05;
+/// Rays counted before the fraction is judged, so a handful of probes can't
+/// trigger a warning on their own.
+const MIN_WINDOW_RAYS: u32 = 64;
+/// Shortest gap between two warnings, in seconds.
+const WARNING_INTERVAL_S: f64 = 10.0;
+
+/// Counts how CPU-traced rays end and reports when too many of them run out
+/// of steps instead of being captured or escaping. Counters are cells so
+/// tracing from `&self` methods can record into it.
+#[derive(Debug, Clone)]
+pub struct StepBudgetMonitor {
+    threshold: f64,
+    traced: Cell<u32>,
+    exhausted: Cell<u32>,
+    last_warning: Cell<Option<f64>>,
+}
+
+impl Default for StepBudgetMonitor {
+    fn default() -> Self {
+        StepBudgetMonitor {
+            threshold: DEFAULT_MAX_STEPS_WARNING_FRACTION,
+            traced: Cell::new(0),
+            exhausted: Cell::new(0),
+            last_warning: Cell::new(None),
+        }
+    }
+}
+
+impl StepBudgetMonitor {
+    pub fn threshold(&self) -> f64 {
+        self.threshold
+    }
+
+    /// Sets the fraction of rays, in `[0, 1]`, above which `check` reports.
+    pub fn set_threshold(&mut self, fraction: f64) {
+        self.threshold = fraction;
+    }
+
+    pub fn record(&self, result: TraceResult) {
+        self.traced.set(self.traced.get().saturating_add(1));
+        if result == TraceResult::MaxSteps {
+            self.exhausted.set(self.exhausted.get().saturating_add(1));
+        }
+    }
+
+    /// Once enough rays have been recorded, returns the fraction that ran
+    /// out of steps if it is over the threshold and no warning was given in
+    /// the last `WARNING_INTERVAL_S`, then starts a new count. `now` is in
+    /// seconds.
+    pub fn check(&self, now: f64) -> Option<f64> {
+        let traced = self.traced.get();
+        if traced < MIN_WINDOW_RAYS {
+            return None;
+        }
+        let fraction = self.exhausted.get() as f64 / traced as f64;
+        self.traced.set(0);
+        self.exhausted.set(0);
+
+        let due = self
+            .last_warning
+            .get()
+            .is_none_or(|last| now - last >= WARNING_INTERVAL_S);
+        if fraction > self.threshold && due {
+            self.last_warning.set(Some(now));
+            Some(fraction)
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,62 @@
+/// Events kept while nobody drains them; older ones are dropped first.
+const MAX_PENDING_EVENTS: usize = 64;
+
+#[derive(Debug, Clone)]
+struct Threshold {
+    name: String,
+    radius: f64,
+}
+
+/// Watches a radius (the camera's) against named threshold radii and queues
+/// an event string each time one is crossed, e.g. `"photon_sphere:inward"`.
+/// An event fires once per crossing, not once per frame spent inside.
+#[derive(Debug, Clone, Default)]
+pub struct ThresholdWatcher {
+    thresholds: Vec<Threshold>,
+    last_radius: Option<f64>,
+    events: Vec<String>,
+}
+
+impl ThresholdWatcher {
+    /// Watches `radius` in meters under `name`, replacing any threshold of
+    /// the same name.
+    pub fn add(&mut self, name: &str, radius: f64) {
+        self.thresholds.retain(|t| t.name != name);
+        self.thresholds.push(Threshold {
+            name: name.to_string(),
+            radius,
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.thresholds.clear();
+    }
+
+    /// Records the current radius, queuing an event for every threshold
+    /// between it and the previously observed one.
+    pub fn observe(&mut self, radius: f64) {
+        if let Some(last) = self.last_radius {
+            for threshold in &self.thresholds {
+                let direction = if last >= threshold.radius && radius < threshold.radius {
+                    "inward"
+                } else if last < threshold.radius && radius >= threshold.radius {
+                    "outward"
+                } else {
+                    continue;
+                };
+                self.events
+                    .push(format!("{}:{}", threshold.name, direction));
+            }
+            if self.events.len() > MAX_PENDING_EVENTS {
+                let excess = self.events.len() - MAX_PENDING_EVENTS;
+                self.events.drain(..excess);
+            }
+        }
+        self.last_radius = Some(radius);
+    }
+
+    /// Takes all queued events, oldest first.
+    pub fn drain(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.events)
+    }
+}